@@ -0,0 +1,121 @@
+use std::path::{Path, PathBuf};
+
+pub const APP_NAME: &str = "DeepSearch";
+pub const DB_FILE_NAME: &str = "deepsearch_index.redb";
+/// eframe state file used in portable mode.
+const PORTABLE_STATE_FILE_NAME: &str = "deepsearch_state.ron";
+/// Placing a file with this name next to the executable turns portable mode on.
+const PORTABLE_MARKER_FILE_NAME: &str = "deepsearch.portable";
+
+/// An index database that an older release left in the working directory, brought over to the
+/// default location by `LaunchOptions::resolve_db_path`.
+#[derive(Debug)]
+pub struct LegacyDbMove {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    /// The file could not be moved (for example across drives) and was copied instead, so the
+    /// original is still there.
+    pub copied: bool,
+}
+
+/// Options given on the command line.
+///
+/// `--db <path>` (or `--db=<path>`) opens a specific index database.
+/// `--portable` keeps the database and the GUI state next to the executable.
+#[derive(Debug, Default)]
+pub struct LaunchOptions {
+    pub db_path: Option<PathBuf>,
+    pub portable: bool,
+}
+
+impl LaunchOptions {
+    /// Parses the process arguments. Unknown arguments are ignored.
+    pub fn from_args() -> Self {
+        let mut options = Self::default();
+        let mut args = std::env::args_os().skip(1);
+        while let Some(arg) = args.next() {
+            let arg_str = arg.to_string_lossy();
+            if arg_str == "--portable" {
+                options.portable = true;
+            } else if arg_str == "--db" {
+                options.db_path = args.next().map(PathBuf::from);
+            } else if let Some(path) = arg_str.strip_prefix("--db=") {
+                options.db_path = Some(PathBuf::from(path));
+            }
+        }
+
+        // Portable mode can also be enabled once and for all with a marker file.
+        if !options.portable {
+            options.portable = executable_dir().is_some_and(|dir| dir.join(PORTABLE_MARKER_FILE_NAME).exists());
+        }
+        options
+    }
+
+    /// In portable mode, the eframe state file lives next to the executable.
+    pub fn persistence_path(&self) -> Option<PathBuf> {
+        if self.portable {
+            executable_dir().map(|dir| dir.join(PORTABLE_STATE_FILE_NAME))
+        } else {
+            None
+        }
+    }
+
+    /// Picks the index database: the command-line flag wins over the path chosen in the settings,
+    /// which wins over the default location (next to the executable in portable mode, otherwise
+    /// the per-user data directory). Also returns the legacy index moved there, if any.
+    pub fn resolve_db_path(&self, setting: Option<&Path>) -> (PathBuf, Option<LegacyDbMove>) {
+        if let Some(path) = &self.db_path {
+            return (path.clone(), None);
+        }
+        if let Some(path) = setting {
+            return (path.to_path_buf(), None);
+        }
+        self.default_db_path()
+    }
+
+    /// Older releases kept the index in the working directory. When the default location has no
+    /// index yet, such a file is moved there once; if that fails, it stays in use where it is.
+    fn default_db_path(&self) -> (PathBuf, Option<LegacyDbMove>) {
+        let dir = if self.portable {
+            executable_dir()
+        } else {
+            eframe::storage_dir(APP_NAME)
+        };
+        let legacy_path = PathBuf::from(DB_FILE_NAME);
+        let Some(path) = dir.map(|dir| dir.join(DB_FILE_NAME)) else {
+            return (legacy_path, None);
+        };
+        if path.exists() || !legacy_path.is_file() {
+            return (path, None);
+        }
+
+        let from = std::path::absolute(&legacy_path).unwrap_or_else(|_| legacy_path.clone());
+        match move_file(&legacy_path, &path) {
+            Ok(copied) => {
+                let legacy_move = LegacyDbMove { from, to: path.clone(), copied };
+                (path, Some(legacy_move))
+            }
+            Err(_) => (legacy_path, None),
+        }
+    }
+}
+
+/// Moves `from` to `to`, copying it when a rename is not possible. Returns whether it was copied.
+fn move_file(from: &Path, to: &Path) -> std::io::Result<bool> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(false);
+    }
+    if let Err(e) = std::fs::copy(from, to) {
+        // Never leave a truncated index behind.
+        std::fs::remove_file(to).ok();
+        return Err(e);
+    }
+    Ok(true)
+}
+
+fn executable_dir() -> Option<PathBuf> {
+    std::env::current_exe().ok()?.parent().map(Path::to_path_buf)
+}
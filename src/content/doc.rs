@@ -0,0 +1,291 @@
+use std::io::Read;
+use std::ops::Range;
+use std::path::Path;
+use super::{ContentExtractor, ContentKind, Segments};
+use crate::db::{ContentSegment, SegmentLocation};
+
+/// Identifies a Word 97-2003 `WordDocument` stream.
+const WORD_IDENT: u16 = 0xA5EC;
+/// The text is kept in `1Table` instead of `0Table`.
+const FLAG_WHICH_TABLE: u16 = 0x0200;
+const FLAG_ENCRYPTED: u16 = 0x0100;
+/// Position of `fcClx`/`lcbClx` among the offset/length pairs of the FIB.
+const CLX_PAIR_INDEX: usize = 33;
+
+/// Legacy Word 97-2003 documents: one segment per paragraph, located by paragraph number like
+/// DOCX. Each table cell counts as a paragraph. Encrypted and pre-97 files are not supported.
+pub struct DocExtractor;
+
+impl ContentExtractor for DocExtractor {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["doc"]
+    }
+
+    fn mime_types(&self) -> &'static [&'static str] {
+        &["application/msword"]
+    }
+
+    fn kind(&self) -> ContentKind {
+        ContentKind::Office
+    }
+
+    fn extract(&self, path: &Path) -> anyhow::Result<Segments> {
+        let mut file = cfb::open(path)?;
+        let mut word_document = Vec::new();
+        file.open_stream("/WordDocument")?.read_to_end(&mut word_document)?;
+        let fib = parse_fib(&word_document)?;
+        let mut table = Vec::new();
+        file.open_stream(fib.table_stream)?.read_to_end(&mut table)?;
+        let clx = table.get(fib.clx).ok_or_else(|| anyhow::anyhow!("Truncated piece table"))?;
+
+        let text = piece_text(&word_document, clx)?;
+        let segments = (1..)
+            .zip(paragraphs(&text))
+            .filter(|(_, text)| !text.is_empty())
+            .map(|(number, text)| ContentSegment { location: SegmentLocation::Paragraph(number), text });
+        Ok(Box::new(segments))
+    }
+}
+
+/// What the File Information Block (FIB) at the start of the `WordDocument` stream says about
+/// where the text is described.
+#[derive(Debug, PartialEq)]
+struct Fib {
+    /// The table stream holding the piece table.
+    table_stream: &'static str,
+    /// Byte range of the piece table (`Clx`) in the table stream.
+    clx: Range<usize>,
+}
+
+fn parse_fib(word_document: &[u8]) -> anyhow::Result<Fib> {
+    if u16_at(word_document, 0)? != WORD_IDENT {
+        anyhow::bail!("Not a Word 97-2003 document");
+    }
+    let flags = u16_at(word_document, 0x0A)?;
+    if flags & FLAG_ENCRYPTED != 0 {
+        anyhow::bail!("Encrypted Word documents are not supported");
+    }
+    let table_stream = if flags & FLAG_WHICH_TABLE == 0 { "/0Table" } else { "/1Table" };
+
+    // The FIB is a fixed header followed by three arrays, each prefixed with its length.
+    let short_count = usize::from(u16_at(word_document, 0x20)?);
+    let long_count_offset = 0x22 + short_count * 2;
+    let long_count = usize::from(u16_at(word_document, long_count_offset)?);
+    let fc_lcb_offset = long_count_offset + 2 + long_count * 4 + 2;
+    let clx_offset = u32_at(word_document, fc_lcb_offset + CLX_PAIR_INDEX * 8)? as usize;
+    let clx_len = u32_at(word_document, fc_lcb_offset + CLX_PAIR_INDEX * 8 + 4)? as usize;
+    let clx_end = clx_offset.checked_add(clx_len).ok_or_else(|| anyhow::anyhow!("Truncated piece table"))?;
+    Ok(Fib { table_stream, clx: clx_offset..clx_end })
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> anyhow::Result<u16> {
+    let bytes = bytes.get(offset..offset + 2).ok_or_else(|| anyhow::anyhow!("Truncated Word document"))?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> anyhow::Result<u32> {
+    let bytes = bytes.get(offset..offset + 4).ok_or_else(|| anyhow::anyhow!("Truncated Word document"))?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Joins the text pieces listed in the piece table (`Clx`). A piece is stored either as
+/// UTF-16 or "compressed" as one Windows-1252 byte per character.
+fn piece_text(word_document: &[u8], clx: &[u8]) -> anyhow::Result<String> {
+    // Skip the formatting (`Prc`) entries that precede the piece table (`Pcdt`).
+    let mut offset = 0;
+    while clx.get(offset) == Some(&0x01) {
+        offset += 3 + usize::from(u16_at(clx, offset + 1)?);
+    }
+    if clx.get(offset) != Some(&0x02) {
+        anyhow::bail!("Piece table not found");
+    }
+    let plc_len = u32_at(clx, offset + 1)? as usize;
+    let plc = clx.get(offset + 5..offset + 5 + plc_len).ok_or_else(|| anyhow::anyhow!("Truncated piece table"))?;
+
+    // n + 1 character positions followed by n 8-byte piece descriptors.
+    let pieces = plc_len.saturating_sub(4) / 12;
+    let mut text = String::new();
+    for i in 0..pieces {
+        let start = u32_at(plc, i * 4)? as usize;
+        let end = u32_at(plc, (i + 1) * 4)? as usize;
+        let chars = end.saturating_sub(start);
+        let fc = u32_at(plc, (pieces + 1) * 4 + i * 8 + 2)?;
+        if fc & 0x4000_0000 == 0 {
+            let offset = fc as usize;
+            let bytes = word_document.get(offset..offset + chars * 2).ok_or_else(|| anyhow::anyhow!("Truncated text piece"))?;
+            let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+            text.push_str(&String::from_utf16_lossy(&units));
+        } else {
+            let offset = (fc & 0x3FFF_FFFF) as usize / 2;
+            let bytes = word_document.get(offset..offset + chars).ok_or_else(|| anyhow::anyhow!("Truncated text piece"))?;
+            text.push_str(&encoding_rs::WINDOWS_1252.decode_without_bom_handling(bytes).0);
+        }
+    }
+    Ok(text)
+}
+
+/// Splits document text at paragraph, cell and section marks, dropping field codes and other
+/// control characters. Empty paragraphs are kept so that the numbers match the document.
+fn paragraphs(text: &str) -> Vec<String> {
+    let mut paragraphs = Vec::new();
+    let mut paragraph = String::new();
+    // One entry per open field: whether its result (rather than its code) is being read.
+    let mut fields: Vec<bool> = Vec::new();
+    for c in text.chars() {
+        match c {
+            '\r' | '\x07' | '\x0C' => paragraphs.push(std::mem::take(&mut paragraph).trim().to_string()),
+            '\x13' => fields.push(false),
+            '\x14' => {
+                if let Some(showing_result) = fields.last_mut() {
+                    *showing_result = true;
+                }
+            }
+            '\x15' => {
+                fields.pop();
+            }
+            _ if !fields.iter().all(|showing_result| *showing_result) => {}
+            '\x0B' | '\t' => paragraph.push(' '),
+            '\x1E' => paragraph.push('-'),
+            c if c.is_control() => {}
+            c => paragraph.push(c),
+        }
+    }
+    paragraphs.push(paragraph.trim().to_string());
+    paragraphs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// Bit of a piece's file offset marking it as one byte per character.
+    const COMPRESSED: u32 = 0x4000_0000;
+
+    /// A FIB with the usual Word 97 array lengths whose `Clx` is at `clx`.
+    fn fib(flags: u16, clx: Range<u32>) -> Vec<u8> {
+        let mut bytes = vec![0; 0x22 + 14 * 2 + 2 + 22 * 4 + 2 + 93 * 8];
+        bytes[0..2].copy_from_slice(&WORD_IDENT.to_le_bytes());
+        bytes[0x0A..0x0C].copy_from_slice(&flags.to_le_bytes());
+        bytes[0x20..0x22].copy_from_slice(&14u16.to_le_bytes());
+        bytes[0x3E..0x40].copy_from_slice(&22u16.to_le_bytes());
+        bytes[0x98..0x9A].copy_from_slice(&93u16.to_le_bytes());
+        let pair = 0x9A + CLX_PAIR_INDEX * 8;
+        bytes[pair..pair + 4].copy_from_slice(&clx.start.to_le_bytes());
+        bytes[pair + 4..pair + 8].copy_from_slice(&(clx.end - clx.start).to_le_bytes());
+        bytes
+    }
+
+    /// A `Clx` with one `Prc` entry and a piece table of `(characters, fc)` pieces.
+    fn clx(pieces: &[(u32, u32)]) -> Vec<u8> {
+        let mut plc = Vec::new();
+        let mut position = 0u32;
+        plc.extend_from_slice(&position.to_le_bytes());
+        for (chars, _) in pieces {
+            position += chars;
+            plc.extend_from_slice(&position.to_le_bytes());
+        }
+        for (_, fc) in pieces {
+            plc.extend_from_slice(&[0, 0]);
+            plc.extend_from_slice(&fc.to_le_bytes());
+            plc.extend_from_slice(&[0, 0]);
+        }
+        let mut bytes = vec![0x01, 2, 0, 0xAA, 0xBB, 0x02];
+        bytes.extend_from_slice(&u32::try_from(plc.len()).unwrap().to_le_bytes());
+        bytes.extend_from_slice(&plc);
+        bytes
+    }
+
+    /// A `WordDocument` stream whose text is "Hello" stored compressed at 0x200, then
+    /// "Thành\r\rlast" stored as UTF-16 at 0x300, along with the matching `Clx`.
+    fn document() -> (Vec<u8>, Vec<u8>) {
+        let mut word_document = fib(0, 0..0);
+        word_document.resize(0x300, 0);
+        word_document[0x200..0x205].copy_from_slice(b"Hello");
+        word_document.extend("Thành\r\rlast".encode_utf16().flat_map(u16::to_le_bytes));
+        (word_document, clx(&[(5, COMPRESSED | 0x400), (11, 0x300)]))
+    }
+
+    #[test]
+    fn fib_locates_the_piece_table() {
+        assert_eq!(parse_fib(&fib(0, 0x10..0x30)).unwrap(), Fib { table_stream: "/0Table", clx: 0x10..0x30 });
+        assert_eq!(parse_fib(&fib(FLAG_WHICH_TABLE, 0..4)).unwrap().table_stream, "/1Table");
+    }
+
+    #[test]
+    fn fib_rejects_other_encrypted_and_truncated_documents() {
+        let mut other = fib(0, 0..4);
+        other[0] = 0;
+        assert!(parse_fib(&other).unwrap_err().to_string().contains("Not a Word 97-2003 document"));
+        assert!(parse_fib(&fib(FLAG_ENCRYPTED, 0..4)).unwrap_err().to_string().contains("Encrypted"));
+
+        // Only the pairs up to the `Clx` one are read.
+        let complete = fib(0, 0..4);
+        let needed = 0x9A + (CLX_PAIR_INDEX + 1) * 8;
+        for len in 0..needed {
+            assert!(parse_fib(&complete[..len]).is_err(), "{len} bytes");
+        }
+        assert!(parse_fib(&complete[..needed]).is_ok());
+        let mut huge_arrays = complete;
+        huge_arrays[0x20..0x22].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert!(parse_fib(&huge_arrays).unwrap_err().to_string().contains("Truncated"));
+    }
+
+    #[test]
+    fn pieces_are_decoded_by_their_storage() {
+        let (word_document, clx) = document();
+        assert_eq!(piece_text(&word_document, &clx).unwrap(), "HelloThành\r\rlast");
+    }
+
+    #[test]
+    fn malformed_piece_tables_are_rejected() {
+        let (word_document, clx) = document();
+        for len in 0..clx.len() {
+            assert!(piece_text(&word_document, &clx[..len]).is_err(), "{len} bytes");
+        }
+
+        let mut no_pcdt = clx.clone();
+        no_pcdt[5] = 0x03;
+        assert!(piece_text(&word_document, &no_pcdt).unwrap_err().to_string().contains("not found"));
+
+        let beyond_document = self::clx(&[(10, 0x1000_0000)]);
+        assert!(piece_text(&word_document, &beyond_document).unwrap_err().to_string().contains("Truncated text piece"));
+
+        // A piece ending before it starts holds no text rather than failing.
+        let mut backwards = self::clx(&[(5, COMPRESSED | 0x400)]);
+        backwards[10..14].copy_from_slice(&9u32.to_le_bytes());
+        assert_eq!(piece_text(&word_document, &backwards).unwrap(), "");
+    }
+
+    #[test]
+    fn paragraphs_keep_field_results_and_empty_paragraphs() {
+        let text = "See \x13 HYPERLINK \"https://example.com\" \x14the \x13 PAGE \x14site\x15\x15 now\rA\tB\x0Bnon\x1Ebreaking\rcell 1\x07cell 2\x07\x07\x0C";
+        assert_eq!(paragraphs(text), ["See the site now", "A B non-breaking", "cell 1", "cell 2", "", "", ""]);
+        assert_eq!(paragraphs("\x13 unclosed field"), [""]);
+    }
+
+    #[test]
+    fn extracts_numbered_paragraphs() {
+        let (mut word_document, clx) = document();
+        // The piece table sits after some other data in the table stream.
+        let mut table = vec![0; 16];
+        table.extend_from_slice(&clx);
+        let pair = 0x9A + CLX_PAIR_INDEX * 8;
+        word_document[pair..pair + 4].copy_from_slice(&16u32.to_le_bytes());
+        word_document[pair + 4..pair + 8].copy_from_slice(&u32::try_from(clx.len()).unwrap().to_le_bytes());
+
+        let path = std::env::temp_dir().join(format!("deepsearch_test_{}.doc", std::process::id()));
+        let mut file = cfb::create(&path).unwrap();
+        file.create_stream("/WordDocument").unwrap().write_all(&word_document).unwrap();
+        file.create_stream("/0Table").unwrap().write_all(&table).unwrap();
+        file.flush().unwrap();
+        drop(file);
+
+        let segments: Vec<_> = DocExtractor.extract(&path).unwrap().collect();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(segments, [
+            ContentSegment { location: SegmentLocation::Paragraph(1), text: "HelloThành".to_string() },
+            ContentSegment { location: SegmentLocation::Paragraph(3), text: "last".to_string() },
+        ]);
+    }
+}
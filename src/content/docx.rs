@@ -0,0 +1,45 @@
+use std::path::Path;
+use docx_rs::{read_docx, DocumentChild, ParagraphChild, RunChild};
+use super::{ContentExtractor, ContentKind, Segments};
+use crate::db::{ContentSegment, SegmentLocation};
+
+/// One segment per paragraph of the document body, located by paragraph number.
+pub struct DocxExtractor;
+
+impl ContentExtractor for DocxExtractor {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["docx"]
+    }
+
+    fn mime_types(&self) -> &'static [&'static str] {
+        &["application/vnd.openxmlformats-officedocument.wordprocessingml.document"]
+    }
+
+    fn kind(&self) -> ContentKind {
+        ContentKind::Office
+    }
+
+    fn extract(&self, path: &Path) -> anyhow::Result<Segments> {
+        let docx = read_docx(&std::fs::read(path)?)?;
+        let paragraphs = docx.document.children.into_iter().filter_map(|child| match child {
+            DocumentChild::Paragraph(p) => Some(p),
+            _ => None,
+        });
+        // Empty paragraphs keep their number so that the numbers match the document.
+        let segments = (1..).zip(paragraphs).filter_map(|(number, p)| {
+            let mut text = String::new();
+            for p_child in p.children {
+                if let ParagraphChild::Run(r) = p_child {
+                    for r_child in r.children {
+                        if let RunChild::Text(t) = r_child {
+                            text.push_str(&t.text);
+                        }
+                    }
+                }
+            }
+            let text = text.trim();
+            (!text.is_empty()).then(|| ContentSegment { location: SegmentLocation::Paragraph(number), text: text.to_string() })
+        });
+        Ok(Box::new(segments))
+    }
+}
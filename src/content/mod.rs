@@ -0,0 +1,186 @@
+mod doc;
+mod docx;
+mod odf;
+mod package;
+mod pdf;
+mod pptx;
+mod registry;
+mod spreadsheet;
+mod text;
+
+pub use registry::{extractors, install_extractors, ContentExtractor, ExtractorRegistry, Segments};
+
+use std::ops::Range;
+use std::path::Path;
+use crate::db::{ContentSegment, SegmentLocation};
+use crate::utils;
+
+/// The content-type groups that the user can include or exclude from content search.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContentKind {
+    Pdf,
+    Office,
+    PlainText,
+}
+
+/// Lines shown before and after each content hit unless the user chooses otherwise.
+pub const DEFAULT_CONTEXT_LINES: usize = 2;
+
+/// Compares document text with a content search keyword. By default every word of the keyword
+/// must appear in the text after both went through `utils::normalize_string`, like file names;
+/// the exact mode looks for the keyword as typed, case and accents included.
+#[derive(Clone, Debug)]
+pub struct ContentMatcher {
+    exact: bool,
+    keyword: String,
+    /// Normalized words of the keyword; empty in exact mode.
+    words: Vec<String>,
+}
+
+impl ContentMatcher {
+    pub fn new(keyword: &str, exact: bool) -> Self {
+        let words = if exact {
+            Vec::new()
+        } else {
+            utils::normalize_string(keyword).split_whitespace().map(ToString::to_string).collect()
+        };
+        Self { exact, keyword: keyword.to_string(), words }
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        if self.exact {
+            return !self.keyword.is_empty() && text.contains(&self.keyword);
+        }
+        if self.words.is_empty() {
+            return false;
+        }
+        let normalized = utils::normalize_string(text);
+        self.words.iter().all(|word| normalized.contains(word.as_str()))
+    }
+
+    /// Byte ranges of `text` that match: the keyword in exact mode, otherwise every occurrence of
+    /// each word, found in the normalized text and mapped back to the original characters.
+    /// Overlapping ranges are merged.
+    pub fn match_ranges(&self, text: &str) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = if self.exact {
+            if self.keyword.is_empty() {
+                return Vec::new();
+            }
+            text.match_indices(&self.keyword).map(|(start, found)| start..start + found.len()).collect()
+        } else {
+            // `origins[i]`: the original characters that byte `i` of `normalized` comes from.
+            let mut normalized = String::new();
+            let mut origins: Vec<Range<usize>> = Vec::new();
+            for (start, c) in text.char_indices() {
+                let origin = start..start + c.len_utf8();
+                // Runs of whitespace collapse into one space, as in `normalize_string`.
+                if c.is_whitespace() && (normalized.is_empty() || normalized.ends_with(' ')) {
+                    continue;
+                }
+                let replacement = if c.is_whitespace() { " ".to_string() } else { utils::normalize_char(c) };
+                normalized.push_str(&replacement);
+                origins.extend(std::iter::repeat_n(origin, replacement.len()));
+            }
+            self.words
+                .iter()
+                .flat_map(|word| normalized.match_indices(word.as_str()).map(|(start, found)| (start, found.len())))
+                .map(|(start, len)| origins[start].start..origins[start + len - 1].end)
+                .collect()
+        };
+
+        ranges.sort_by_key(|range| range.start);
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        merged
+    }
+}
+
+/// A segment of a document that matches a content search, with the segments around it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContentMatch {
+    /// Page, paragraph, slide, row or line of the hit.
+    pub location: SegmentLocation,
+    pub text: String,
+    /// Byte ranges of the matched text in `text`, for highlighting.
+    pub match_ranges: Vec<Range<usize>>,
+    pub context_before: Vec<String>,
+    pub context_after: Vec<String>,
+}
+
+/// Finds every segment of a document that matches, each with up to `context` segments before and
+/// after it.
+pub fn find_hits(segments: &[ContentSegment], matcher: &ContentMatcher, context: usize) -> Vec<ContentMatch> {
+    let texts = |range: Range<usize>| segments[range].iter().map(|segment| segment.text.clone()).collect();
+    segments
+        .iter()
+        .enumerate()
+        .filter(|(_, segment)| matcher.is_match(&segment.text))
+        .map(|(i, segment)| ContentMatch {
+            location: segment.location.clone(),
+            text: segment.text.clone(),
+            match_ranges: matcher.match_ranges(&segment.text),
+            context_before: texts(i.saturating_sub(context)..i),
+            context_after: texts(i + 1..(i + 1 + context).min(segments.len())),
+        })
+        .collect()
+}
+
+/// Maps a lowercase file extension to its content-type group, if its text can be extracted.
+pub fn content_kind(extension: &str) -> Option<ContentKind> {
+    extractors().get(extension).map(ContentExtractor::kind)
+}
+
+/// Extracts the text of a document with the extractor registered for its extension.
+/// Returns `None` when the format is unsupported or the file cannot be read.
+pub fn extract_segments(path: &Path) -> Option<Vec<ContentSegment>> {
+    Some(extractors().for_path(path)?.extract(path).ok()?.collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The parts of `text` highlighted for `keyword`. Slicing panics if a range splits a character.
+    fn highlighted<'a>(keyword: &str, exact: bool, text: &'a str) -> Vec<&'a str> {
+        ContentMatcher::new(keyword, exact).match_ranges(text).into_iter().map(|range| &text[range]).collect()
+    }
+
+    #[test]
+    fn ranges_cover_the_accented_original_text() {
+        assert_eq!(highlighted("thanh", false, "Thành phố"), ["Thành"]);
+        assert_eq!(highlighted("pho", false, "Thành phố"), ["phố"]);
+        assert_eq!(highlighted("thanh pho", false, "Thành phố"), ["Thành", "phố"]);
+        // Decomposed accents belong to the letter they follow.
+        assert_eq!(highlighted("thanh", false, "Tha\u{300}nh phố"), ["Tha\u{300}nh"]);
+    }
+
+    #[test]
+    fn ranges_after_multi_byte_characters_stay_on_boundaries() {
+        assert_eq!(highlighted("thanh", false, "Đường Thành phố"), ["Thành"]);
+        assert_eq!(highlighted("report", false, "報告書 — report"), ["report"]);
+        assert_eq!(highlighted("thanh", false, "Đường  \t Thành"), ["Thành"]);
+    }
+
+    #[test]
+    fn every_hit_of_a_line_is_highlighted() {
+        let text = "Thanh toán ở Thành phố, thanh lý";
+        assert_eq!(highlighted("thanh", false, text), ["Thanh", "Thành", "thanh"]);
+        // Overlapping hits of different words merge into one range.
+        assert_eq!(highlighted("thanh anh", false, text), ["Thanh", "Thành", "thanh"]);
+        assert!(highlighted("hanoi", false, text).is_empty());
+    }
+
+    #[test]
+    fn exact_ranges_match_case_and_accents() {
+        let text = "thành Thành Thanh";
+        assert_eq!(ContentMatcher::new("Thành", true).match_ranges(text), vec![7..13]);
+        assert_eq!(highlighted("Thành", true, text), ["Thành"]);
+        assert!(highlighted("thanh", true, "Thành phố").is_empty());
+        assert!(highlighted("", true, text).is_empty());
+    }
+}
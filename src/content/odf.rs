@@ -0,0 +1,237 @@
+use std::path::Path;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use super::package::{self, TableRow};
+use super::{ContentExtractor, ContentKind, Segments};
+use crate::db::{ContentSegment, SegmentLocation};
+
+/// `OpenDocument` text: one segment per paragraph or heading, located by paragraph number like
+/// DOCX. Table rows count as one paragraph, their cells joined with " | ".
+pub struct OdtExtractor;
+
+impl ContentExtractor for OdtExtractor {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["odt"]
+    }
+
+    fn mime_types(&self) -> &'static [&'static str] {
+        &["application/vnd.oasis.opendocument.text"]
+    }
+
+    fn kind(&self) -> ContentKind {
+        ContentKind::Office
+    }
+
+    fn extract(&self, path: &Path) -> anyhow::Result<Segments> {
+        let segments = blocks(path)?
+            .into_iter()
+            .map(|block| ContentSegment { location: SegmentLocation::Paragraph(block.number), text: block.text });
+        Ok(Box::new(segments))
+    }
+}
+
+/// `OpenDocument` presentations: slide text, table rows and speaker notes, located by slide.
+pub struct OdpExtractor;
+
+impl ContentExtractor for OdpExtractor {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["odp"]
+    }
+
+    fn mime_types(&self) -> &'static [&'static str] {
+        &["application/vnd.oasis.opendocument.presentation"]
+    }
+
+    fn kind(&self) -> ContentKind {
+        ContentKind::Office
+    }
+
+    fn extract(&self, path: &Path) -> anyhow::Result<Segments> {
+        let segments = blocks(path)?
+            .into_iter()
+            .map(|block| ContentSegment { location: SegmentLocation::Slide(block.page), text: block.text });
+        Ok(Box::new(segments))
+    }
+}
+
+/// Most spaces a single `<text:s/>` adds; the count comes from the file and more spaces do not
+/// change what a search finds.
+const MAX_REPEATED_SPACES: usize = 16;
+
+/// A non-empty paragraph or table row of a document body.
+struct Block {
+    /// Number of the `draw:page` (slide) it is on; 0 outside pages.
+    page: u64,
+    /// Position among all paragraphs and table rows, empty ones included.
+    number: u64,
+    text: String,
+}
+
+/// Reads the paragraphs and table rows of a package's `content.xml`, in document order.
+fn blocks(path: &Path) -> anyhow::Result<Vec<Block>> {
+    parse_blocks(&package::read_entry(&mut package::open(path)?, "content.xml")?)
+}
+
+/// Paragraphs nested in another one, such as footnotes, come before their parent and share its
+/// number. Tables nested in a cell are part of that cell's text.
+fn parse_blocks(xml: &str) -> anyhow::Result<Vec<Block>> {
+    let mut reader = Reader::from_str(xml);
+    let decoder = reader.decoder();
+
+    let mut blocks = Vec::new();
+    let (mut page, mut number) = (0, 0);
+    // Open paragraphs and table rows, innermost last.
+    let mut paragraphs: Vec<String> = Vec::new();
+    let mut rows: Vec<TableRow> = Vec::new();
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"page" => page += 1,
+                b"p" | b"h" => paragraphs.push(String::new()),
+                b"table-row" => rows.push(TableRow::default()),
+                b"table-cell" | b"covered-table-cell" => {
+                    if let Some(row) = rows.last_mut() {
+                        row.start_cell();
+                    }
+                }
+                _ => {}
+            },
+            Event::Empty(e) => match e.local_name().as_ref() {
+                b"s" => {
+                    // `<text:s text:c="3"/>` stands for several spaces.
+                    let count = e.try_get_attribute("text:c")?.map(|a| a.decode_and_unescape_value(decoder)).transpose()?;
+                    let count = count.and_then(|count| count.parse().ok()).unwrap_or(1).min(MAX_REPEATED_SPACES);
+                    if let Some(paragraph) = paragraphs.last_mut() {
+                        paragraph.extend(std::iter::repeat_n(' ', count));
+                    }
+                }
+                b"tab" | b"line-break" => {
+                    if let Some(paragraph) = paragraphs.last_mut() {
+                        paragraph.push(' ');
+                    }
+                }
+                b"p" | b"h" if rows.is_empty() => number += 1,
+                b"table-cell" | b"covered-table-cell" => {
+                    if let Some(row) = rows.last_mut() {
+                        row.start_cell();
+                        row.end_cell();
+                    }
+                }
+                _ => {}
+            },
+            Event::Text(e) => {
+                if let Some(paragraph) = paragraphs.last_mut() {
+                    paragraph.push_str(&e.xml_content()?);
+                }
+            }
+            Event::GeneralRef(e) => {
+                if let Some(paragraph) = paragraphs.last_mut() {
+                    paragraph.push_str(&package::reference_text(&e)?);
+                }
+            }
+            Event::End(e) => match e.local_name().as_ref() {
+                b"p" | b"h" => {
+                    let Some(paragraph) = paragraphs.pop() else {
+                        continue;
+                    };
+                    let text = paragraph.trim();
+                    if let Some(row) = rows.last_mut() {
+                        row.add_paragraph(text);
+                    } else {
+                        // Nested paragraphs are located at the paragraph that contains them.
+                        let nested = !paragraphs.is_empty();
+                        if !nested {
+                            number += 1;
+                        }
+                        if !text.is_empty() {
+                            blocks.push(Block { page, number: if nested { number + 1 } else { number }, text: text.to_string() });
+                        }
+                    }
+                }
+                b"table-cell" | b"covered-table-cell" => {
+                    if let Some(row) = rows.last_mut() {
+                        row.end_cell();
+                    }
+                }
+                b"table-row" => {
+                    let Some(row) = rows.pop() else {
+                        continue;
+                    };
+                    let text = row.finish();
+                    if let Some(outer) = rows.last_mut() {
+                        if let Some(text) = text {
+                            outer.add_paragraph(&text);
+                        }
+                    } else {
+                        number += 1;
+                        if let Some(text) = text {
+                            blocks.push(Block { page, number, text });
+                        }
+                    }
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The `(page, number, text)` of the blocks of a document body.
+    fn parse(body: &str) -> Vec<(u64, u64, String)> {
+        let xml = format!("<office:document-content><office:body>{body}</office:body></office:document-content>");
+        parse_blocks(&xml).unwrap().into_iter().map(|block| (block.page, block.number, block.text)).collect()
+    }
+
+    fn block(number: u64, text: &str) -> (u64, u64, String) {
+        (0, number, text.to_string())
+    }
+
+    #[test]
+    fn paragraphs_are_numbered_in_document_order() {
+        let body = "<text:h>Title</text:h><text:p/><text:p>  R&amp;D  </text:p>\
+            <text:p>Body<text:note><text:note-body><text:p>Footnote</text:p></text:note-body></text:note> end</text:p>";
+        assert_eq!(parse(body), [block(1, "Title"), block(3, "R&D"), block(4, "Footnote"), block(4, "Body end")]);
+    }
+
+    #[test]
+    fn table_rows_count_as_one_paragraph() {
+        let body = "<text:p>Before</text:p><table:table>\
+            <table:table-row><table:table-cell><text:p>A</text:p><text:p>a</text:p></table:table-cell>\
+            <table:covered-table-cell/><table:table-cell><text:p>B</text:p></table:table-cell></table:table-row>\
+            <table:table-row><table:table-cell><text:p/></table:table-cell></table:table-row>\
+            </table:table><text:p>After</text:p>";
+        assert_eq!(parse(body), [block(1, "Before"), block(2, "A a |  | B"), block(4, "After")]);
+    }
+
+    #[test]
+    fn repeated_spaces_are_expanded_and_capped() {
+        let body = r#"<text:p>a<text:s text:c="3"/>b<text:tab/>c<text:s/>d<text:line-break/>e</text:p><text:p>x<text:s text:c="1000000"/>y</text:p>"#;
+        let expected = format!("x{}y", " ".repeat(MAX_REPEATED_SPACES));
+        assert_eq!(parse(body), [block(1, "a   b c d e"), block(2, &expected)]);
+    }
+
+    #[test]
+    fn nested_tables_stay_in_their_cell() {
+        let body = "<table:table><table:table-row>\
+            <table:table-cell><text:p>Left</text:p><table:table>\
+            <table:table-row><table:table-cell><text:p>X</text:p></table:table-cell><table:table-cell><text:p>Y</text:p></table:table-cell></table:table-row>\
+            <table:table-row><table:table-cell><text:p>Z</text:p></table:table-cell></table:table-row>\
+            </table:table><text:p>below</text:p></table:table-cell>\
+            <table:table-cell><text:p>Right</text:p></table:table-cell>\
+            </table:table-row></table:table><text:p>After</text:p>";
+        assert_eq!(parse(body), [block(1, "Left X | Y Z below | Right"), block(2, "After")]);
+    }
+
+    #[test]
+    fn presentation_blocks_know_their_slide() {
+        let body = "<draw:page><draw:frame><draw:text-box><text:p>One</text:p></draw:text-box></draw:frame></draw:page>\
+            <draw:page><presentation:notes><text:p>Notes</text:p></presentation:notes></draw:page>";
+        assert_eq!(parse(body), [(1, 1, "One".to_string()), (2, 2, "Notes".to_string())]);
+    }
+}
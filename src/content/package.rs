@@ -0,0 +1,76 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use quick_xml::events::BytesRef;
+use zip::ZipArchive;
+
+/// A zip-based document package: Office Open XML or `OpenDocument`.
+pub type Archive = ZipArchive<BufReader<File>>;
+
+/// Largest uncompressed part that is read. A few kilobytes of zip can expand to gigabytes, so
+/// documents with a larger part are skipped instead.
+const MAX_PART_BYTES: u64 = 64 << 20;
+
+pub fn open(path: &Path) -> anyhow::Result<Archive> {
+    Ok(ZipArchive::new(BufReader::new(File::open(path)?))?)
+}
+
+/// Reads a part of the package as text. Fails for parts larger than `MAX_PART_BYTES`, whatever
+/// size the package declares for them.
+pub fn read_entry(archive: &mut Archive, name: &str) -> anyhow::Result<String> {
+    let too_large = || anyhow::anyhow!("'{name}' is larger than {} MB", MAX_PART_BYTES >> 20);
+    let entry = archive.by_name(name)?;
+    if entry.size() > MAX_PART_BYTES {
+        return Err(too_large());
+    }
+    let mut xml = String::new();
+    entry.take(MAX_PART_BYTES + 1).read_to_string(&mut xml)?;
+    if xml.len() as u64 > MAX_PART_BYTES {
+        return Err(too_large());
+    }
+    Ok(xml)
+}
+
+/// Whether `error` comes from a part missing from the package.
+pub fn is_missing_entry(error: &anyhow::Error) -> bool {
+    matches!(error.downcast_ref::<zip::result::ZipError>(), Some(zip::result::ZipError::FileNotFound))
+}
+
+/// The text of an entity or character reference found in text content, such as `&amp;`.
+pub fn reference_text(reference: &BytesRef) -> anyhow::Result<String> {
+    Ok(quick_xml::escape::unescape(&format!("&{};", reference.decode()?))?.into_owned())
+}
+
+/// Collects the cells of a table row while its XML is read. The paragraphs of a cell are joined
+/// with spaces and the cells with " | ", like spreadsheet rows.
+#[derive(Default)]
+pub struct TableRow {
+    cells: Vec<String>,
+    cell: String,
+}
+
+impl TableRow {
+    pub fn start_cell(&mut self) {
+        self.cell.clear();
+    }
+
+    /// Adds a paragraph to the current cell; empty paragraphs are skipped.
+    pub fn add_paragraph(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        if !self.cell.is_empty() {
+            self.cell.push(' ');
+        }
+        self.cell.push_str(text);
+    }
+
+    pub fn end_cell(&mut self) {
+        self.cells.push(std::mem::take(&mut self.cell));
+    }
+
+    /// The text of the row, or `None` when all its cells are empty.
+    pub fn finish(self) -> Option<String> {
+        self.cells.iter().any(|cell| !cell.is_empty()).then(|| self.cells.join(" | "))
+    }
+}
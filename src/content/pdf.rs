@@ -0,0 +1,31 @@
+use std::path::Path;
+use super::{ContentExtractor, ContentKind, Segments};
+use crate::db::{ContentSegment, SegmentLocation};
+
+/// One segment per non-empty line, located by page.
+pub struct PdfExtractor;
+
+impl ContentExtractor for PdfExtractor {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["pdf"]
+    }
+
+    fn mime_types(&self) -> &'static [&'static str] {
+        &["application/pdf"]
+    }
+
+    fn kind(&self) -> ContentKind {
+        ContentKind::Pdf
+    }
+
+    fn extract(&self, path: &Path) -> anyhow::Result<Segments> {
+        let text_content = pdf_extract::extract_text(path)?;
+        let mut segments = Vec::new();
+        for (page, page_text) in (1..).zip(text_content.split('\x0C')) {
+            for line in page_text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+                segments.push(ContentSegment { location: SegmentLocation::Page(page), text: line.to_string() });
+            }
+        }
+        Ok(Box::new(segments.into_iter()))
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use std::path::Path;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use super::package::{self, Archive, TableRow};
+use super::{ContentExtractor, ContentKind, Segments};
+use crate::db::{ContentSegment, SegmentLocation};
+
+/// Slide text, table rows and speaker notes, located by slide in presentation order. The cells of
+/// a table row are joined with " | ", like spreadsheet rows.
+pub struct PptxExtractor;
+
+impl ContentExtractor for PptxExtractor {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["pptx"]
+    }
+
+    fn mime_types(&self) -> &'static [&'static str] {
+        &["application/vnd.openxmlformats-officedocument.presentationml.presentation"]
+    }
+
+    fn kind(&self) -> ContentKind {
+        ContentKind::Office
+    }
+
+    fn extract(&self, path: &Path) -> anyhow::Result<Segments> {
+        let mut archive = package::open(path)?;
+        let presentation = package::read_entry(&mut archive, "ppt/presentation.xml")?;
+        let slides = relationships(&mut archive, "ppt/presentation.xml")?;
+
+        let mut segments = Vec::new();
+        for (number, id) in (1..).zip(slide_ids(&presentation)?) {
+            let Some((_, slide_path)) = slides.get(&id) else {
+                continue;
+            };
+            let location = SegmentLocation::Slide(number);
+            for text in paragraphs(&package::read_entry(&mut archive, slide_path)?)? {
+                segments.push(ContentSegment { location: location.clone(), text });
+            }
+            let notes_path = relationships(&mut archive, slide_path)?
+                .into_values()
+                .find_map(|(kind, target)| kind.ends_with("/notesSlide").then_some(target));
+            if let Some(notes_path) = notes_path {
+                for text in paragraphs(&package::read_entry(&mut archive, &notes_path)?)? {
+                    segments.push(ContentSegment { location: location.clone(), text });
+                }
+            }
+        }
+        Ok(Box::new(segments.into_iter()))
+    }
+}
+
+/// Reads the relationships of a package part: id → (type, target part path).
+fn relationships(archive: &mut Archive, part: &str) -> anyhow::Result<HashMap<String, (String, String)>> {
+    let (folder, file_name) = part.rsplit_once('/').unwrap_or(("", part));
+    let xml = match package::read_entry(archive, &format!("{folder}/_rels/{file_name}.rels")) {
+        Ok(xml) => xml,
+        Err(e) if package::is_missing_entry(&e) => return Ok(HashMap::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut relationships = HashMap::new();
+    let mut reader = Reader::from_str(&xml);
+    let decoder = reader.decoder();
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"Relationship" => {
+                let attribute = |name: &[u8]| -> anyhow::Result<String> {
+                    Ok(e.try_get_attribute(name)?.map(|a| a.decode_and_unescape_value(decoder)).transpose()?.unwrap_or_default().into_owned())
+                };
+                let (id, kind, target) = (attribute(b"Id")?, attribute(b"Type")?, attribute(b"Target")?);
+                relationships.insert(id, (kind, resolve(folder, &target)));
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(relationships)
+}
+
+/// Resolves a relationship target, relative to the folder of its source part, to a part path.
+fn resolve(folder: &str, target: &str) -> String {
+    let mut parts: Vec<&str> = if target.starts_with('/') { Vec::new() } else { folder.split('/').filter(|p| !p.is_empty()).collect() };
+    for part in target.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// The relationship ids of the slides, in presentation order.
+fn slide_ids(presentation: &str) -> anyhow::Result<Vec<String>> {
+    let mut ids = Vec::new();
+    let mut reader = Reader::from_str(presentation);
+    let decoder = reader.decoder();
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"sldId" => {
+                // The namespaced `r:id`, not the numeric `id`.
+                let id = e.attributes().flatten().find(|a| a.key.prefix().is_some() && a.key.local_name().as_ref() == b"id");
+                if let Some(id) = id {
+                    ids.push(id.decode_and_unescape_value(decoder)?.into_owned());
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(ids)
+}
+
+/// Collects the non-empty paragraphs of a slide or notes page, one per table row inside tables.
+/// Fields (slide numbers, dates) are generated text and are left out.
+fn paragraphs(xml: &str) -> anyhow::Result<Vec<String>> {
+    let mut paragraphs = Vec::new();
+    let mut reader = Reader::from_str(xml);
+    let mut paragraph = String::new();
+    let mut row: Option<TableRow> = None;
+    let (mut in_text, mut in_field) = (false, false);
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"p" => paragraph.clear(),
+                b"t" => in_text = true,
+                b"fld" => in_field = true,
+                b"tr" => row = Some(TableRow::default()),
+                b"tc" => {
+                    if let Some(row) = &mut row {
+                        row.start_cell();
+                    }
+                }
+                _ => {}
+            },
+            Event::Empty(e) if e.local_name().as_ref() == b"br" => paragraph.push(' '),
+            Event::Text(e) if in_text && !in_field => paragraph.push_str(&e.xml_content()?),
+            Event::GeneralRef(e) if in_text && !in_field => paragraph.push_str(&package::reference_text(&e)?),
+            Event::End(e) => match e.local_name().as_ref() {
+                b"t" => in_text = false,
+                b"fld" => in_field = false,
+                b"p" => {
+                    let text = paragraph.trim();
+                    if let Some(row) = &mut row {
+                        row.add_paragraph(text);
+                    } else if !text.is_empty() {
+                        paragraphs.push(text.to_string());
+                    }
+                }
+                b"tc" => {
+                    if let Some(row) = &mut row {
+                        row.end_cell();
+                    }
+                }
+                b"tr" => {
+                    if let Some(text) = row.take().and_then(TableRow::finish) {
+                        paragraphs.push(text);
+                    }
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(paragraphs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn slide(body: &str) -> String {
+        format!("<p:sld><p:cSld><p:spTree>{body}</p:spTree></p:cSld></p:sld>")
+    }
+
+    #[test]
+    fn paragraphs_skip_fields_and_join_runs() {
+        let xml = slide(
+            "<p:sp><p:txBody><a:p><a:r><a:t>Quarterly </a:t></a:r><a:r><a:t>R&amp;D</a:t></a:r><a:br/><a:r><a:t>plan</a:t></a:r></a:p>\
+             <a:p><a:fld type=\"slidenum\"><a:t>7</a:t></a:fld></a:p><a:p><a:r><a:t>  </a:t></a:r></a:p></p:txBody></p:sp>",
+        );
+        assert_eq!(paragraphs(&xml).unwrap(), ["Quarterly R&D plan"]);
+    }
+
+    #[test]
+    fn table_rows_become_one_paragraph() {
+        let xml = slide(
+            "<a:tbl><a:tr><a:tc><a:txBody><a:p><a:r><a:t>Name</a:t></a:r></a:p></a:txBody></a:tc><a:tc><a:txBody><a:p/></a:txBody></a:tc>\
+             <a:tc><a:txBody><a:p><a:r><a:t>Total</a:t></a:r></a:p></a:txBody></a:tc></a:tr>\
+             <a:tr><a:tc><a:txBody><a:p/></a:txBody></a:tc></a:tr></a:tbl><a:p><a:r><a:t>After</a:t></a:r></a:p>",
+        );
+        assert_eq!(paragraphs(&xml).unwrap(), ["Name |  | Total", "After"]);
+    }
+
+    #[test]
+    fn relationship_targets_resolve_against_their_part() {
+        assert_eq!(resolve("ppt/slides", "../notesSlides/notesSlide1.xml"), "ppt/notesSlides/notesSlide1.xml");
+        assert_eq!(resolve("ppt", "slides/slide1.xml"), "ppt/slides/slide1.xml");
+        assert_eq!(resolve("ppt/slides", "/ppt/media/image1.png"), "ppt/media/image1.png");
+    }
+
+    #[test]
+    fn slides_follow_presentation_order_with_their_notes() {
+        let relationship = |id: &str, kind: &str, target: &str| {
+            format!(r#"<Relationship Id="{id}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/{kind}" Target="{target}"/>"#)
+        };
+        let relationships = |items: &[String]| format!("<Relationships>{}</Relationships>", items.concat());
+        let text = |text: &str| format!("<p:sp><p:txBody><a:p><a:r><a:t>{text}</a:t></a:r></a:p></p:txBody></p:sp>");
+        let entries = [
+            ("ppt/presentation.xml", r#"<p:presentation><p:sldIdLst><p:sldId id="257" r:id="rId3"/><p:sldId id="256" r:id="rId2"/></p:sldIdLst></p:presentation>"#.to_string()),
+            ("ppt/_rels/presentation.xml.rels", relationships(&[relationship("rId2", "slide", "slides/slide1.xml"), relationship("rId3", "slide", "slides/slide2.xml")])),
+            ("ppt/slides/slide1.xml", slide(&text("Second"))),
+            ("ppt/slides/slide2.xml", slide(&text("First"))),
+            ("ppt/slides/_rels/slide1.xml.rels", relationships(&[relationship("rId1", "notesSlide", "../notesSlides/notesSlide1.xml")])),
+            ("ppt/notesSlides/notesSlide1.xml", format!("<p:notes><p:cSld><p:spTree>{}</p:spTree></p:cSld></p:notes>", text("Speaker notes"))),
+        ];
+
+        let path = std::env::temp_dir().join(format!("deepsearch_test_{}.pptx", std::process::id()));
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        for (name, xml) in &entries {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+            zip.write_all(xml.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+        let segments: anyhow::Result<Vec<_>> = PptxExtractor.extract(&path).map(Iterator::collect);
+        std::fs::remove_file(&path).ok();
+
+        let segment = |slide: u64, text: &str| ContentSegment { location: SegmentLocation::Slide(slide), text: text.to_string() };
+        assert_eq!(segments.unwrap(), [segment(1, "First"), segment(2, "Second"), segment(2, "Speaker notes")]);
+    }
+}
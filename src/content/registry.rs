@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use super::{doc, docx, odf, pdf, pptx, spreadsheet, text, ContentKind};
+use crate::db::ContentSegment;
+
+/// The text of a document, one segment at a time in document order.
+pub type Segments = Box<dyn Iterator<Item = ContentSegment>>;
+
+/// Extracts the text of one family of document formats as located segments: pages, paragraphs,
+/// slides, spreadsheet rows or lines. Empty segments are skipped and the text is trimmed.
+pub trait ContentExtractor: Send + Sync {
+    /// Lowercase file extensions handled by this extractor, without the dot.
+    fn extensions(&self) -> &'static [&'static str];
+
+    /// Lowercase mime types of these formats, without parameters.
+    fn mime_types(&self) -> &'static [&'static str];
+
+    /// The group under which the user includes or excludes these formats.
+    fn kind(&self) -> ContentKind;
+
+    /// Opens the document and streams its segments.
+    fn extract(&self, path: &Path) -> anyhow::Result<Segments>;
+}
+
+/// The Registry maps file extensions and mime types to the extractor that reads them.
+pub struct ExtractorRegistry {
+    extractors: HashMap<String, Arc<dyn ContentExtractor>>,
+    mime_types: HashMap<String, Arc<dyn ContentExtractor>>,
+}
+
+impl ExtractorRegistry {
+    pub fn new() -> Self {
+        let mut registry = Self { extractors: HashMap::new(), mime_types: HashMap::new() };
+        registry.register(pdf::PdfExtractor);
+        registry.register(docx::DocxExtractor);
+        registry.register(doc::DocExtractor);
+        registry.register(spreadsheet::SpreadsheetExtractor);
+        registry.register(pptx::PptxExtractor);
+        registry.register(odf::OdtExtractor);
+        registry.register(odf::OdpExtractor);
+        registry.register(text::PlainTextExtractor);
+        registry
+    }
+
+    /// Registers `extractor` for each of its extensions and mime types, replacing earlier registrations.
+    pub fn register(&mut self, extractor: impl ContentExtractor + 'static) {
+        let extractor: Arc<dyn ContentExtractor> = Arc::new(extractor);
+        for extension in extractor.extensions() {
+            self.extractors.insert((*extension).to_string(), extractor.clone());
+        }
+        for mime_type in extractor.mime_types() {
+            self.mime_types.insert((*mime_type).to_string(), extractor.clone());
+        }
+    }
+
+    /// Finds the extractor of a lowercase extension (`pdf`) or of a mime type (`application/pdf`,
+    /// parameters such as `; charset=utf-8` are ignored).
+    pub fn get(&self, extension_or_mime_type: &str) -> Option<&dyn ContentExtractor> {
+        if extension_or_mime_type.contains('/') {
+            let essence = extension_or_mime_type.split(';').next().unwrap_or_default().trim().to_lowercase();
+            return self.mime_types.get(&essence).map(AsRef::as_ref);
+        }
+        self.extractors.get(extension_or_mime_type).map(AsRef::as_ref)
+    }
+
+    pub fn for_path(&self, path: &Path) -> Option<&dyn ContentExtractor> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        self.get(&extension)
+    }
+
+    /// All registered extensions, sorted.
+    pub fn extensions(&self) -> Vec<&str> {
+        let mut extensions: Vec<&str> = self.extractors.keys().map(String::as_str).collect();
+        extensions.sort_unstable();
+        extensions
+    }
+
+    /// The registered extensions of a content-type group, sorted.
+    pub fn extensions_of(&self, kind: ContentKind) -> Vec<&str> {
+        let mut extensions: Vec<&str> = self.extractors.iter().filter(|(_, extractor)| extractor.kind() == kind).map(|(extension, _)| extension.as_str()).collect();
+        extensions.sort_unstable();
+        extensions
+    }
+}
+
+static REGISTRY: OnceLock<ExtractorRegistry> = OnceLock::new();
+
+/// Makes `registry` the one returned by `extractors`. Must run at startup, before any search
+/// or indexing reads the registry.
+pub fn install_extractors(registry: ExtractorRegistry) -> anyhow::Result<()> {
+    REGISTRY.set(registry).map_err(|_| anyhow::anyhow!("The content extractor registry is already in use"))
+}
+
+/// The installed registry, shared by live search, content indexing and the indexed content
+/// search. Falls back to the built-in extractors when none was installed.
+pub fn extractors() -> &'static ExtractorRegistry {
+    REGISTRY.get_or_init(ExtractorRegistry::new)
+}
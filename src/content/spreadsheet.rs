@@ -0,0 +1,51 @@
+use std::path::Path;
+use calamine::{open_workbook_auto, Data, Range, Reader};
+use super::{ContentExtractor, ContentKind, Segments};
+use crate::db::{ContentSegment, SegmentLocation};
+
+/// One segment per non-empty row of every sheet, its cells joined with " | ".
+pub struct SpreadsheetExtractor;
+
+impl ContentExtractor for SpreadsheetExtractor {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["xlsx", "xlsb", "xls", "ods"]
+    }
+
+    fn mime_types(&self) -> &'static [&'static str] {
+        &[
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            "application/vnd.ms-excel.sheet.binary.macroenabled.12",
+            "application/vnd.ms-excel",
+            "application/vnd.oasis.opendocument.spreadsheet",
+        ]
+    }
+
+    fn kind(&self) -> ContentKind {
+        ContentKind::Office
+    }
+
+    fn extract(&self, path: &Path) -> anyhow::Result<Segments> {
+        let mut workbook = open_workbook_auto(path)?;
+        let mut segments = Vec::new();
+        for sheet_name in workbook.sheet_names() {
+            if let Ok(range) = workbook.worksheet_range(&sheet_name) {
+                push_rows(&mut segments, &sheet_name, &range);
+            }
+        }
+        Ok(Box::new(segments.into_iter()))
+    }
+}
+
+/// Appends the non-empty rows of a sheet, numbered like the spreadsheet shows them.
+fn push_rows(segments: &mut Vec<ContentSegment>, sheet_name: &str, range: &Range<Data>) {
+    let first_row = range.start().map_or(0, |(row, _)| u64::from(row));
+    for (row, cells) in (first_row + 1..).zip(range.rows()) {
+        let row_text = cells.iter().map(ToString::to_string).collect::<Vec<_>>().join(" | ");
+        if !row_text.replace('|', "").trim().is_empty() {
+            segments.push(ContentSegment {
+                location: SegmentLocation::Row { sheet: sheet_name.to_string(), row },
+                text: row_text.trim().to_string(),
+            });
+        }
+    }
+}
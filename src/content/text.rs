@@ -0,0 +1,43 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use super::{ContentExtractor, ContentKind, Segments};
+use crate::db::{ContentSegment, SegmentLocation};
+
+/// One segment per non-empty line, read lazily.
+pub struct PlainTextExtractor;
+
+impl ContentExtractor for PlainTextExtractor {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["txt", "md", "log", "rs", "py", "js", "html", "css", "json", "xml", "toml"]
+    }
+
+    fn mime_types(&self) -> &'static [&'static str] {
+        &[
+            "text/plain",
+            "text/markdown",
+            "text/x-rust",
+            "text/x-python",
+            "text/javascript",
+            "text/html",
+            "text/css",
+            "application/json",
+            "application/xml",
+            "text/xml",
+            "application/toml",
+        ]
+    }
+
+    fn kind(&self) -> ContentKind {
+        ContentKind::PlainText
+    }
+
+    fn extract(&self, path: &Path) -> anyhow::Result<Segments> {
+        let reader = BufReader::new(File::open(path)?);
+        let segments = (1..)
+            .zip(reader.lines().map_while(Result::ok))
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(line, text)| ContentSegment { location: SegmentLocation::Line(line), text: text.trim().to_string() });
+        Ok(Box::new(segments))
+    }
+}
@@ -1,18 +1,314 @@
-use redb::{Database, ReadableDatabase, ReadableTable, ReadableTableMetadata, TableDefinition};
+use redb::{Database, DatabaseError, MultimapTableDefinition, ReadableDatabase, ReadableMultimapTable, ReadableTable, ReadableTableMetadata, TableDefinition, TableError, TableHandle, MultimapTableHandle};
 use serde::{Deserialize, Serialize};
 use bincode::{Decode, Encode};
-use std::path::Path;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use rayon::prelude::*;
+use crate::content;
+use crate::gui::events::GuiSender;
+use crate::pop::control::TaskController;
+use crate::query::{Candidate, NameMatcher, PathNormalizer};
 use crate::utils;
 
 const LOCATIONS_TABLE: TableDefinition<&str, &str> = TableDefinition::new("locations");
+const META_TABLE: TableDefinition<&str, u64> = TableDefinition::new("meta");
+/// Root paths whose initial scan has started but not finished, with the start time.
+const PARTIAL_SCANS_TABLE: TableDefinition<&str, u64> = TableDefinition::new("partial_scans");
+/// For each partially scanned root, the top-level subdirectories that are fully written.
+const SCAN_CHECKPOINTS_TABLE: MultimapTableDefinition<&str, &str> = MultimapTableDefinition::new("scan_checkpoints");
 
-#[derive(Serialize, Deserialize, Debug, Clone, Encode, Decode)]
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+const RECORD_VERSION_KEY: &str = "record_version";
+const CONTENT_VERSION_KEY: &str = "content_version";
+
+/// Layout of the database tables.
+/// v1: `locations` + `index_*` tables. v2: adds `meta` and the `*_trigrams` companion tables.
+/// v3: adds the `*_content_trigrams` tables over the words of the content indexes.
+/// v4: the `*_trigrams` postings hold numeric file ids, mapped to keys by `*_file_ids` and `*_file_keys`.
+pub const SCHEMA_VERSION: u64 = 4;
+/// Encoding of `FileMetadata` values.
+/// v1: name + modified time. v2: adds size, created time, directory flag, extension and attributes.
+pub const RECORD_VERSION: u64 = 2;
+/// Encoding of the `ContentSegment` values of the content indexes.
+/// v1: a bare segment number. v2: a `SegmentLocation`.
+pub const CONTENT_VERSION: u64 = 2;
+
+const MIGRATION_BATCH_SIZE: usize = 50_000;
+
+/// How many indexed paths are looked up under the new root before a location is relocated.
+const RELOCATION_SAMPLE_SIZE: usize = 20;
+/// Share of the sampled paths that must exist under the new root; files deleted since the last
+/// scan should not block a relocation.
+const RELOCATION_MIN_FOUND_RATIO: f64 = 0.8;
+/// How many entries `sample_keys` walks at most when the top-level entries give too few samples.
+const SAMPLE_WALK_LIMIT: usize = 10_000;
+
+/// How many records name search verifies before it hands their matches over, so the first
+/// results of a large location show up without waiting for the whole table.
+const SEARCH_CHUNK_SIZE: usize = 1_000;
+
+/// Orders a chunk of search results best score first; ties keep their collection order.
+fn sort_by_score(results: &mut [(String, FileMetadata, f32)]) {
+    results.sort_by(|a, b| b.2.total_cmp(&a.2));
+}
+
+/// Keeps the candidates that also appear in `postings`. Both must be in ascending order,
+/// which is how redb returns the values of a multimap key.
+fn intersect_sorted<T: Ord>(candidates: Vec<T>, postings: impl Iterator<Item = T>) -> Vec<T> {
+    let mut kept = Vec::with_capacity(candidates.len());
+    let mut pending = candidates.into_iter().peekable();
+    for key in postings {
+        while pending.next_if(|candidate| *candidate < key).is_some() {}
+        if let Some(candidate) = pending.next_if(|candidate| *candidate == key) {
+            kept.push(candidate);
+        }
+        if pending.peek().is_none() {
+            break;
+        }
+    }
+    kept
+}
+
+/// Returns the name of the trigram posting table that accompanies an `index_*` table.
+pub fn trigram_table_name(table_name: &str) -> String {
+    format!("{table_name}_trigrams")
+}
+
+/// Returns the name of the table giving each key of an `index_*` table its numeric file id.
+pub fn file_ids_table_name(table_name: &str) -> String {
+    format!("{table_name}_file_ids")
+}
+
+/// Returns the name of the table resolving the file ids of the trigram postings back to keys.
+pub fn file_keys_table_name(table_name: &str) -> String {
+    format!("{table_name}_file_keys")
+}
+
+/// Returns the name of the token posting table of a location's optional content index.
+pub fn content_table_name(table_name: &str) -> String {
+    format!("{table_name}_content")
+}
+
+/// Returns the name of the trigram table over the words of a location's content index.
+pub fn content_trigram_table_name(table_name: &str) -> String {
+    format!("{table_name}_content_trigrams")
+}
+
+/// Returns the name of the table holding the text segments referenced by the content postings.
+pub fn segments_table_name(table_name: &str) -> String {
+    format!("{table_name}_segments")
+}
+
+/// Returns the name of the table listing the file extensions extracted into a location's content index.
+pub fn content_extensions_table_name(table_name: &str) -> String {
+    format!("{table_name}_content_extensions")
+}
+
+/// The extensions extracted into content indexes that were built before the extension set was
+/// stored with each index.
+const LEGACY_CONTENT_EXTENSIONS: &[&str] = &["pdf", "docx", "xlsx", "txt", "md", "log", "rs", "py", "js", "html", "css", "json", "xml", "toml"];
+
+/// Returns the name of the table holding the content hashes of a location's files.
+pub fn hashes_table_name(table_name: &str) -> String {
+    format!("{table_name}_hashes")
+}
+
+/// Words longer than this are not worth a posting list (hashes, base64 blobs...).
+const MAX_CONTENT_TOKEN_LEN: usize = 64;
+
+/// How many content hits are loaded before they are handed over, like the batches sent to the GUI.
+const CONTENT_CHUNK_SIZE: usize = 200;
+
+/// Where a piece of extracted text is in its document. Numbers start at 1.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum SegmentLocation {
+    Page(u64),
+    Paragraph(u64),
+    Slide(u64),
+    /// A spreadsheet row. `sheet` is empty for rows indexed before sheet names were recorded.
+    Row { sheet: String, row: u64 },
+    Line(u64),
+}
+
+impl std::fmt::Display for SegmentLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Page(page) => write!(f, "Page {page}"),
+            Self::Paragraph(paragraph) => write!(f, "Paragraph {paragraph}"),
+            Self::Slide(slide) => write!(f, "Slide {slide}"),
+            Self::Row { sheet, row } if sheet.is_empty() => write!(f, "Row {row}"),
+            Self::Row { sheet, row } => write!(f, "{sheet}, Row {row}"),
+            Self::Line(line) => write!(f, "Line {line}"),
+        }
+    }
+}
+
+/// One piece of extracted document text together with its location in the document.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Encode, Decode)]
+pub struct ContentSegment {
+    pub location: SegmentLocation,
+    pub text: String,
+}
+
+/// The `ContentSegment` encoding of content version 1, kept for migration.
+#[derive(Decode)]
+struct ContentSegmentV1 {
+    number: u64,
+    text: String,
+}
+
+/// A matching segment of an indexed document, with the texts of the segments around it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentHit {
+    pub key: String,
+    pub segment: ContentSegment,
+    pub context_before: Vec<String>,
+    pub context_after: Vec<String>,
+}
+
+/// The content hash of a file, together with the size and modification time it was computed for.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub struct FileHash {
+    pub size: u64,
+    pub modified_time: u64,
+    pub digest: [u8; 16],
+}
+
+/// Returns the distinct normalized words of a segment that get a content posting.
+fn content_tokens(text: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = utils::normalize_string(text)
+        .split_whitespace()
+        .filter(|token| token.chars().count() <= MAX_CONTENT_TOKEN_LEN)
+        .map(ToString::to_string)
+        .collect();
+    tokens.sort_unstable();
+    tokens.dedup();
+    tokens
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Encode, Decode)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct FileMetadata {
     pub normalized_name: String,
     pub modified_time: u64,
+    pub size: u64,
+    pub created_time: u64,
+    pub is_dir: bool,
+    /// Lowercase extension without the leading dot, empty when the name has none.
+    pub extension: String,
+    pub is_read_only: bool,
+    pub is_hidden: bool,
+}
+
+impl FileMetadata {
+    /// Builds the indexed record for a walked entry. Fields that the platform or the
+    /// filesystem cannot provide fall back to zero / `false`.
+    pub fn from_dir_entry(entry: &walkdir::DirEntry) -> Self {
+        let mut metadata = Self::from_dir_entry_name(entry);
+        if let Ok(meta) = entry.metadata() {
+            metadata.modified_time = meta.modified().map_or(0, utils::system_time_to_secs);
+            metadata.created_time = meta.created().map_or(0, utils::system_time_to_secs);
+            metadata.size = if meta.is_dir() { 0 } else { meta.len() };
+            metadata.is_read_only = meta.permissions().readonly();
+            #[cfg(windows)]
+            {
+                use std::os::windows::fs::MetadataExt;
+                const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+                metadata.is_hidden |= meta.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0;
+            }
+        }
+
+        metadata
+    }
+
+    /// Upgrades a record v1 (name + modified time) of the file at `path`. The extension and the
+    /// hidden flag are derived from the path; the other fields stay empty until a rescan.
+    pub fn from_v1(path: &str, normalized_name: String, modified_time: u64) -> Self {
+        let file_name = Path::new(path).file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        Self {
+            normalized_name,
+            modified_time,
+            size: 0,
+            created_time: 0,
+            is_dir: false,
+            extension: Path::new(path).extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default(),
+            is_read_only: false,
+            is_hidden: file_name.starts_with('.'),
+        }
+    }
+
+    /// Builds a record from the entry's name and type alone, without reading its metadata from disk.
+    pub fn from_dir_entry_name(entry: &walkdir::DirEntry) -> Self {
+        let file_name = entry.file_name().to_string_lossy();
+        let extension = entry.path()
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        Self {
+            normalized_name: utils::normalize_string(&file_name),
+            modified_time: 0,
+            size: 0,
+            created_time: 0,
+            is_dir: entry.file_type().is_dir(),
+            extension,
+            is_read_only: false,
+            is_hidden: file_name.starts_with('.'),
+        }
+    }
+}
+
+/// The `FileMetadata` encoding written before record version 2, kept for migration.
+#[derive(Decode)]
+struct FileMetadataV1 {
+    normalized_name: String,
+    modified_time: u64,
 }
 
+/// Decodes a stored value as a current `FileMetadata`, rejecting values with trailing bytes.
+fn decode_current(bytes: &[u8]) -> Option<FileMetadata> {
+    match bincode::decode_from_slice::<FileMetadata, _>(bytes, bincode::config::standard()) {
+        Ok((metadata, len)) if len == bytes.len() => Some(metadata),
+        _ => None,
+    }
+}
+
+/// What `DbManager::open_and_migrate` had to do to bring the index up to date.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MigrationOutcome {
+    /// The file already used the current schema, record and content encodings.
+    UpToDate,
+    /// Records and content segments were re-encoded in place. Until they are rescanned,
+    /// `locations` lack the record fields added since `from_version` and `content_locations`
+    /// lack the segment details added since `from_content_version`. Both lists may be empty.
+    Migrated { from_version: u64, locations: Vec<String>, from_content_version: u64, content_locations: Vec<String> },
+    /// The file is up to date, but the content indexes of `locations` were built before
+    /// `extensions` could be extracted. The next rescan of each location adds those files.
+    ContentFormatsAdded { extensions: Vec<String>, locations: Vec<String> },
+    /// The file was written by an incompatible redb format and could not be read.
+    /// It was moved to `backup_path` and an empty index was created instead.
+    Reset { backup_path: PathBuf },
+}
+
+/// Summary of `DbManager::verify_and_repair`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RepairReport {
+    /// `index_*` tables (and companions) not referenced by any location, which were dropped.
+    pub dropped_tables: Vec<String>,
+    /// Locations whose `index_*` table does not exist.
+    pub missing_tables: Vec<String>,
+    /// Locations with `FileMetadata` rows that cannot be decoded, with the row count and a few sample keys.
+    pub undecodable_rows: Vec<(String, u64, Vec<String>)>,
+    /// Scan checkpoints left behind for roots that are no longer locations.
+    pub stale_scan_states: Vec<String>,
+    /// Whether compaction freed space in the file.
+    pub compacted: bool,
+}
+
+/// How many undecodable keys are listed per location in a `RepairReport`.
+const UNDECODABLE_SAMPLE_SIZE: usize = 5;
+
 pub struct DbManager {
     pub db: Database,
 }
@@ -23,29 +319,379 @@ impl DbManager {
         let txn = db.begin_write()?;
         {
             txn.open_table(LOCATIONS_TABLE)?;
+            txn.open_table(META_TABLE)?;
+            txn.open_table(PARTIAL_SCANS_TABLE)?;
+            txn.open_multimap_table(SCAN_CHECKPOINTS_TABLE)?;
         }
         txn.commit()?;
         Ok(Self { db })
     }
 
+    /// Opens the database at startup and upgrades it to the current schema and record encoding.
+    /// Files from an unreadable redb format are set aside so the application can still start.
+    pub fn open_and_migrate(db_path: &Path, reporter: Option<&GuiSender>) -> anyhow::Result<MigrationOutcome> {
+        let db_manager = match Self::new(db_path) {
+            Ok(db_manager) => db_manager,
+            Err(e) if matches!(e.downcast_ref::<DatabaseError>(), Some(DatabaseError::UpgradeRequired(_))) => {
+                let mut backup_path = db_path.as_os_str().to_owned();
+                backup_path.push(".old");
+                let backup_path = PathBuf::from(backup_path);
+                std::fs::rename(db_path, &backup_path)?;
+                Self::new(db_path)?.set_versions(SCHEMA_VERSION, RECORD_VERSION, CONTENT_VERSION)?;
+                return Ok(MigrationOutcome::Reset { backup_path });
+            }
+            Err(e) => return Err(e),
+        };
+
+        let locations = db_manager.get_all_locations()?;
+        let (schema_version, record_version, content_version) = match db_manager.get_versions()? {
+            Some(versions) => versions,
+            // A file without version info is either brand new or from before versioning existed.
+            None if locations.is_empty() => (SCHEMA_VERSION, RECORD_VERSION, CONTENT_VERSION),
+            None => (1, 1, 1),
+        };
+
+        if schema_version > SCHEMA_VERSION || record_version > RECORD_VERSION || content_version > CONTENT_VERSION {
+            anyhow::bail!(
+                "The index file '{}' was created by a newer version of DeepSearch (schema v{schema_version}, records v{record_version}, content v{content_version}). Please update DeepSearch.",
+                db_path.display()
+            );
+        }
+
+        if schema_version == SCHEMA_VERSION && record_version == RECORD_VERSION && content_version == CONTENT_VERSION {
+            db_manager.set_versions(SCHEMA_VERSION, RECORD_VERSION, CONTENT_VERSION)?;
+            return db_manager.check_content_formats(&locations);
+        }
+
+        // Rewriting every row re-encodes old records and fills the trigram tables. Postings written
+        // before file ids are dropped first, each time, so an interrupted upgrade starts over cleanly.
+        if schema_version < 4 || record_version < 2 {
+            for (path, table_name) in &locations {
+                utils::report_progress(reporter, 0.0, &format!("⚙️ Upgrading index for {path}..."));
+                if schema_version < 4 {
+                    db_manager.drop_name_postings(table_name)?;
+                }
+                db_manager.migrate_table(table_name)?;
+            }
+        }
+        if schema_version < 3 {
+            for (path, table_name) in &locations {
+                if db_manager.has_content_index(table_name)? {
+                    utils::report_progress(reporter, 0.0, &format!("⚙️ Indexing the content words of {path}..."));
+                    db_manager.build_content_trigrams(table_name)?;
+                }
+            }
+        }
+        if content_version < 2 {
+            utils::report_progress(reporter, 0.0, "⚙️ Upgrading content indexes...");
+        }
+        let segment_tables: Vec<&str> = if content_version < 2 { locations.iter().map(|(_, table_name)| table_name.as_str()).collect() } else { Vec::new() };
+        db_manager.migrate_segments_and_set_versions(&segment_tables)?;
+
+        let mut incomplete = Vec::new();
+        let mut incomplete_content = Vec::new();
+        for (path, table_name) in locations {
+            if content_version < 2 && db_manager.has_content_index(&table_name)? {
+                incomplete_content.push(path.clone());
+            }
+            if record_version < 2 {
+                incomplete.push(path);
+            }
+        }
+        Ok(MigrationOutcome::Migrated {
+            from_version: record_version,
+            locations: incomplete,
+            from_content_version: content_version,
+            content_locations: incomplete_content,
+        })
+    }
 
+    /// Lists the content-indexed locations whose index was built before some of the supported
+    /// formats. Their files are extracted by the next rescan.
+    fn check_content_formats(&self, locations: &[(String, String)]) -> anyhow::Result<MigrationOutcome> {
+        let supported = content::extractors().extensions();
+        let mut extensions = BTreeSet::new();
+        let mut outdated = Vec::new();
+        for (path, table_name) in locations {
+            if !self.has_content_index(table_name)? {
+                continue;
+            }
+            let missing = self.missing_content_extensions(table_name, &supported)?;
+            if !missing.is_empty() {
+                extensions.extend(missing);
+                outdated.push(path.clone());
+            }
+        }
+        if outdated.is_empty() {
+            return Ok(MigrationOutcome::UpToDate);
+        }
+        Ok(MigrationOutcome::ContentFormatsAdded { extensions: extensions.into_iter().collect(), locations: outdated })
+    }
 
+    /// Reads the stored schema, record and content versions. The content version was recorded
+    /// later than the other two; files without it use content v1.
+    fn get_versions(&self) -> anyhow::Result<Option<(u64, u64, u64)>> {
+        let txn = self.db.begin_read()?;
+        let meta_table = txn.open_table(META_TABLE)?;
+        let schema_version = meta_table.get(SCHEMA_VERSION_KEY)?.map(|guard| guard.value());
+        let record_version = meta_table.get(RECORD_VERSION_KEY)?.map(|guard| guard.value());
+        let content_version = meta_table.get(CONTENT_VERSION_KEY)?.map_or(1, |guard| guard.value());
+        Ok(schema_version.zip(record_version).map(|(schema_version, record_version)| (schema_version, record_version, content_version)))
+    }
+
+    fn set_versions(&self, schema_version: u64, record_version: u64, content_version: u64) -> anyhow::Result<()> {
+        let txn = self.db.begin_write()?;
+        Self::write_versions(&txn, schema_version, record_version, content_version)?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    fn write_versions(txn: &redb::WriteTransaction, schema_version: u64, record_version: u64, content_version: u64) -> anyhow::Result<()> {
+        let mut meta_table = txn.open_table(META_TABLE)?;
+        meta_table.insert(SCHEMA_VERSION_KEY, schema_version)?;
+        meta_table.insert(RECORD_VERSION_KEY, record_version)?;
+        meta_table.insert(CONTENT_VERSION_KEY, content_version)?;
+        Ok(())
+    }
+
+    /// Re-encodes the content segments of the given locations from content v1, deriving each
+    /// location kind from the file extension, then records the current versions. Everything
+    /// happens in one transaction: a v1 row must never be converted twice.
+    fn migrate_segments_and_set_versions(&self, table_names: &[&str]) -> anyhow::Result<()> {
+        let txn = self.db.begin_write()?;
+        for table_name in table_names {
+            let segments_name = segments_table_name(table_name);
+            let segments_def: TableDefinition<(&str, u32), &[u8]> = TableDefinition::new(&segments_name);
+            let migrated_name = format!("{segments_name}_migrating");
+            let migrated_def: TableDefinition<(&str, u32), &[u8]> = TableDefinition::new(&migrated_name);
+            {
+                let segments_table = match txn.open_table(segments_def) {
+                    Ok(table) => table,
+                    Err(TableError::TableDoesNotExist(_)) => continue,
+                    Err(e) => return Err(e.into()),
+                };
+                let mut migrated_table = txn.open_table(migrated_def)?;
+                for item in segments_table.iter()? {
+                    let (key, value) = item?;
+                    let (file_key, seq) = key.value();
+                    // Undecodable rows are dropped; search already skips them.
+                    let Ok((old, _len)) = bincode::decode_from_slice::<ContentSegmentV1, _>(value.value(), bincode::config::standard()) else {
+                        continue;
+                    };
+                    let extension = Path::new(file_key).extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default();
+                    let location = match extension.as_str() {
+                        "pdf" => SegmentLocation::Page(old.number),
+                        "docx" => SegmentLocation::Paragraph(old.number),
+                        "xlsx" => SegmentLocation::Row { sheet: String::new(), row: old.number },
+                        _ => SegmentLocation::Line(old.number),
+                    };
+                    let value = bincode::encode_to_vec(ContentSegment { location, text: old.text }, bincode::config::standard())?;
+                    migrated_table.insert((file_key, seq), &value[..])?;
+                }
+            }
+            txn.delete_table(segments_def)?;
+            txn.rename_table(migrated_def, segments_def)?;
+        }
+        Self::write_versions(&txn, SCHEMA_VERSION, RECORD_VERSION, CONTENT_VERSION)?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Deletes the trigram postings and file ids of an `index_*` table, whatever their layout.
+    fn drop_name_postings(&self, table_name: &str) -> anyhow::Result<()> {
+        let txn = self.db.begin_write()?;
+        Self::delete_name_postings(&txn, table_name)?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Deletes the trigram postings and file ids of an `index_*` table in the given transaction.
+    /// Tables are deleted by name, so postings of any schema version go.
+    fn delete_name_postings(txn: &redb::WriteTransaction, table_name: &str) -> anyhow::Result<()> {
+        let trigram_name = trigram_table_name(table_name);
+        let trigram_def: MultimapTableDefinition<&str, u64> = MultimapTableDefinition::new(&trigram_name);
+        txn.delete_multimap_table(trigram_def)?;
+
+        let ids_name = file_ids_table_name(table_name);
+        let ids_def: TableDefinition<&str, u64> = TableDefinition::new(&ids_name);
+        txn.delete_table(ids_def)?;
+
+        let keys_name = file_keys_table_name(table_name);
+        let keys_def: TableDefinition<u64, &str> = TableDefinition::new(&keys_name);
+        txn.delete_table(keys_def)?;
+        Ok(())
+    }
+
+    /// Fills the trigram table over the words of a content index built before schema v3.
+    /// Inserting is idempotent, so an interrupted migration can run again.
+    fn build_content_trigrams(&self, table_name: &str) -> anyhow::Result<()> {
+        let content_name = content_table_name(table_name);
+        let content_def: MultimapTableDefinition<&str, (&str, u32)> = MultimapTableDefinition::new(&content_name);
+        let content_trigram_name = content_trigram_table_name(table_name);
+        let content_trigram_def: MultimapTableDefinition<&str, &str> = MultimapTableDefinition::new(&content_trigram_name);
+
+        let txn = self.db.begin_write()?;
+        {
+            let content_table = txn.open_multimap_table(content_def)?;
+            let mut content_trigram_table = txn.open_multimap_table(content_trigram_def)?;
+            for item in content_table.iter()? {
+                let (word, _) = item?;
+                for trigram in utils::trigrams(word.value()) {
+                    content_trigram_table.insert(trigram.as_str(), word.value())?;
+                }
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Re-encodes every row of an `index_*` table with the current `FileMetadata` layout.
+    /// Rows that already use it are rewritten unchanged, so an interrupted migration can resume.
+    fn migrate_table(&self, table_name: &str) -> anyhow::Result<()> {
+        let table_def: TableDefinition<&str, &[u8]> = TableDefinition::new(table_name);
+        let txn = self.db.begin_read()?;
+        let table = match txn.open_table(table_def) {
+            Ok(table) => table,
+            Err(TableError::TableDoesNotExist(_)) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut batch: Vec<(String, FileMetadata)> = Vec::with_capacity(MIGRATION_BATCH_SIZE);
+        for item in table.iter()? {
+            let (key, value) = item?;
+            let key = key.value().to_string();
+            let metadata = decode_current(value.value()).or_else(|| {
+                let (old, _len) = bincode::decode_from_slice::<FileMetadataV1, _>(value.value(), bincode::config::standard()).ok()?;
+                Some(FileMetadata::from_v1(&key, old.normalized_name, old.modified_time))
+            });
+
+            // Undecodable rows are left as they are; search already skips them.
+            if let Some(metadata) = metadata {
+                batch.push((key, metadata));
+            }
+            if batch.len() >= MIGRATION_BATCH_SIZE {
+                self.write_to_table(table_name, &batch)?;
+                batch.clear();
+            }
+        }
+
+        if !batch.is_empty() {
+            self.write_to_table(table_name, &batch)?;
+        }
+        Ok(())
+    }
+
+
+
+    /// Inserts or replaces records and lists each file under the trigrams of its name. A new key
+    /// gets the next free file id; the postings hold that id instead of the key.
     pub fn write_to_table(&self, table_name: &str, files: &[(String, FileMetadata)]) -> anyhow::Result<()> {
         let table_def: TableDefinition<&str, &[u8]> = TableDefinition::new(table_name);
+        let trigram_name = trigram_table_name(table_name);
+        let trigram_def: MultimapTableDefinition<&str, u64> = MultimapTableDefinition::new(&trigram_name);
+        let ids_name = file_ids_table_name(table_name);
+        let ids_def: TableDefinition<&str, u64> = TableDefinition::new(&ids_name);
+        let keys_name = file_keys_table_name(table_name);
+        let keys_def: TableDefinition<u64, &str> = TableDefinition::new(&keys_name);
 
         let txn = self.db.begin_write()?;
         {
             let mut table = txn.open_table(table_def)?;
+            let mut trigram_table = txn.open_multimap_table(trigram_def)?;
+            let mut ids_table = txn.open_table(ids_def)?;
+            let mut keys_table = txn.open_table(keys_def)?;
+            let mut next_id = keys_table.last()?.map_or(0, |(id, _)| id.value() + 1);
             for (path, metadata) in files {
                 let key = path.as_str();
                 let value = bincode::encode_to_vec(metadata, bincode::config::standard())?;
                 table.insert(key, &value[..])?;
+
+                let existing_id = ids_table.get(key)?.map(|guard| guard.value());
+                let id = if let Some(id) = existing_id {
+                    id
+                } else {
+                    let id = next_id;
+                    next_id += 1;
+                    ids_table.insert(key, id)?;
+                    keys_table.insert(id, key)?;
+                    id
+                };
+                for trigram in utils::trigrams(&metadata.normalized_name) {
+                    trigram_table.insert(trigram.as_str(), id)?;
+                }
             }
         }
         txn.commit()?;
         Ok(())
     }
 
+    /// Removes the given keys from an `index_*` table and drops their file ids and trigram postings.
+    pub fn remove_from_table(&self, table_name: &str, keys: &[String]) -> anyhow::Result<()> {
+        let table_def: TableDefinition<&str, &[u8]> = TableDefinition::new(table_name);
+        let trigram_name = trigram_table_name(table_name);
+        let trigram_def: MultimapTableDefinition<&str, u64> = MultimapTableDefinition::new(&trigram_name);
+        let ids_name = file_ids_table_name(table_name);
+        let ids_def: TableDefinition<&str, u64> = TableDefinition::new(&ids_name);
+        let keys_name = file_keys_table_name(table_name);
+        let keys_def: TableDefinition<u64, &str> = TableDefinition::new(&keys_name);
+
+        let txn = self.db.begin_write()?;
+        {
+            let mut table = txn.open_table(table_def)?;
+            let mut trigram_table = txn.open_multimap_table(trigram_def)?;
+            let mut ids_table = txn.open_table(ids_def)?;
+            let mut keys_table = txn.open_table(keys_def)?;
+            for key in keys {
+                let removed = table.remove(key.as_str())?
+                    .and_then(|guard| bincode::decode_from_slice::<FileMetadata, _>(guard.value(), bincode::config::standard()).ok());
+                let Some(id) = ids_table.remove(key.as_str())?.map(|guard| guard.value()) else {
+                    continue;
+                };
+                keys_table.remove(id)?;
+                if let Some((metadata, _len)) = removed {
+                    for trigram in utils::trigrams(&metadata.normalized_name) {
+                        trigram_table.remove(trigram.as_str(), id)?;
+                    }
+                }
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Loads every key of an `index_*` table together with its stored modification time.
+    pub fn get_modified_times(&self, table_name: &str) -> anyhow::Result<HashMap<String, u64>> {
+        let table_def: TableDefinition<&str, &[u8]> = TableDefinition::new(table_name);
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table(table_def)?;
+
+        let mut modified_times = HashMap::new();
+        for item in table.iter()? {
+            let (key, value) = item?;
+            let modified_time = bincode::decode_from_slice::<FileMetadata, _>(value.value(), bincode::config::standard())
+                .map_or(0, |(metadata, _len)| metadata.modified_time);
+            modified_times.insert(key.value().to_string(), modified_time);
+        }
+        Ok(modified_times)
+    }
+
+    /// Calls `visit` for every decodable record of an `index_*` table, in key order.
+    pub fn for_each_record<F>(&self, table_name: &str, mut visit: F) -> anyhow::Result<()>
+    where
+        F: FnMut(&str, FileMetadata) -> anyhow::Result<()>,
+    {
+        let table_def: TableDefinition<&str, &[u8]> = TableDefinition::new(table_name);
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table(table_def)?;
+        for item in table.iter()? {
+            let (key, value) = item?;
+            if let Some(metadata) = decode_current(value.value()) {
+                visit(key.value(), metadata)?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn get_all_locations(&self) -> anyhow::Result<Vec<(String, String)>> {
         let txn = self.db.begin_read()?;
         let table = txn.open_table(LOCATIONS_TABLE)?;
@@ -70,45 +716,712 @@ impl DbManager {
                 .map(|guard| guard.value().to_string());
 
             if let Some(table_name) = table_name_to_delete {
-                let table_def: TableDefinition<&str, &[u8]> = TableDefinition::new(&table_name);
-                
                 // Now we can safely get mutable borrows
-                txn.delete_table(table_def)?;
+                Self::delete_index_tables(&txn, &table_name)?;
                 locations_table.remove(path_to_delete)?;
             }
+
+            txn.open_table(PARTIAL_SCANS_TABLE)?.remove(path_to_delete)?;
+            txn.open_multimap_table(SCAN_CHECKPOINTS_TABLE)?.remove_all(path_to_delete)?;
         }
         txn.commit()?;
         Ok(())
     }
 
-    pub fn search_in_table(&self, table_name: &str, query: &str) -> anyhow::Result<Vec<String>> {
+    /// Deletes an `index_*` table together with its companion tables in the given transaction.
+    pub fn delete_index_tables(txn: &redb::WriteTransaction, table_name: &str) -> anyhow::Result<()> {
         let table_def: TableDefinition<&str, &[u8]> = TableDefinition::new(table_name);
+        txn.delete_table(table_def)?;
+        Self::delete_name_postings(txn, table_name)?;
+
+        let content_name = content_table_name(table_name);
+        let content_def: MultimapTableDefinition<&str, (&str, u32)> = MultimapTableDefinition::new(&content_name);
+        txn.delete_multimap_table(content_def)?;
+
+        let content_trigram_name = content_trigram_table_name(table_name);
+        let content_trigram_def: MultimapTableDefinition<&str, &str> = MultimapTableDefinition::new(&content_trigram_name);
+        txn.delete_multimap_table(content_trigram_def)?;
+
+        let segments_name = segments_table_name(table_name);
+        let segments_def: TableDefinition<(&str, u32), &[u8]> = TableDefinition::new(&segments_name);
+        txn.delete_table(segments_def)?;
+
+        let hashes_name = hashes_table_name(table_name);
+        let hashes_def: TableDefinition<&str, &[u8]> = TableDefinition::new(&hashes_name);
+        txn.delete_table(hashes_def)?;
+
+        let extensions_name = content_extensions_table_name(table_name);
+        let extensions_def: TableDefinition<&str, ()> = TableDefinition::new(&extensions_name);
+        txn.delete_table(extensions_def)?;
+        Ok(())
+    }
+
+    /// Returns whether the location stored in `table_name` was indexed with its file contents.
+    pub fn has_content_index(&self, table_name: &str) -> anyhow::Result<bool> {
+        let content_name = content_table_name(table_name);
+        let content_def: MultimapTableDefinition<&str, (&str, u32)> = MultimapTableDefinition::new(&content_name);
         let txn = self.db.begin_read()?;
-        let table = txn.open_table(table_def)?;
+        match txn.open_multimap_table(content_def) {
+            Ok(_) => Ok(true),
+            Err(TableError::TableDoesNotExist(_)) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Records the extensions whose files are extracted into the content index of `table_name`.
+    pub fn set_content_extensions(&self, table_name: &str, extensions: &[&str]) -> anyhow::Result<()> {
+        let extensions_name = content_extensions_table_name(table_name);
+        let extensions_def: TableDefinition<&str, ()> = TableDefinition::new(&extensions_name);
+        let txn = self.db.begin_write()?;
+        txn.delete_table(extensions_def)?;
+        {
+            let mut extensions_table = txn.open_table(extensions_def)?;
+            for extension in extensions {
+                extensions_table.insert(*extension, ())?;
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Returns the extensions whose files are extracted into the content index of `table_name`.
+    pub fn get_content_extensions(&self, table_name: &str) -> anyhow::Result<Vec<String>> {
+        let extensions_name = content_extensions_table_name(table_name);
+        let extensions_def: TableDefinition<&str, ()> = TableDefinition::new(&extensions_name);
+        let txn = self.db.begin_read()?;
+        match txn.open_table(extensions_def) {
+            Ok(table) => Ok(table.iter()?.filter_map(Result::ok).map(|(extension, _)| extension.value().to_string()).collect()),
+            Err(TableError::TableDoesNotExist(_)) => Ok(LEGACY_CONTENT_EXTENSIONS.iter().map(ToString::to_string).collect()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Returns the extensions of `supported` that the content index of `table_name` was built
+    /// without, so that their files still have to be extracted.
+    pub fn missing_content_extensions(&self, table_name: &str, supported: &[&str]) -> anyhow::Result<Vec<String>> {
+        let extracted: HashSet<String> = self.get_content_extensions(table_name)?.into_iter().collect();
+        Ok(supported.iter().filter(|extension| !extracted.contains(**extension)).map(ToString::to_string).collect())
+    }
+
+    /// Loads the content segments of the given files, in document order. Files without segments
+    /// or a location without a content index yield no entry.
+    pub fn get_content_segments(&self, table_name: &str, keys: &[&str]) -> anyhow::Result<HashMap<String, Vec<ContentSegment>>> {
+        let segments_name = segments_table_name(table_name);
+        let segments_def: TableDefinition<(&str, u32), &[u8]> = TableDefinition::new(&segments_name);
+        let txn = self.db.begin_read()?;
+        let segments_table = match txn.open_table(segments_def) {
+            Ok(table) => table,
+            Err(TableError::TableDoesNotExist(_)) => return Ok(HashMap::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut segments = HashMap::new();
+        for key in keys {
+            let mut file_segments = Vec::new();
+            for item in segments_table.range((*key, 0)..=(*key, u32::MAX))? {
+                let (_, value) = item?;
+                if let Ok((segment, _len)) = bincode::decode_from_slice::<ContentSegment, _>(value.value(), bincode::config::standard()) {
+                    file_segments.push(segment);
+                }
+            }
+            if !file_segments.is_empty() {
+                segments.insert((*key).to_string(), file_segments);
+            }
+        }
+        Ok(segments)
+    }
+
+    /// Replaces the content index entries of each given file with its freshly extracted segments.
+    pub fn write_content(&self, table_name: &str, files: &[(String, Vec<ContentSegment>)]) -> anyhow::Result<()> {
+        let content_name = content_table_name(table_name);
+        let content_def: MultimapTableDefinition<&str, (&str, u32)> = MultimapTableDefinition::new(&content_name);
+        let content_trigram_name = content_trigram_table_name(table_name);
+        let content_trigram_def: MultimapTableDefinition<&str, &str> = MultimapTableDefinition::new(&content_trigram_name);
+        let segments_name = segments_table_name(table_name);
+        let segments_def: TableDefinition<(&str, u32), &[u8]> = TableDefinition::new(&segments_name);
+
+        let txn = self.db.begin_write()?;
+        {
+            let mut content_table = txn.open_multimap_table(content_def)?;
+            let mut content_trigram_table = txn.open_multimap_table(content_trigram_def)?;
+            let mut segments_table = txn.open_table(segments_def)?;
+            for (key, segments) in files {
+                Self::remove_content_of(&mut content_table, &mut content_trigram_table, &mut segments_table, key)?;
+                for (seq, segment) in (0..u32::MAX).zip(segments) {
+                    let value = bincode::encode_to_vec(segment, bincode::config::standard())?;
+                    segments_table.insert((key.as_str(), seq), &value[..])?;
+                    for token in content_tokens(&segment.text) {
+                        // A word new to the vocabulary is listed under its trigrams.
+                        if content_table.get(token.as_str())?.is_empty() {
+                            for trigram in utils::trigrams(&token) {
+                                content_trigram_table.insert(trigram.as_str(), token.as_str())?;
+                            }
+                        }
+                        content_table.insert(token.as_str(), (key.as_str(), seq))?;
+                    }
+                }
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Drops the content index entries of the given files, if the location has a content index.
+    pub fn remove_content(&self, table_name: &str, keys: &[String]) -> anyhow::Result<()> {
+        if !self.has_content_index(table_name)? {
+            return Ok(());
+        }
+        let content_name = content_table_name(table_name);
+        let content_def: MultimapTableDefinition<&str, (&str, u32)> = MultimapTableDefinition::new(&content_name);
+        let content_trigram_name = content_trigram_table_name(table_name);
+        let content_trigram_def: MultimapTableDefinition<&str, &str> = MultimapTableDefinition::new(&content_trigram_name);
+        let segments_name = segments_table_name(table_name);
+        let segments_def: TableDefinition<(&str, u32), &[u8]> = TableDefinition::new(&segments_name);
+
+        let txn = self.db.begin_write()?;
+        {
+            let mut content_table = txn.open_multimap_table(content_def)?;
+            let mut content_trigram_table = txn.open_multimap_table(content_trigram_def)?;
+            let mut segments_table = txn.open_table(segments_def)?;
+            for key in keys {
+                Self::remove_content_of(&mut content_table, &mut content_trigram_table, &mut segments_table, key)?;
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Drops the postings and segments of one file. Words left without postings leave the
+    /// vocabulary and its trigram table.
+    fn remove_content_of(
+        content_table: &mut redb::MultimapTable<&str, (&str, u32)>,
+        content_trigram_table: &mut redb::MultimapTable<&str, &str>,
+        segments_table: &mut redb::Table<(&str, u32), &[u8]>,
+        key: &str,
+    ) -> anyhow::Result<()> {
+        let existing: Vec<(u32, ContentSegment)> = segments_table
+            .range((key, 0)..=(key, u32::MAX))?
+            .filter_map(Result::ok)
+            .filter_map(|(seg_key, value)| {
+                let (segment, _len) = bincode::decode_from_slice::<ContentSegment, _>(value.value(), bincode::config::standard()).ok()?;
+                Some((seg_key.value().1, segment))
+            })
+            .collect();
+
+        for (seq, segment) in existing {
+            for token in content_tokens(&segment.text) {
+                content_table.remove(token.as_str(), (key, seq))?;
+                if content_table.get(token.as_str())?.is_empty() {
+                    for trigram in utils::trigrams(&token) {
+                        content_trigram_table.remove(trigram.as_str(), token.as_str())?;
+                    }
+                }
+            }
+            segments_table.remove((key, seq))?;
+        }
+        Ok(())
+    }
+
+    /// Looks the normalized query up in the content index of a location and passes the hits to
+    /// `on_hits` in chunks, in file order. Every query token must occur in a word of the same
+    /// segment, as live search requires it to occur in the segment text. Returns `false` when the
+    /// location has no content index.
+    /// Stops early once `controller` is cancelled.
+    pub fn search_content_in_table<F>(
+        &self,
+        table_name: &str,
+        query: &str,
+        context_lines: usize,
+        controller: &TaskController,
+        mut on_hits: F,
+    ) -> anyhow::Result<bool>
+    where
+        F: FnMut(Vec<ContentHit>) -> anyhow::Result<()>,
+    {
+        let content_name = content_table_name(table_name);
+        let content_def: MultimapTableDefinition<&str, (&str, u32)> = MultimapTableDefinition::new(&content_name);
+        let content_trigram_name = content_trigram_table_name(table_name);
+        let content_trigram_def: MultimapTableDefinition<&str, &str> = MultimapTableDefinition::new(&content_trigram_name);
+        let segments_name = segments_table_name(table_name);
+        let segments_def: TableDefinition<(&str, u32), &[u8]> = TableDefinition::new(&segments_name);
+
+        let txn = self.db.begin_read()?;
+        let content_table = match txn.open_multimap_table(content_def) {
+            Ok(table) => table,
+            Err(TableError::TableDoesNotExist(_)) => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+        let content_trigram_table = txn.open_multimap_table(content_trigram_def)?;
+        let segments_table = txn.open_table(segments_def)?;
 
-        // Token-based search: split the query into words
         let query_tokens: Vec<&str> = query.split_whitespace().collect();
         if query_tokens.is_empty() {
-            return Ok(Vec::new());
+            return Ok(true);
+        }
+
+        // Tokens can match anywhere inside a word, so each one collects the postings of every word containing it.
+        let mut postings: Vec<HashSet<(String, u32)>> = Vec::with_capacity(query_tokens.len());
+        for token in &query_tokens {
+            let words = Self::words_containing(&content_table, &content_trigram_table, token, controller)?;
+            let mut token_postings = HashSet::new();
+            for word in words {
+                if controller.is_cancelled() {
+                    return Ok(true);
+                }
+                for value in content_table.get(word.as_str())? {
+                    let value = value?;
+                    let (key, seq) = value.value();
+                    token_postings.insert((key.to_string(), seq));
+                }
+            }
+            postings.push(token_postings);
         }
 
-        let results = table.iter()?
-            .par_bridge()
-            .filter_map(|item_result| {
-                let (key, value) = item_result.ok()?;
+        postings.sort_by_key(HashSet::len);
+        let mut postings = postings.into_iter();
+        let mut candidates = postings.next().unwrap_or_default();
+        for other in postings {
+            candidates.retain(|posting| other.contains(posting));
+        }
+
+        let mut candidates: Vec<(String, u32)> = candidates.into_iter().collect();
+        candidates.sort_unstable();
+
+        let context_lines = u32::try_from(context_lines).unwrap_or(u32::MAX);
+        let texts = |range: std::ops::Range<u32>, key: &str| -> anyhow::Result<Vec<String>> {
+            let mut texts = Vec::new();
+            for item in segments_table.range((key, range.start)..(key, range.end))? {
+                let (_, value) = item?;
+                if let Ok((segment, _len)) = bincode::decode_from_slice::<ContentSegment, _>(value.value(), bincode::config::standard()) {
+                    texts.push(segment.text);
+                }
+            }
+            Ok(texts)
+        };
+
+        let mut hits = Vec::with_capacity(CONTENT_CHUNK_SIZE);
+        for (key, seq) in candidates {
+            if controller.is_cancelled() {
+                return Ok(true);
+            }
+            if let Some(value) = segments_table.get((key.as_str(), seq))? {
+                if let Ok((segment, _len)) = bincode::decode_from_slice::<ContentSegment, _>(value.value(), bincode::config::standard()) {
+                    // Segments of a file are numbered consecutively, so its neighbours are the seqs around it.
+                    let context_before = texts(seq.saturating_sub(context_lines)..seq, &key)?;
+                    let context_after = texts(seq + 1..seq.saturating_add(context_lines).saturating_add(1), &key)?;
+                    hits.push(ContentHit { key, segment, context_before, context_after });
+                }
+            }
+            if hits.len() >= CONTENT_CHUNK_SIZE {
+                on_hits(std::mem::take(&mut hits))?;
+            }
+        }
+        if !hits.is_empty() {
+            on_hits(hits)?;
+        }
+        Ok(true)
+    }
+
+    /// Returns the words of a content index that contain `token`, or what was found so far once
+    /// `controller` is cancelled. Only the words listed under all trigrams of the token can
+    /// contain it; tokens too short for a trigram check every word.
+    fn words_containing(
+        content_table: &redb::ReadOnlyMultimapTable<&str, (&str, u32)>,
+        content_trigram_table: &redb::ReadOnlyMultimapTable<&str, &str>,
+        token: &str,
+        controller: &TaskController,
+    ) -> anyhow::Result<Vec<String>> {
+        let trigrams = utils::trigrams(token);
+        let mut words = Vec::new();
+        if trigrams.is_empty() {
+            for item in content_table.iter()? {
+                if controller.is_cancelled() {
+                    break;
+                }
+                let (word, _) = item?;
+                if word.value().contains(token) {
+                    words.push(word.value().to_string());
+                }
+            }
+            return Ok(words);
+        }
+
+        let mut word_lists = Vec::with_capacity(trigrams.len());
+        for trigram in &trigrams {
+            word_lists.push(content_trigram_table.get(trigram.as_str())?);
+        }
+        // Intersecting from the smallest list keeps the candidate set as small as possible.
+        word_lists.sort_by_key(redb::MultimapValue::len);
+        let mut word_lists = word_lists.into_iter();
+        words = word_lists.next().into_iter().flatten().filter_map(Result::ok).map(|word| word.value().to_string()).collect();
+        for list in word_lists {
+            if words.is_empty() || controller.is_cancelled() {
+                break;
+            }
+            words = intersect_sorted(words, list.filter_map(Result::ok).map(|word| word.value().to_string()));
+        }
+        // The trigrams of a word do not have to be adjacent the way they are in the token.
+        words.retain(|word| word.contains(token));
+        Ok(words)
+    }
+
+    /// Returns whether the location stored in `table_name` was indexed with content hashes.
+    pub fn has_hash_index(&self, table_name: &str) -> anyhow::Result<bool> {
+        let hashes_name = hashes_table_name(table_name);
+        let hashes_def: TableDefinition<&str, &[u8]> = TableDefinition::new(&hashes_name);
+        let txn = self.db.begin_read()?;
+        match txn.open_table(hashes_def) {
+            Ok(_) => Ok(true),
+            Err(TableError::TableDoesNotExist(_)) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Loads the stored hashes of the given keys. Keys without a hash are left out.
+    pub fn get_file_hashes(&self, table_name: &str, keys: &[&str]) -> anyhow::Result<HashMap<String, FileHash>> {
+        let hashes_name = hashes_table_name(table_name);
+        let hashes_def: TableDefinition<&str, &[u8]> = TableDefinition::new(&hashes_name);
+        let txn = self.db.begin_read()?;
+        let hashes_table = match txn.open_table(hashes_def) {
+            Ok(table) => table,
+            Err(TableError::TableDoesNotExist(_)) => return Ok(HashMap::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut hashes = HashMap::new();
+        for key in keys {
+            if let Some(value) = hashes_table.get(*key)? {
+                if let Ok((hash, _len)) = bincode::decode_from_slice::<FileHash, _>(value.value(), bincode::config::standard()) {
+                    hashes.insert((*key).to_string(), hash);
+                }
+            }
+        }
+        Ok(hashes)
+    }
+
+    /// Loads every stored hash of a location, keyed by relative path.
+    pub fn get_all_hashes(&self, table_name: &str) -> anyhow::Result<Vec<(String, FileHash)>> {
+        let hashes_name = hashes_table_name(table_name);
+        let hashes_def: TableDefinition<&str, &[u8]> = TableDefinition::new(&hashes_name);
+        let txn = self.db.begin_read()?;
+        let hashes_table = match txn.open_table(hashes_def) {
+            Ok(table) => table,
+            Err(TableError::TableDoesNotExist(_)) => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut hashes = Vec::new();
+        for item in hashes_table.iter()? {
+            let (key, value) = item?;
+            if let Ok((hash, _len)) = bincode::decode_from_slice::<FileHash, _>(value.value(), bincode::config::standard()) {
+                hashes.push((key.value().to_string(), hash));
+            }
+        }
+        Ok(hashes)
+    }
+
+    /// Stores the hashes of the given files. Called even with no files so that the table exists
+    /// and marks the location as hashed.
+    pub fn write_hashes(&self, table_name: &str, hashes: &[(String, FileHash)]) -> anyhow::Result<()> {
+        let hashes_name = hashes_table_name(table_name);
+        let hashes_def: TableDefinition<&str, &[u8]> = TableDefinition::new(&hashes_name);
+        let txn = self.db.begin_write()?;
+        {
+            let mut hashes_table = txn.open_table(hashes_def)?;
+            for (key, hash) in hashes {
+                let value = bincode::encode_to_vec(hash, bincode::config::standard())?;
+                hashes_table.insert(key.as_str(), &value[..])?;
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Drops the hashes of the given files, if the location has a hash index.
+    pub fn remove_hashes(&self, table_name: &str, keys: &[String]) -> anyhow::Result<()> {
+        if !self.has_hash_index(table_name)? {
+            return Ok(());
+        }
+        let hashes_name = hashes_table_name(table_name);
+        let hashes_def: TableDefinition<&str, &[u8]> = TableDefinition::new(&hashes_name);
+        let txn = self.db.begin_write()?;
+        {
+            let mut hashes_table = txn.open_table(hashes_def)?;
+            for key in keys {
+                hashes_table.remove(key.as_str())?;
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Passes the records of the table of `location` that match `matcher`, with their match score,
+    /// to `on_results` in chunks, each sorted best first. Queries with required words only verify
+    /// the files listed under every query trigram; others check every record.
+    /// Stops early once `controller` is cancelled.
+    pub fn search_in_table<F>(
+        &self,
+        table_name: &str,
+        location: &str,
+        matcher: &NameMatcher,
+        controller: &TaskController,
+        mut on_results: F,
+    ) -> anyhow::Result<()>
+    where
+        F: FnMut(Vec<(String, FileMetadata, f32)>) -> anyhow::Result<()>,
+    {
+        let table_def: TableDefinition<&str, &[u8]> = TableDefinition::new(table_name);
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table(table_def)?;
+
+        if matcher.is_empty() {
+            return Ok(());
+        }
+        let needs_paths = matcher.needs_paths();
 
-                let value_bytes = value.value();
-                if let Ok((metadata, _len)) = bincode::decode_from_slice::<FileMetadata, _>(value_bytes, bincode::config::standard()) {
-                    // Check if all tokens are present in the normalized name
-                    if utils::contains_all_tokens(&metadata.normalized_name, &query_tokens) {
-                        return Some(key.value().to_string());
+        // Fast path: only verify the files listed under every query trigram.
+        let trigram_name = trigram_table_name(table_name);
+        let trigram_def: MultimapTableDefinition<&str, u64> = MultimapTableDefinition::new(&trigram_name);
+        let keys_name = file_keys_table_name(table_name);
+        let keys_def: TableDefinition<u64, &str> = TableDefinition::new(&keys_name);
+        let query_trigrams: Vec<String> = matcher.required_words().iter().flat_map(|word| utils::trigrams(word)).collect();
+
+        if !query_trigrams.is_empty() {
+            match txn.open_multimap_table(trigram_def) {
+                Ok(trigram_table) => {
+                    let mut unique_trigrams: Vec<&str> = query_trigrams.iter().map(String::as_str).collect();
+                    unique_trigrams.sort_unstable();
+                    unique_trigrams.dedup();
+                    let mut posting_lists = Vec::with_capacity(unique_trigrams.len());
+                    for trigram in unique_trigrams {
+                        posting_lists.push(trigram_table.get(trigram)?);
+                    }
+                    // Intersecting from the smallest list keeps the candidate set as small as possible.
+                    posting_lists.sort_by_key(redb::MultimapValue::len);
+
+                    let mut posting_lists = posting_lists.into_iter();
+                    let mut candidates: Vec<u64> = posting_lists
+                        .next()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(Result::ok)
+                        .map(|id| id.value())
+                        .collect();
+                    for postings in posting_lists {
+                        if candidates.is_empty() || controller.is_cancelled() {
+                            break;
+                        }
+                        candidates = intersect_sorted(candidates, postings.filter_map(Result::ok).map(|id| id.value()));
+                    }
+
+                    // Only the files left after the intersection are resolved to their key and record.
+                    let keys_table = txn.open_table(keys_def)?;
+                    let mut paths = PathNormalizer::default();
+                    for chunk in candidates.chunks(SEARCH_CHUNK_SIZE) {
+                        if controller.is_cancelled() {
+                            break;
+                        }
+                        let mut results = Vec::new();
+                        for &id in chunk {
+                            let Some(key) = keys_table.get(id)? else {
+                                continue;
+                            };
+                            let key = key.value();
+                            if let Some(value) = table.get(key)? {
+                                if let Ok((metadata, _len)) = bincode::decode_from_slice::<FileMetadata, _>(value.value(), bincode::config::standard()) {
+                                    let normalized_path = if needs_paths { paths.normalize(key, &metadata.normalized_name) } else { String::new() };
+                                    if let Some(score) = matcher.score(&Candidate { location, relative_path: key, metadata: &metadata, normalized_path: &normalized_path }) {
+                                        results.push((key.to_string(), metadata, score));
+                                    }
+                                }
+                            }
+                        }
+                        if !results.is_empty() {
+                            sort_by_score(&mut results);
+                            on_results(results)?;
+                        }
                     }
+                    return Ok(());
                 }
-                None
+                // Indexes built before trigram support fall back to a full scan.
+                Err(TableError::TableDoesNotExist(_)) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let mut items = table.iter()?;
+        while !controller.is_cancelled() {
+            let chunk = items.by_ref().take(SEARCH_CHUNK_SIZE).collect::<Result<Vec<_>, _>>()?;
+            if chunk.is_empty() {
+                break;
+            }
+            let mut results: Vec<_> = chunk
+                .into_par_iter()
+                // Each worker keeps its own folder cache for path matching.
+                .map_init(PathNormalizer::default, |paths, (key, value)| {
+                    let (metadata, _len) = bincode::decode_from_slice::<FileMetadata, _>(value.value(), bincode::config::standard()).ok()?;
+                    let normalized_path = if needs_paths { paths.normalize(key.value(), &metadata.normalized_name) } else { String::new() };
+                    let score = matcher.score(&Candidate { location, relative_path: key.value(), metadata: &metadata, normalized_path: &normalized_path })?;
+                    Some((key.value().to_string(), metadata, score))
+                })
+                .flatten()
+                .collect();
+            if !results.is_empty() {
+                sort_by_score(&mut results);
+                on_results(results)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops `index_*` tables that no location references (left behind by interrupted rescans),
+    /// checks that every location points to an existing, decodable table and compacts the file.
+    pub fn verify_and_repair(&mut self, reporter: Option<&GuiSender>) -> anyhow::Result<RepairReport> {
+        let mut report = RepairReport::default();
+        let locations = self.get_all_locations()?;
+        let referenced: HashSet<String> = locations
+            .iter()
+            .flat_map(|(_, table_name)| {
+                [
+                    table_name.clone(),
+                    trigram_table_name(table_name),
+                    file_ids_table_name(table_name),
+                    file_keys_table_name(table_name),
+                    content_table_name(table_name),
+                    content_trigram_table_name(table_name),
+                    segments_table_name(table_name),
+                    content_extensions_table_name(table_name),
+                    hashes_table_name(table_name),
+                ]
             })
             .collect();
 
-        Ok(results)
+        // --- Orphan tables ---
+        utils::report_progress(reporter, 0.1, "🩺 Looking for orphan tables...");
+        let txn = self.db.begin_write()?;
+        {
+            let tables: Vec<_> = txn.list_tables()?.filter(|t| t.name().starts_with("index_") && !referenced.contains(t.name())).collect();
+            for table in tables {
+                report.dropped_tables.push(table.name().to_string());
+                txn.delete_table(table)?;
+            }
+            let multimap_tables: Vec<_> = txn.list_multimap_tables()?.filter(|t| t.name().starts_with("index_") && !referenced.contains(t.name())).collect();
+            for table in multimap_tables {
+                report.dropped_tables.push(table.name().to_string());
+                txn.delete_multimap_table(table)?;
+            }
+
+            let location_paths: HashSet<&str> = locations.iter().map(|(path, _)| path.as_str()).collect();
+            let mut partial_table = txn.open_table(PARTIAL_SCANS_TABLE)?;
+            let stale: Vec<String> = partial_table.iter()?
+                .filter_map(Result::ok)
+                .map(|(path, _)| path.value().to_string())
+                .filter(|path| !location_paths.contains(path.as_str()))
+                .collect();
+            let mut checkpoints_table = txn.open_multimap_table(SCAN_CHECKPOINTS_TABLE)?;
+            for path in &stale {
+                partial_table.remove(path.as_str())?;
+                checkpoints_table.remove_all(path.as_str())?;
+            }
+            report.stale_scan_states = stale;
+        }
+        txn.commit()?;
+
+        // --- Location tables ---
+        for (i, (path, table_name)) in locations.iter().enumerate() {
+            #[allow(clippy::cast_precision_loss)]
+            utils::report_progress(reporter, 0.2 + 0.6 * (i as f32 / locations.len() as f32), &format!("🩺 Verifying {path}..."));
+            let table_def: TableDefinition<&str, &[u8]> = TableDefinition::new(table_name);
+            let txn = self.db.begin_read()?;
+            let table = match txn.open_table(table_def) {
+                Ok(table) => table,
+                Err(TableError::TableDoesNotExist(_)) => {
+                    report.missing_tables.push(path.clone());
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            let mut undecodable_count = 0;
+            let mut samples = Vec::new();
+            for item in table.iter()? {
+                let (key, value) = item?;
+                if decode_current(value.value()).is_none() {
+                    undecodable_count += 1;
+                    if samples.len() < UNDECODABLE_SAMPLE_SIZE {
+                        samples.push(key.value().to_string());
+                    }
+                }
+            }
+            if undecodable_count > 0 {
+                report.undecodable_rows.push((path.clone(), undecodable_count, samples));
+            }
+        }
+
+        // --- Compaction ---
+        utils::report_progress(reporter, 0.9, "🩺 Compacting the index file...");
+        report.compacted = self.db.compact()?;
+
+        Ok(report)
+    }
+
+    /// Marks the initial scan of `root_path` as in progress. A fresh scan forgets the
+    /// checkpoints of any earlier attempt; a resumed scan keeps them.
+    pub fn begin_scan(&self, root_path: &str, resume: bool) -> anyhow::Result<()> {
+        let started_at = utils::system_time_to_secs(std::time::SystemTime::now());
+        let txn = self.db.begin_write()?;
+        {
+            let mut partial_table = txn.open_table(PARTIAL_SCANS_TABLE)?;
+            if !resume || partial_table.get(root_path)?.is_none() {
+                partial_table.insert(root_path, started_at)?;
+            }
+            if !resume {
+                txn.open_multimap_table(SCAN_CHECKPOINTS_TABLE)?.remove_all(root_path)?;
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Records top-level subdirectories whose entries are all written to the index.
+    pub fn add_scan_checkpoints(&self, root_path: &str, subdirs: &[String]) -> anyhow::Result<()> {
+        let txn = self.db.begin_write()?;
+        {
+            let mut checkpoints_table = txn.open_multimap_table(SCAN_CHECKPOINTS_TABLE)?;
+            for subdir in subdirs {
+                checkpoints_table.insert(root_path, subdir.as_str())?;
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    pub fn get_scan_checkpoints(&self, root_path: &str) -> anyhow::Result<HashSet<String>> {
+        let txn = self.db.begin_read()?;
+        let checkpoints_table = txn.open_multimap_table(SCAN_CHECKPOINTS_TABLE)?;
+        let mut subdirs = HashSet::new();
+        for subdir in checkpoints_table.get(root_path)? {
+            subdirs.insert(subdir?.value().to_string());
+        }
+        Ok(subdirs)
+    }
+
+    /// Clears the in-progress mark and the checkpoints once the initial scan has completed.
+    pub fn finish_scan(&self, root_path: &str) -> anyhow::Result<()> {
+        let txn = self.db.begin_write()?;
+        {
+            txn.open_table(PARTIAL_SCANS_TABLE)?.remove(root_path)?;
+            txn.open_multimap_table(SCAN_CHECKPOINTS_TABLE)?.remove_all(root_path)?;
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Returns the root paths whose initial scan was cancelled or interrupted.
+    pub fn get_partial_scans(&self) -> anyhow::Result<HashSet<String>> {
+        let txn = self.db.begin_read()?;
+        let partial_table = txn.open_table(PARTIAL_SCANS_TABLE)?;
+        Ok(partial_table.iter()?.filter_map(Result::ok).map(|(path, _)| path.value().to_string()).collect())
     }
 
     pub fn get_table_name(&self, root_path: &str) -> anyhow::Result<Option<String>> {
@@ -131,7 +1444,12 @@ impl DbManager {
             if let Some(name) = maybe_name {
                 final_table_name = name;
             } else {
-                let new_name = format!("index_{:x}", md5::compute(root_path.as_bytes()));
+                let mut new_name = format!("index_{:x}", md5::compute(root_path.as_bytes()));
+                // A relocated location keeps the table named after its former root.
+                let name_taken = locations_table.iter()?.filter_map(Result::ok).any(|(_, name)| name.value() == new_name);
+                if name_taken {
+                    new_name = format!("{new_name}_{}", SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?.as_secs());
+                }
                 locations_table.insert(root_path, new_name.as_str())?;
                 final_table_name = new_name;
             }
@@ -147,6 +1465,127 @@ impl DbManager {
         self.create_table_name(root_path)
     }
 
+    /// Points `root_path` at an already written `index_*` table. An existing location is only
+    /// replaced when `overwrite` is set; its old tables and scan state are then deleted.
+    pub fn register_location_table(&self, root_path: &str, table_name: &str, overwrite: bool) -> anyhow::Result<()> {
+        let txn = self.db.begin_write()?;
+        {
+            let mut locations_table = txn.open_table(LOCATIONS_TABLE)?;
+            let old_table_name = locations_table.get(root_path)?.map(|guard| guard.value().to_string());
+            if let Some(old_table_name) = old_table_name {
+                if !overwrite {
+                    anyhow::bail!("The location '{root_path}' is already indexed");
+                }
+                Self::delete_index_tables(&txn, &old_table_name)?;
+            }
+            locations_table.insert(root_path, table_name)?;
+
+            txn.open_table(PARTIAL_SCANS_TABLE)?.remove(root_path)?;
+            txn.open_multimap_table(SCAN_CHECKPOINTS_TABLE)?.remove_all(root_path)?;
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Returns up to `count` keys spread over an `index_*` table without reading all of it: one key
+    /// per top-level entry of the location, found by seeking past each subtree, topped up from a
+    /// bounded walk over the start of the table.
+    pub fn sample_keys(&self, table_name: &str, count: usize) -> anyhow::Result<Vec<String>> {
+        let table_def: TableDefinition<&str, &[u8]> = TableDefinition::new(table_name);
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table(table_def)?;
+        let mut keys = Vec::with_capacity(count);
+
+        // Keys below a top-level entry sort between "name" and "name" followed by the character
+        // after the separator, so seeking to the latter skips the whole subtree.
+        let after_separator = char::from(std::path::MAIN_SEPARATOR as u8 + 1);
+        let mut cursor = String::new();
+        while keys.len() < count {
+            let Some(item) = table.range::<&str>(cursor.as_str()..)?.next() else { break };
+            let key = item?.0.value().to_string();
+            let top_level = key.split(std::path::MAIN_SEPARATOR).next().unwrap_or_default();
+            cursor = format!("{top_level}{after_separator}");
+            keys.push(key);
+        }
+
+        let missing = count.saturating_sub(keys.len());
+        let walk = usize::try_from(table.len()?)?.min(SAMPLE_WALK_LIMIT);
+        if let Some(step) = walk.checked_div(missing) {
+            let mut extra = Vec::with_capacity(missing);
+            for item in table.iter()?.take(walk).step_by(step.max(1)) {
+                let key = item?.0.value().to_string();
+                if !keys.contains(&key) {
+                    extra.push(key);
+                }
+                if extra.len() == missing {
+                    break;
+                }
+            }
+            keys.extend(extra);
+        }
+        Ok(keys)
+    }
+
+    /// Checks that enough of the indexed paths of `old_root` exist under `new_root` and, if so,
+    /// relocates the location. Returns how many of the sampled paths were found.
+    pub fn verify_and_relocate(&self, old_root: &str, new_root: &Path) -> anyhow::Result<(usize, usize)> {
+        if !new_root.is_dir() {
+            anyhow::bail!("'{}' is not a folder", new_root.display());
+        }
+        let new_root_str = new_root.to_str()
+            .ok_or_else(|| anyhow::anyhow!("'{}' is not a valid path", new_root.display()))?;
+        let table_name = self.get_table_name(old_root)?
+            .ok_or_else(|| anyhow::anyhow!("The location '{old_root}' is not indexed"))?;
+
+        let samples = self.sample_keys(&table_name, RELOCATION_SAMPLE_SIZE)?;
+        let found = samples.iter().filter(|key| new_root.join(key).exists()).count();
+        #[allow(clippy::cast_precision_loss)]
+        let found_ratio = if samples.is_empty() { 1.0 } else { found as f64 / samples.len() as f64 };
+        if found_ratio < RELOCATION_MIN_FOUND_RATIO {
+            anyhow::bail!(
+                "Only {found} of {} sampled files of '{old_root}' exist under '{}'. Check that it is the same folder",
+                samples.len(),
+                new_root.display()
+            );
+        }
+
+        self.relocate_location(old_root, new_root_str)?;
+        Ok((found, samples.len()))
+    }
+
+    /// Moves a location to a new root path. The `index_*` table is kept as is, since its keys are
+    /// relative to the root; only the `LOCATIONS_TABLE` entry and the scan state are rewritten.
+    pub fn relocate_location(&self, old_root: &str, new_root: &str) -> anyhow::Result<()> {
+        let txn = self.db.begin_write()?;
+        {
+            let mut locations_table = txn.open_table(LOCATIONS_TABLE)?;
+            if locations_table.get(new_root)?.is_some() {
+                anyhow::bail!("The location '{new_root}' is already indexed");
+            }
+            let table_name = locations_table.remove(old_root)?
+                .map(|guard| guard.value().to_string())
+                .ok_or_else(|| anyhow::anyhow!("The location '{old_root}' is not indexed"))?;
+            locations_table.insert(new_root, table_name.as_str())?;
+
+            let mut partial_table = txn.open_table(PARTIAL_SCANS_TABLE)?;
+            let started_at = partial_table.remove(old_root)?.map(|guard| guard.value());
+            if let Some(started_at) = started_at {
+                partial_table.insert(new_root, started_at)?;
+            }
+
+            let mut checkpoints_table = txn.open_multimap_table(SCAN_CHECKPOINTS_TABLE)?;
+            let checkpoints: Vec<String> = checkpoints_table.remove_all(old_root)?
+                .filter_map(Result::ok)
+                .map(|subdir| subdir.value().to_string())
+                .collect();
+            for subdir in &checkpoints {
+                checkpoints_table.insert(new_root, subdir.as_str())?;
+            }
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
     pub fn swap_location_table(&self, root_path: &str, new_table_name: &str) -> anyhow::Result<String> {
         let txn = self.db.begin_write()?;
         let old_table_name;
@@ -164,3 +1603,391 @@ impl DbManager {
         Ok(old_table_name)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pop::control::TaskController;
+    use crate::query::QueryMode;
+
+    /// A database file in the temp directory, removed when dropped.
+    struct TempDb(PathBuf);
+
+    impl TempDb {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("deepsearch_test_{}_{name}.redb", std::process::id()));
+            std::fs::remove_file(&path).ok();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDb {
+        fn drop(&mut self) {
+            std::fs::remove_file(&self.0).ok();
+        }
+    }
+
+    fn encode<T: Encode>(value: T) -> Vec<u8> {
+        bincode::encode_to_vec(value, bincode::config::standard()).unwrap()
+    }
+
+    fn current_record(name: &str, modified_time: u64) -> FileMetadata {
+        FileMetadata {
+            normalized_name: utils::normalize_string(name),
+            modified_time,
+            size: 42,
+            created_time: 7,
+            is_dir: false,
+            extension: "md".to_string(),
+            is_read_only: false,
+            is_hidden: false,
+        }
+    }
+
+    /// Writes a file the way older releases did before versioning: no `meta` table, no trigram
+    /// tables, v1 records (name + modified time) and v1 content segments (number + text).
+    fn write_unversioned_database(path: &Path) {
+        let db = Database::create(path).unwrap();
+        let txn = db.begin_write().unwrap();
+        {
+            let mut locations = txn.open_table(LOCATIONS_TABLE).unwrap();
+            locations.insert("/data", "index_old").unwrap();
+
+            let table_def: TableDefinition<&str, &[u8]> = TableDefinition::new("index_old");
+            let mut table = txn.open_table(table_def).unwrap();
+            table.insert("docs/Report.PDF", &encode(("report.pdf".to_string(), 100_u64))[..]).unwrap();
+            table.insert("sheet.xlsx", &encode(("sheet.xlsx".to_string(), 200_u64))[..]).unwrap();
+            table.insert(".hidden", &encode((".hidden".to_string(), 300_u64))[..]).unwrap();
+            // Left by an interrupted migration: already in the current layout.
+            table.insert("notes.md", &encode(current_record("notes.md", 400))[..]).unwrap();
+            table.insert("broken", &[0xFF][..]).unwrap();
+
+            let content_def: MultimapTableDefinition<&str, (&str, u32)> = MultimapTableDefinition::new("index_old_content");
+            let mut content = txn.open_multimap_table(content_def).unwrap();
+            content.insert("budget", ("sheet.xlsx", 0)).unwrap();
+            content.insert("quarterly", ("docs/Report.PDF", 0)).unwrap();
+
+            let segments_def: TableDefinition<(&str, u32), &[u8]> = TableDefinition::new("index_old_segments");
+            let mut segments = txn.open_table(segments_def).unwrap();
+            segments.insert(("sheet.xlsx", 0), &encode((3_u64, "Budget 2024".to_string()))[..]).unwrap();
+            segments.insert(("docs/Report.PDF", 0), &encode((2_u64, "Quarterly report".to_string()))[..]).unwrap();
+            segments.insert(("docs/Report.PDF", 1), &[0xFF][..]).unwrap();
+        }
+        txn.commit().unwrap();
+    }
+
+    fn records(db_manager: &DbManager, table_name: &str) -> HashMap<String, FileMetadata> {
+        let mut records = HashMap::new();
+        db_manager.for_each_record(table_name, |key, metadata| {
+            records.insert(key.to_string(), metadata);
+            Ok(())
+        }).unwrap();
+        records
+    }
+
+    /// Collects the content hits of a query, or `None` when the location has no content index.
+    fn content_hits(db_manager: &DbManager, table_name: &str, query: &str) -> Option<Vec<ContentHit>> {
+        let mut hits = Vec::new();
+        let has_index = db_manager.search_content_in_table(table_name, query, 0, &TaskController::new(), |found| {
+            hits.extend(found);
+            Ok(())
+        }).unwrap();
+        has_index.then_some(hits)
+    }
+
+    fn content_location(db_manager: &DbManager, query: &str) -> SegmentLocation {
+        let hits = content_hits(db_manager, "index_old", query).unwrap();
+        assert_eq!(hits.len(), 1, "hits for {query}");
+        hits[0].segment.location.clone()
+    }
+
+    #[test]
+    fn new_database_is_up_to_date() {
+        let temp = TempDb::new("new");
+        assert_eq!(DbManager::open_and_migrate(&temp.0, None).unwrap(), MigrationOutcome::UpToDate);
+        let db_manager = DbManager::new(&temp.0).unwrap();
+        assert_eq!(db_manager.get_versions().unwrap(), Some((SCHEMA_VERSION, RECORD_VERSION, CONTENT_VERSION)));
+    }
+
+    #[test]
+    fn unversioned_database_is_migrated() {
+        let temp = TempDb::new("unversioned");
+        write_unversioned_database(&temp.0);
+
+        let outcome = DbManager::open_and_migrate(&temp.0, None).unwrap();
+        assert_eq!(outcome, MigrationOutcome::Migrated {
+            from_version: 1,
+            locations: vec!["/data".to_string()],
+            from_content_version: 1,
+            content_locations: vec!["/data".to_string()],
+        });
+
+        let db_manager = DbManager::new(&temp.0).unwrap();
+        assert_eq!(db_manager.get_versions().unwrap(), Some((SCHEMA_VERSION, RECORD_VERSION, CONTENT_VERSION)));
+
+        let records = records(&db_manager, "index_old");
+        assert_eq!(records.len(), 4, "the undecodable row is skipped");
+        let report = &records["docs/Report.PDF"];
+        assert_eq!(report.normalized_name, "report.pdf");
+        assert_eq!(report.modified_time, 100);
+        assert_eq!(report.extension, "pdf");
+        assert_eq!(report.size, 0);
+        assert!(records[".hidden"].is_hidden);
+        assert_eq!(records["notes.md"], current_record("notes.md", 400));
+
+        // The rewrite also filled the trigram table used by indexed search.
+        let matcher = NameMatcher::compile(QueryMode::Tokens, "report", false, false).unwrap();
+        let mut keys = Vec::new();
+        db_manager.search_in_table("index_old", "/data", &matcher, &TaskController::new(), |results| {
+            keys.extend(results.into_iter().map(|(key, _, _)| key));
+            Ok(())
+        }).unwrap();
+        assert_eq!(keys, ["docs/Report.PDF"]);
+
+        assert_eq!(content_location(&db_manager, "budget"), SegmentLocation::Row { sheet: String::new(), row: 3 });
+        assert_eq!(content_location(&db_manager, "quarterly"), SegmentLocation::Page(2));
+    }
+
+    #[test]
+    fn migration_runs_once() {
+        let temp = TempDb::new("once");
+        write_unversioned_database(&temp.0);
+        DbManager::open_and_migrate(&temp.0, None).unwrap();
+
+        // The content index predates the stored format list, so newer formats are reported
+        // instead of migrating again.
+        let MigrationOutcome::ContentFormatsAdded { extensions, locations } = DbManager::open_and_migrate(&temp.0, None).unwrap() else {
+            panic!("expected the content formats to be reported");
+        };
+        assert!(extensions.iter().any(|extension| extension == "pptx"));
+        assert!(!extensions.iter().any(|extension| extension == "pdf"));
+        assert_eq!(locations, ["/data"]);
+
+        let db_manager = DbManager::new(&temp.0).unwrap();
+        assert_eq!(content_location(&db_manager, "budget"), SegmentLocation::Row { sheet: String::new(), row: 3 });
+        db_manager.set_content_extensions("index_old", &content::extractors().extensions()).unwrap();
+        drop(db_manager);
+        assert_eq!(DbManager::open_and_migrate(&temp.0, None).unwrap(), MigrationOutcome::UpToDate);
+    }
+
+    #[test]
+    fn indexed_content_matches_inside_words() {
+        let temp = TempDb::new("content");
+        let db_manager = DbManager::new(&temp.0).unwrap();
+        let segment = |text: &str| ContentSegment { location: SegmentLocation::Line(1), text: text.to_string() };
+        db_manager.write_content("index_content", &[
+            ("a.txt".to_string(), vec![segment("Báo cáo Thành phố")]),
+            ("b.txt".to_string(), vec![segment("Thanh toán")]),
+        ]).unwrap();
+
+        let keys = |query: &str| {
+            let mut keys: Vec<String> = content_hits(&db_manager, "index_content", query).unwrap().into_iter().map(|hit| hit.key).collect();
+            keys.sort();
+            keys
+        };
+        assert_eq!(keys("hanh"), ["a.txt", "b.txt"]);
+        assert_eq!(keys("hanh pho"), ["a.txt"]);
+        assert_eq!(keys("oan"), ["b.txt"]);
+        assert!(keys("phone").is_empty());
+        // Tokens too short for a trigram still match inside words.
+        assert_eq!(keys("ao"), ["a.txt"]);
+        assert_eq!(keys("h"), ["a.txt", "b.txt"]);
+    }
+
+    #[test]
+    fn removed_content_leaves_the_vocabulary() {
+        let temp = TempDb::new("vocabulary");
+        let db_manager = DbManager::new(&temp.0).unwrap();
+        let segment = |text: &str| ContentSegment { location: SegmentLocation::Line(1), text: text.to_string() };
+        db_manager.write_content("index_vocabulary", &[
+            ("a.txt".to_string(), vec![segment("invoice total")]),
+            ("b.txt".to_string(), vec![segment("invoice draft")]),
+        ]).unwrap();
+        db_manager.write_content("index_vocabulary", &[("a.txt".to_string(), vec![segment("receipt")])]).unwrap();
+        db_manager.remove_content("index_vocabulary", &["b.txt".to_string()]).unwrap();
+
+        let txn = db_manager.db.begin_read().unwrap();
+        let name = content_trigram_table_name("index_vocabulary");
+        let content_trigrams = txn.open_multimap_table(MultimapTableDefinition::<&str, &str>::new(&name)).unwrap();
+        let mut words: Vec<String> = content_trigrams.iter().unwrap()
+            .flat_map(|item| item.unwrap().1.map(|word| word.unwrap().value().to_string()))
+            .collect();
+        words.sort();
+        words.dedup();
+        assert_eq!(words, ["receipt"]);
+        assert!(content_hits(&db_manager, "index_vocabulary", "voice").unwrap().is_empty());
+        assert_eq!(content_hits(&db_manager, "index_vocabulary", "ceip").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn content_hits_stream_in_chunks_until_cancelled() {
+        let temp = TempDb::new("content_chunks");
+        let db_manager = DbManager::new(&temp.0).unwrap();
+        let segments: Vec<_> = (1..=CONTENT_CHUNK_SIZE as u64 + 5)
+            .map(|line| ContentSegment { location: SegmentLocation::Line(line), text: format!("budget line {line}") })
+            .collect();
+        db_manager.write_content("index_chunks", &[("a.txt".to_string(), segments)]).unwrap();
+
+        let mut chunks = Vec::new();
+        db_manager.search_content_in_table("index_chunks", "budget", 0, &TaskController::new(), |hits| {
+            chunks.push(hits.len());
+            Ok(())
+        }).unwrap();
+        assert_eq!(chunks, [CONTENT_CHUNK_SIZE, 5]);
+
+        let controller = TaskController::new();
+        let mut chunks = 0;
+        db_manager.search_content_in_table("index_chunks", "budget", 0, &controller, |_| {
+            chunks += 1;
+            controller.cancel();
+            Ok(())
+        }).unwrap();
+        assert_eq!(chunks, 1);
+        assert!(content_hits(&db_manager, "index_missing", "budget").is_none());
+    }
+
+    #[test]
+    fn content_words_are_indexed_by_the_schema_upgrade() {
+        let temp = TempDb::new("schema_v2");
+        let db_manager = DbManager::new(&temp.0).unwrap();
+        let table_name = db_manager.get_or_create_table_name("/docs").unwrap();
+        db_manager.write_content(&table_name, &[
+            ("a.txt".to_string(), vec![ContentSegment { location: SegmentLocation::Line(1), text: "Quarterly budget".to_string() }]),
+        ]).unwrap();
+        // Content indexes of schema v2 have no trigram table over their words.
+        let txn = db_manager.db.begin_write().unwrap();
+        txn.delete_multimap_table(MultimapTableDefinition::<&str, &str>::new(&content_trigram_table_name(&table_name))).unwrap();
+        txn.commit().unwrap();
+        db_manager.set_versions(2, RECORD_VERSION, CONTENT_VERSION).unwrap();
+        drop(db_manager);
+
+        let outcome = DbManager::open_and_migrate(&temp.0, None).unwrap();
+        assert_eq!(outcome, MigrationOutcome::Migrated { from_version: RECORD_VERSION, locations: Vec::new(), from_content_version: CONTENT_VERSION, content_locations: Vec::new() });
+        let db_manager = DbManager::new(&temp.0).unwrap();
+        assert_eq!(db_manager.get_versions().unwrap(), Some((SCHEMA_VERSION, RECORD_VERSION, CONTENT_VERSION)));
+        assert_eq!(content_hits(&db_manager, &table_name, "udge").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn name_search_streams_chunks_until_cancelled() {
+        let temp = TempDb::new("chunks");
+        let db_manager = DbManager::new(&temp.0).unwrap();
+        let records: Vec<_> = (0..SEARCH_CHUNK_SIZE + 10).map(|i| (format!("report{i:05}.md"), current_record(&format!("report{i:05}.md"), 1))).collect();
+        db_manager.write_to_table("index_chunks", &records).unwrap();
+
+        for (mode, query) in [(QueryMode::Tokens, "report"), (QueryMode::Glob, "*.md")] {
+            let matcher = NameMatcher::compile(mode, query, false, false).unwrap();
+            let mut chunks = Vec::new();
+            db_manager.search_in_table("index_chunks", "/root", &matcher, &TaskController::new(), |results| {
+                chunks.push(results.len());
+                Ok(())
+            }).unwrap();
+            assert_eq!(chunks, [SEARCH_CHUNK_SIZE, 10], "{query}");
+
+            let controller = TaskController::new();
+            let mut chunks = 0;
+            db_manager.search_in_table("index_chunks", "/root", &matcher, &controller, |_| {
+                chunks += 1;
+                controller.cancel();
+                Ok(())
+            }).unwrap();
+            assert_eq!(chunks, 1, "{query}");
+        }
+    }
+
+    fn found_keys(db_manager: &DbManager, table_name: &str, query: &str) -> Vec<String> {
+        let matcher = NameMatcher::compile(QueryMode::Tokens, query, false, false).unwrap();
+        let mut keys = Vec::new();
+        db_manager.search_in_table(table_name, "/root", &matcher, &TaskController::new(), |results| {
+            keys.extend(results.into_iter().map(|(key, _, _)| key));
+            Ok(())
+        }).unwrap();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn name_postings_hold_file_ids() {
+        let temp = TempDb::new("file_ids");
+        let db_manager = DbManager::new(&temp.0).unwrap();
+        let records = |keys: &[&str]| keys.iter().map(|key| ((*key).to_string(), current_record(key, 1))).collect::<Vec<_>>();
+        db_manager.write_to_table("index_ids", &records(&["report.md", "docs/report 2.md"])).unwrap();
+        // Rewriting a record keeps its id.
+        db_manager.write_to_table("index_ids", &records(&["report.md"])).unwrap();
+        db_manager.remove_from_table("index_ids", &["report.md".to_string()]).unwrap();
+        db_manager.write_to_table("index_ids", &records(&["notes.md"])).unwrap();
+
+        let txn = db_manager.db.begin_read().unwrap();
+        let keys_name = file_keys_table_name("index_ids");
+        let keys_table = txn.open_table(TableDefinition::<u64, &str>::new(&keys_name)).unwrap();
+        let keys: Vec<(u64, String)> = keys_table.iter().unwrap().map(|item| {
+            let (id, key) = item.unwrap();
+            (id.value(), key.value().to_string())
+        }).collect();
+        assert_eq!(keys, [(1, "docs/report 2.md".to_string()), (2, "notes.md".to_string())]);
+        let trigram_name = trigram_table_name("index_ids");
+        let trigram_table = txn.open_multimap_table(MultimapTableDefinition::<&str, u64>::new(&trigram_name)).unwrap();
+        let ids: Vec<u64> = trigram_table.get("rep").unwrap().map(|id| id.unwrap().value()).collect();
+        assert_eq!(ids, [1]);
+
+        assert_eq!(found_keys(&db_manager, "index_ids", "report"), ["docs/report 2.md"]);
+        assert_eq!(found_keys(&db_manager, "index_ids", "notes"), ["notes.md"]);
+    }
+
+    #[test]
+    fn key_postings_are_replaced_by_file_ids() {
+        let temp = TempDb::new("schema_v3");
+        let db = Database::create(&temp.0).unwrap();
+        let txn = db.begin_write().unwrap();
+        {
+            txn.open_table(LOCATIONS_TABLE).unwrap().insert("/root", "index_v3").unwrap();
+            let mut table = txn.open_table(TableDefinition::<&str, &[u8]>::new("index_v3")).unwrap();
+            let mut trigrams = txn.open_multimap_table(MultimapTableDefinition::<&str, &str>::new("index_v3_trigrams")).unwrap();
+            for key in ["report.md", "notes.md"] {
+                let record = current_record(key, 1);
+                table.insert(key, &encode(&record)[..]).unwrap();
+                for trigram in utils::trigrams(&record.normalized_name) {
+                    trigrams.insert(trigram.as_str(), key).unwrap();
+                }
+            }
+        }
+        DbManager::write_versions(&txn, 3, RECORD_VERSION, CONTENT_VERSION).unwrap();
+        txn.commit().unwrap();
+        drop(db);
+
+        DbManager::open_and_migrate(&temp.0, None).unwrap();
+        let db_manager = DbManager::new(&temp.0).unwrap();
+        assert_eq!(db_manager.get_versions().unwrap(), Some((SCHEMA_VERSION, RECORD_VERSION, CONTENT_VERSION)));
+        assert_eq!(found_keys(&db_manager, "index_v3", "report"), ["report.md"]);
+        assert_eq!(found_keys(&db_manager, "index_v3", "md"), ["notes.md", "report.md"]);
+    }
+
+    #[test]
+    fn newer_database_is_rejected() {
+        let temp = TempDb::new("newer");
+        DbManager::new(&temp.0).unwrap().set_versions(SCHEMA_VERSION, RECORD_VERSION + 1, CONTENT_VERSION).unwrap();
+        let error = DbManager::open_and_migrate(&temp.0, None).unwrap_err();
+        assert!(error.to_string().contains("newer version"), "{error}");
+    }
+
+    #[test]
+    fn samples_cover_every_top_level_folder() {
+        let temp = TempDb::new("samples");
+        let db_manager = DbManager::new(&temp.0).unwrap();
+        let table_name = db_manager.get_or_create_table_name("/root").unwrap();
+        let separator = std::path::MAIN_SEPARATOR;
+        let records: Vec<_> = ["alpha", "beta", "gamma"].iter()
+            .flat_map(|folder| (0..100).map(move |i| format!("{folder}{separator}file{i:03}.txt")))
+            .map(|key| (key.clone(), current_record(&key, 1)))
+            .collect();
+        db_manager.write_to_table(&table_name, &records).unwrap();
+
+        let samples = db_manager.sample_keys(&table_name, 3).unwrap();
+        let folders: Vec<_> = samples.iter().map(|key| key.split(separator).next().unwrap()).collect();
+        assert_eq!(folders, ["alpha", "beta", "gamma"]);
+
+        let samples = db_manager.sample_keys(&table_name, 10).unwrap();
+        assert_eq!(samples.len(), 10);
+        assert_eq!(samples.iter().collect::<HashSet<_>>().len(), 10);
+    }
+}
@@ -1,19 +1,22 @@
+use std::collections::{HashSet, VecDeque};
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, OnceLock};
 use std::thread;
 use eframe::egui;
-use crate::db::DbManager;
+use crate::db::{DbManager, MigrationOutcome, RepairReport};
 use crate::gui::components::indexing_tab::IndexingTab;
 use crate::gui::components::menu_bar::MenuBar;
-use crate::gui::components::search_tab::SearchTab;
+use crate::gui::components::search_tab::{SavedSearches, SearchTab};
 use crate::gui::components::status_bar::StatusBar;
 use crate::pop::context::Context;
 use crate::pop::control::TaskController;
 use crate::pop::engine::Engine;
 use crate::pop::registry::Registry;
+use crate::query::QueryMode;
 use crate::processes;
-use super::events::{Command, GuiUpdate, GuiSender};
+use crate::{transfer, utils};
+use super::events::{Command, DuplicateGroup, GuiUpdate, GuiSender, RescanStats};
 
 #[derive(PartialEq, serde::Deserialize, serde::Serialize)]
 pub enum Tab {
@@ -24,22 +27,31 @@ pub enum Tab {
 // Holds the shared state of the application that multiple components might need to access or modify.
 pub struct AppState {
     pub locations: Vec<(String, String, u64)>,
+    /// Locations whose initial scan was cancelled or interrupted and can be resumed.
+    pub partial_locations: HashSet<String>,
     pub current_status: String,
     pub scan_progress: f32,
     pub is_running_task: bool,
     pub is_paused: bool,
     pub active_task_control: Option<Arc<TaskController>>,
+    /// (title, message) pairs shown one at a time in a modal window until the user dismisses them.
+    pub notices: VecDeque<(String, String)>,
+    /// The index database the worker thread has open.
+    pub active_db_path: PathBuf,
 }
 
 impl Default for AppState {
     fn default() -> Self {
         Self {
             locations: vec![],
+            partial_locations: HashSet::new(),
             current_status: "Ready. Fetching locations...".to_string(),
             scan_progress: 0.0,
             is_running_task: false,
             is_paused: false,
             active_task_control: None,
+            notices: VecDeque::new(),
+            active_db_path: PathBuf::new(),
         }
     }
 }
@@ -48,6 +60,10 @@ impl Default for AppState {
 #[serde(default)]
 pub struct DeepSearchApp {
     active_tab: Tab,
+    /// Index database chosen in the settings. `None` uses the default location.
+    db_path_setting: Option<PathBuf>,
+    /// Search profiles and recent queries.
+    saved_searches: SavedSearches,
 
     #[serde(skip)]
     state: AppState,
@@ -64,6 +80,9 @@ pub struct DeepSearchApp {
     command_sender: Sender<Command>,
     #[serde(skip)]
     update_receiver: Receiver<GuiUpdate>,
+    /// The worker's ends of the channels, handed over by `start_worker`.
+    #[serde(skip)]
+    worker_channels: Option<(Receiver<Command>, Sender<GuiUpdate>)>,
 
     #[serde(skip)]
     repaint_ctx: Arc<OnceLock<egui::Context>>,
@@ -73,34 +92,92 @@ pub struct DeepSearchApp {
 }
 
 impl Default for DeepSearchApp {
-    #[allow(clippy::too_many_lines)]
     fn default() -> Self {
         let (command_sender, command_receiver) = mpsc::channel();
         let (update_sender, update_receiver) = mpsc::channel();
-        let repaint_ctx = Arc::new(OnceLock::new());
-        let thread_repaint_ctx = repaint_ctx.clone();
+
+        // Queued until the worker is started.
+        command_sender.send(Command::FetchLocations).unwrap();
+
+        Self {
+            command_sender,
+            update_receiver,
+            worker_channels: Some((command_receiver, update_sender)),
+            repaint_ctx: Arc::new(OnceLock::new()),
+            active_tab: Tab::Indexing,
+            db_path_setting: None,
+            saved_searches: SavedSearches::default(),
+            state: AppState::default(),
+            menu_bar: MenuBar::default(),
+            indexing_tab: IndexingTab::default(),
+            search_tab: SearchTab::default(),
+            status_bar: StatusBar,
+            background_texture: None,
+        }
+    }
+}
+
+impl DeepSearchApp {
+    pub fn set_repaint_ctx(&self, ctx: egui::Context) {
+        self.repaint_ctx.set(ctx).ok();
+    }
+
+    /// Queues a message for the notice window.
+    pub fn show_notice(&mut self, title: &str, message: String) {
+        self.state.notices.push_back((title.to_string(), message));
+    }
+
+    /// The index database chosen in the settings, if any.
+    pub fn db_path_setting(&self) -> Option<&Path> {
+        self.db_path_setting.as_deref()
+    }
+
+    /// Spawns the background thread that owns the index database at `db_path` and runs every
+    /// workflow. Must be called once, after the persisted state has been loaded.
+    #[allow(clippy::too_many_lines)]
+    pub fn start_worker(&mut self, db_path: PathBuf) {
+        let Some((command_receiver, update_sender)) = self.worker_channels.take() else {
+            return;
+        };
+        self.state.active_db_path.clone_from(&db_path);
+        let thread_repaint_ctx = self.repaint_ctx.clone();
 
         thread::spawn(move || {
             let mut registry = Registry::new();
             registry.register_process("scan_directory_streaming", processes::scan::scan_directory_streaming);
             registry.register_process("write_index_from_stream_batched", processes::index::write_index_from_stream_batched);
             registry.register_process("rescan_scan_streaming", processes::scan::rescan_scan_streaming);
+            registry.register_process("rescan_apply_delta_batched", processes::index::rescan_apply_delta_batched);
             registry.register_process("rescan_write_index_from_stream_batched", processes::index::rescan_write_index_from_stream_batched);
             registry.register_process("rescan_atomic_swap_final", processes::scan::rescan_atomic_swap_final);
             registry.register_process("search_index", processes::search::search_index);
             registry.register_process("live_search_2_phase", processes::live_search::live_search_2_phase);
+            registry.register_process("find_duplicates", processes::duplicates::find_duplicates);
             registry.register_workflow("gui_initial_scan", vec!["scan_directory_streaming".to_string(), "write_index_from_stream_batched".to_string()]);
-            registry.register_workflow("gui_rescan", vec!["rescan_scan_streaming".to_string(), "rescan_write_index_from_stream_batched".to_string(), "rescan_atomic_swap_final".to_string()]);
+            registry.register_workflow("gui_rescan", vec!["rescan_scan_streaming".to_string(), "rescan_apply_delta_batched".to_string()]);
+            registry.register_workflow("gui_full_rescan", vec!["rescan_scan_streaming".to_string(), "rescan_write_index_from_stream_batched".to_string(), "rescan_atomic_swap_final".to_string()]);
             registry.register_workflow("gui_search", vec!["search_index".to_string()]);
             registry.register_workflow("gui_live_search", vec!["live_search_2_phase".to_string()]);
+            registry.register_workflow("gui_find_duplicates", vec!["find_duplicates".to_string()]);
 
             let engine = Engine::new(registry);
-            let db_path = PathBuf::from("deepsearch_index.redb");
             let gui_sender = GuiSender::new(update_sender, thread_repaint_ctx);
 
+            if let Some(parent) = db_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent).ok();
+            }
+
+            // Bring the index up to date before any other command touches it.
+            match DbManager::open_and_migrate(&db_path, Some(&gui_sender)) {
+                Ok(MigrationOutcome::UpToDate) => {}
+                Ok(outcome) => gui_sender.send(GuiUpdate::DatabaseMigrated(outcome)).unwrap(),
+                Err(e) => gui_sender.send(GuiUpdate::DatabaseUnavailable(e.to_string())).unwrap(),
+            }
+
             for command in command_receiver {
                 let mut context = Context {
                     search_keyword: None,
+                    search_generation: 0,
 
                     progress_reporter: Some(gui_sender.clone()),
                     live_search_root_path: None,
@@ -108,14 +185,23 @@ impl Default for DeepSearchApp {
                     search_in_pdf: false,
                     search_in_office: false,
                     search_in_plain_text: false,
+                    content_exact_match: false,
+                    content_context_lines: 0,
+                    query_mode: QueryMode::Tokens,
+                    match_normalized_name: false,
+                    match_full_path: false,
                     task_controller: None,
                     db_path: Some(db_path.clone()),
                     target_path: None,
+                    index_content: false,
+                    hash_files: false,
+                    resume_scan: false,
                     file_data_stream: None,
                     files_found_count: 0,
                     search_locations: None,
                     new_table_name: None,
                     old_table_name: None,
+                    rescan_stats: RescanStats::default(),
                 };
 
                 match command {
@@ -127,7 +213,8 @@ impl Default for DeepSearchApp {
                                     let count = db_manager.get_table_len(&table_name).unwrap_or(0);
                                     locations_with_counts.push((path, table_name, count));
                                 }
-                                gui_sender.send(GuiUpdate::LocationsUpdated(locations_with_counts)).unwrap();
+                                let partial_locations = db_manager.get_partial_scans().unwrap_or_default();
+                                gui_sender.send(GuiUpdate::LocationsUpdated(locations_with_counts, partial_locations)).unwrap();
                             }
                         }
                     }
@@ -150,8 +237,11 @@ impl Default for DeepSearchApp {
                         }
                         gui_sender.send(GuiUpdate::ScanCompleted(0)).unwrap();
                     }
-                    Command::StartInitialScan { path, task_controller } => {
+                    Command::StartInitialScan { path, index_content, hash_files, resume, task_controller } => {
                         context.target_path = Some(path);
+                        context.index_content = index_content;
+                        context.hash_files = hash_files;
+                        context.resume_scan = resume;
                         context.task_controller = Some(task_controller);
                         match engine.run_workflow("gui_initial_scan", context) {
                             Ok(final_context) => {
@@ -160,59 +250,86 @@ impl Default for DeepSearchApp {
                             Err(e) => gui_sender.send(GuiUpdate::Error(e.to_string())).unwrap(),
                         }
                     }
-                    Command::StartRescan { path, task_controller } => {
+                    Command::StartRescan { path, full_rebuild, task_controller } => {
                         context.target_path = Some(path);
                         context.task_controller = Some(task_controller);
-                        match engine.run_workflow("gui_rescan", context) {
-                            Ok(final_context) => {
+                        let workflow = if full_rebuild { "gui_full_rescan" } else { "gui_rescan" };
+                        match engine.run_workflow(workflow, context) {
+                            Ok(final_context) if full_rebuild => {
                                 gui_sender.send(GuiUpdate::ScanCompleted(final_context.files_found_count)).unwrap();
                             }
+                            Ok(final_context) => {
+                                gui_sender.send(GuiUpdate::RescanCompleted(final_context.rescan_stats)).unwrap();
+                            }
                             Err(e) => gui_sender.send(GuiUpdate::Error(e.to_string())).unwrap(),
                         }
                     }
-                    Command::StartSearch { locations, keyword, is_live_search_active, live_search_path, search_in_content, search_in_pdf, search_in_office, search_in_plain_text, task_controller } => {
+                    Command::VerifyAndRepair => {
+                        match DbManager::new(&db_path).and_then(|mut db_manager| db_manager.verify_and_repair(Some(&gui_sender))) {
+                            Ok(report) => gui_sender.send(GuiUpdate::RepairCompleted(report)).unwrap(),
+                            Err(e) => gui_sender.send(GuiUpdate::Error(format!("Verify & Repair failed: {e}"))).unwrap(),
+                        }
+                    }
+                    Command::ExportLocation { path, destination } => {
+                        utils::report_progress(Some(&gui_sender), 0.5, &format!("📤 Exporting {path}..."));
+                        match DbManager::new(&db_path).and_then(|db_manager| transfer::export_location(&db_manager, &path, &destination)) {
+                            Ok(count) => gui_sender.send(GuiUpdate::ExportCompleted(count, destination)).unwrap(),
+                            Err(e) => gui_sender.send(GuiUpdate::Error(format!("Export failed: {e}"))).unwrap(),
+                        }
+                    }
+                    Command::ImportLocation { source, root_path, overwrite } => {
+                        match DbManager::new(&db_path).and_then(|db_manager| transfer::import_location(&db_manager, &source, &root_path, overwrite, Some(&gui_sender))) {
+                            Ok(count) => gui_sender.send(GuiUpdate::ImportCompleted(root_path, count)).unwrap(),
+                            Err(e) => gui_sender.send(GuiUpdate::Error(format!("Import failed: {e}"))).unwrap(),
+                        }
+                    }
+                    Command::RelocateLocation { path, new_path } => {
+                        match DbManager::new(&db_path).and_then(|db_manager| db_manager.verify_and_relocate(&path, &new_path)) {
+                            Ok((found, sampled)) => gui_sender.send(GuiUpdate::LocationRelocated {
+                                old_path: path,
+                                new_path: new_path.display().to_string(),
+                                found,
+                                sampled,
+                            }).unwrap(),
+                            Err(e) => gui_sender.send(GuiUpdate::Error(format!("Relocation failed: {e}"))).unwrap(),
+                        }
+                    }
+                    Command::FindDuplicates { locations } => {
+                        context.search_locations = Some(locations);
+                        if let Err(e) = engine.run_workflow("gui_find_duplicates", context) {
+                            gui_sender.send(GuiUpdate::Error(e.to_string())).unwrap();
+                        }
+                    }
+                    Command::StartSearch { generation, locations, keyword, query_mode, match_normalized_name, match_full_path, is_live_search_active, live_search_path, search_in_content, search_in_pdf, search_in_office, search_in_plain_text, content_exact_match, content_context_lines, task_controller } => {
                         context.search_keyword = Some(keyword);
+                        context.search_generation = generation;
+                        let search_sender = gui_sender.for_search(generation);
+                        context.progress_reporter = Some(search_sender.clone());
+                        context.query_mode = query_mode;
+                        context.match_normalized_name = match_normalized_name;
+                        context.match_full_path = match_full_path;
                         context.search_in_content = search_in_content;
                         context.search_in_pdf = search_in_pdf;
                         context.search_in_office = search_in_office;
                         context.search_in_plain_text = search_in_plain_text;
+                        context.content_exact_match = content_exact_match;
+                        context.content_context_lines = content_context_lines;
                         context.task_controller = Some(task_controller);
                         if is_live_search_active {
                             context.live_search_root_path = live_search_path;
                             if let Err(e) = engine.run_workflow("gui_live_search", context) {
-                                gui_sender.send(GuiUpdate::Error(e.to_string())).unwrap();
+                                search_sender.send(GuiUpdate::SearchError(generation, e.to_string())).unwrap();
                             }
                         } else {
                             context.search_locations = Some(locations);
                             if let Err(e) = engine.run_workflow("gui_search", context) {
-                                gui_sender.send(GuiUpdate::Error(e.to_string())).unwrap();
+                                search_sender.send(GuiUpdate::SearchError(generation, e.to_string())).unwrap();
                             }
                         }
                     }
                 }
             }
         });
-
-        command_sender.send(Command::FetchLocations).unwrap();
-
-        Self {
-            command_sender,
-            update_receiver,
-            repaint_ctx,
-            active_tab: Tab::Indexing,
-            state: AppState::default(),
-            menu_bar: MenuBar::default(),
-            indexing_tab: IndexingTab::default(),
-            search_tab: SearchTab::default(),
-            status_bar: StatusBar,
-            background_texture: None,
-        }
-    }
-}
-
-impl DeepSearchApp {
-    pub fn set_repaint_ctx(&self, ctx: egui::Context) {
-        self.repaint_ctx.set(ctx).ok();
     }
 }
 
@@ -226,39 +343,142 @@ impl eframe::App for DeepSearchApp {
         // --- Handle Updates from Backend Thread ---
         while let Ok(update) = self.update_receiver.try_recv() {
             match update {
-                GuiUpdate::LocationsUpdated(locations) => {
+                GuiUpdate::LocationsUpdated(locations, partial_locations) => {
                     self.state.locations = locations;
-                    self.search_tab.search_scope.clear();
-                    for (path, _, _) in &self.state.locations {
-                        self.search_tab.search_scope.insert(path.clone(), true);
-                    }
+                    self.state.partial_locations = partial_locations;
+                    self.search_tab.update_scope(&self.state.locations);
                     self.state.current_status = format!("{} locations loaded.", self.state.locations.len());
                 }
                 GuiUpdate::ScanProgress(progress, status) => {
                     self.state.scan_progress = progress;
                     self.state.current_status = status;
                 }
+                GuiUpdate::SearchProgress(generation, progress, status) => {
+                    if generation == self.search_tab.search_generation {
+                        self.state.scan_progress = progress;
+                        self.state.current_status = status;
+                    }
+                }
                 GuiUpdate::ScanCompleted(count) => {
                     self.state.is_running_task = false;
                     self.state.scan_progress = 1.0;
                     self.state.current_status = format!("✅ Scan completed. Indexed {count} files.");
                     self.command_sender.send(Command::FetchLocations).unwrap();
                 }
-                GuiUpdate::SearchResultsBatch(results) => {
-                    self.search_tab.search_results.extend(results);
+                GuiUpdate::RescanCompleted(stats) => {
+                    self.state.is_running_task = false;
+                    self.state.scan_progress = 1.0;
+                    self.state.current_status = format!("✅ Rescan completed. {} added, {} modified, {} removed.", stats.added, stats.modified, stats.removed);
+                    self.command_sender.send(Command::FetchLocations).unwrap();
                 }
-                GuiUpdate::LiveSearchResultsBatch(results) => {
-                    self.search_tab.live_search_results.extend(results);
+                // Updates of a search replaced by a newer one are dropped.
+                GuiUpdate::SearchResultsBatch(generation, results) => {
+                    if generation == self.search_tab.search_generation {
+                        self.search_tab.add_results(results);
+                    }
+                }
+                GuiUpdate::LiveSearchResultsBatch(generation, results) => {
+                    if generation == self.search_tab.search_generation {
+                        self.search_tab.add_live_results(results);
+                    }
+                }
+                GuiUpdate::InvalidQuery(generation, e) => {
+                    if generation == self.search_tab.search_generation {
+                        self.search_tab.active_search = None;
+                        self.state.is_running_task = false;
+                        self.state.is_paused = false;
+                        self.state.current_status = "The search query is invalid.".to_string();
+                        self.search_tab.query_error = Some(e);
+                    }
+                }
+                GuiUpdate::SearchFinished(generation) => {
+                    if generation == self.search_tab.search_generation {
+                        self.search_tab.active_search = None;
+                        self.state.is_running_task = false;
+                        self.state.is_paused = false;
+                        self.state.current_status = format!("Found {} results.", self.search_tab.search_results.len() + self.search_tab.live_hit_count());
+                    }
                 }
-                GuiUpdate::SearchFinished => {
+                GuiUpdate::DatabaseMigrated(outcome) => {
+                    let message = match outcome {
+                        MigrationOutcome::UpToDate => continue,
+                        MigrationOutcome::Migrated { ref locations, ref content_locations, .. } if locations.is_empty() && content_locations.is_empty() => continue,
+                        MigrationOutcome::Migrated { from_version, locations, from_content_version, content_locations } => {
+                            let mut message = format!("The index was upgraded from record format v{from_version} and content format v{from_content_version}.\n\nSearches work right away, but some details stay missing until these locations are rescanned.");
+                            if !locations.is_empty() {
+                                message.push_str("\n\nSize, creation date and attributes stay empty in:\n");
+                                message.push_str(&locations.join("\n"));
+                            }
+                            if !content_locations.is_empty() {
+                                message.push_str("\n\nSpreadsheet content hits show no sheet name, and documents in newer formats are not content-indexed, in:\n");
+                                message.push_str(&content_locations.join("\n"));
+                            }
+                            message
+                        }
+                        MigrationOutcome::ContentFormatsAdded { extensions, locations } => format!(
+                            "DeepSearch can now search the contents of more document formats: {}.\n\nThe content indexes of these locations include them after their next rescan:\n\n{}",
+                            extensions.join(", "),
+                            locations.join("\n")
+                        ),
+                        MigrationOutcome::Reset { backup_path } => format!(
+                            "The index file was created by an older DeepSearch release and cannot be read by this version.\n\nIt was moved to '{}' and a new, empty index was created. Please re-index your folders.",
+                            backup_path.display()
+                        ),
+                    };
+                    self.state.notices.push_back(("Index Upgraded".to_string(), message));
+                }
+                GuiUpdate::DatabaseUnavailable(e) => {
+                    self.state.current_status = "Error: the index database could not be opened.".to_string();
+                    self.state.notices.push_back(("Index Unavailable".to_string(), e));
+                }
+                GuiUpdate::RepairCompleted(report) => {
                     self.state.is_running_task = false;
-                    self.state.is_paused = false;
-                    self.state.current_status = format!("Found {} results.", self.search_tab.search_results.len() + self.search_tab.live_search_results.len());
+                    self.state.scan_progress = 1.0;
+                    self.state.current_status = "✅ Verify & Repair completed.".to_string();
+                    self.state.notices.push_back(("Verify & Repair".to_string(), format_repair_report(&report)));
+                    self.command_sender.send(Command::FetchLocations).unwrap();
+                }
+                GuiUpdate::ExportCompleted(count, destination) => {
+                    self.state.is_running_task = false;
+                    self.state.scan_progress = 1.0;
+                    self.state.current_status = format!("✅ Exported {count} items to {}.", destination.display());
+                }
+                GuiUpdate::ImportCompleted(root_path, count) => {
+                    self.state.is_running_task = false;
+                    self.state.scan_progress = 1.0;
+                    self.state.current_status = format!("✅ Imported {count} items as {root_path}.");
+                    self.command_sender.send(Command::FetchLocations).unwrap();
+                }
+                GuiUpdate::DuplicatesFound { groups, unhashed_locations } => {
+                    self.state.is_running_task = false;
+                    self.state.scan_progress = 1.0;
+                    let wasted: u64 = groups.iter().map(DuplicateGroup::wasted_size).sum();
+                    let skipped = if unhashed_locations.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" {} locations were indexed without hashes and skipped.", unhashed_locations.len())
+                    };
+                    self.state.current_status = format!("Found {} groups of duplicates, {} wasted.{skipped}", groups.len(), utils::format_size(wasted));
+                    self.search_tab.duplicate_groups = groups;
+                    self.search_tab.unhashed_locations = unhashed_locations;
+                }
+                GuiUpdate::LocationRelocated { old_path, new_path, found, sampled } => {
+                    self.state.is_running_task = false;
+                    self.state.current_status = format!("✅ Moved {old_path} to {new_path} ({found} of {sampled} sampled files found).");
+                    self.command_sender.send(Command::FetchLocations).unwrap();
                 }
                 GuiUpdate::Error(e) => {
                     self.state.is_running_task = false;
                     self.state.current_status = format!("Error: {e}");
                 }
+                GuiUpdate::SearchError(generation, e) => {
+                    if generation == self.search_tab.search_generation {
+                        self.search_tab.active_search = None;
+                        self.state.is_running_task = false;
+                        self.state.is_paused = false;
+                        self.state.current_status = format!("Error: {e}");
+                    }
+                }
             }
         }
 
@@ -316,7 +536,7 @@ impl eframe::App for DeepSearchApp {
             .show(ctx, |ui| {
                 ui.horizontal(|ui| {
                     // Left side - menu
-                    self.menu_bar.ui(ctx, ui);
+                    self.menu_bar.ui(ctx, ui, &self.state, &mut self.db_path_setting);
 
                     // Right side - title (allocate remaining space)
                     let available = ui.available_size();
@@ -357,8 +577,51 @@ impl eframe::App for DeepSearchApp {
 
                 match self.active_tab {
                     Tab::Indexing => self.indexing_tab.ui(ui, &mut self.state, &self.command_sender),
-                    Tab::Search => self.search_tab.ui(ui, &mut self.state, &self.command_sender),
+                    Tab::Search => self.search_tab.ui(ui, &mut self.state, &self.command_sender, &mut self.saved_searches),
                 }
             });
+
+        // --- Notice Window ---
+        if let Some((title, message)) = self.state.notices.front() {
+            let mut dismissed = false;
+            egui::Window::new(title.as_str())
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+                .show(ctx, |ui| {
+                    ui.label(message);
+                    ui.add_space(10.0);
+                    ui.vertical_centered(|ui| {
+                        if ui.button("OK").clicked() {
+                            dismissed = true;
+                        }
+                    });
+                });
+            if dismissed {
+                self.state.notices.pop_front();
+            }
+        }
+    }
+}
+
+/// Builds the summary shown to the user after `Command::VerifyAndRepair`.
+fn format_repair_report(report: &RepairReport) -> String {
+    let mut sections = Vec::new();
+    if !report.dropped_tables.is_empty() {
+        sections.push(format!("Removed {} orphan tables:\n{}", report.dropped_tables.len(), report.dropped_tables.join("\n")));
+    }
+    if !report.missing_tables.is_empty() {
+        sections.push(format!("These locations have no index data and should be deleted or rebuilt:\n{}", report.missing_tables.join("\n")));
+    }
+    for (path, count, samples) in &report.undecodable_rows {
+        sections.push(format!("{count} unreadable entries in '{path}' (rebuild it to fix), e.g.:\n{}", samples.join("\n")));
+    }
+    if !report.stale_scan_states.is_empty() {
+        sections.push(format!("Cleared leftover scan state for:\n{}", report.stale_scan_states.join("\n")));
+    }
+    if sections.is_empty() {
+        sections.push("No problems were found.".to_string());
     }
+    sections.push(if report.compacted { "The index file was compacted." } else { "The index file was already compact." }.to_string());
+    sections.join("\n\n")
 }
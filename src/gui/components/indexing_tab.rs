@@ -1,14 +1,30 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::Sender;
 use eframe::egui;
 use crate::gui::app::AppState;
 use crate::gui::events::Command;
 use crate::pop::control::TaskController;
+use crate::transfer::{self, ExportHeader};
+use crate::utils;
+
+/// An export file the user picked, waiting for a root path to register it under.
+pub struct PendingImport {
+    pub source: PathBuf,
+    pub header: ExportHeader,
+    pub root_path_input: String,
+    /// Set once the user asked to import over an existing location and must confirm it.
+    pub confirming_overwrite: bool,
+}
 
 #[derive(Default)]
 pub struct IndexingTab {
     pub target_path_input: String,
+    pub index_content: bool,
+    pub hash_files: bool,
     pub confirming_delete: Option<String>,
+    pub pending_import: Option<PendingImport>,
+    /// The location being relocated and the new root path typed so far.
+    pub relocating: Option<(String, String)>,
 }
 
 impl IndexingTab {
@@ -40,9 +56,13 @@ impl IndexingTab {
                         let path = PathBuf::from(&self.target_path_input);
                         let task_controller = TaskController::new();
                         state.active_task_control = Some(task_controller.clone());
-                        command_sender.send(Command::StartInitialScan { path, task_controller }).unwrap();
+                        command_sender.send(Command::StartInitialScan { path, index_content: self.index_content, hash_files: self.hash_files, resume: false, task_controller }).unwrap();
                     }
                 }
+                ui.checkbox(&mut self.index_content, "Index file contents")
+                    .on_hover_text("Also store the text of PDF, Office and plain-text files so the Search tab can find it instantly. Slower to build and uses more disk space.");
+                ui.checkbox(&mut self.hash_files, "Hash files")
+                    .on_hover_text("Also compute a content hash of every file so duplicates can be found from the Search tab. Reads every file once; rescans only rehash changed files.");
             });
         });
 
@@ -77,7 +97,35 @@ impl IndexingTab {
         }
 
         // --- Section for listing indexed locations ---
-        ui.label(egui::RichText::new("Indexed Locations").strong());
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Indexed Locations").strong());
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                let verify_button = ui.add_enabled(!state.is_running_task, egui::Button::new("🩺 Verify & Repair"))
+                    .on_hover_text("Remove leftover tables, check every location and compact the index file");
+                if verify_button.clicked() {
+                    state.is_running_task = true;
+                    state.scan_progress = 0.0;
+                    state.current_status = "Verifying the index...".to_string();
+                    command_sender.send(Command::VerifyAndRepair).unwrap();
+                }
+                let import_button = ui.add_enabled(!state.is_running_task, egui::Button::new("📥 Import..."))
+                    .on_hover_text("Add a location from an index exported on another machine");
+                if import_button.clicked() {
+                    if let Some(source) = rfd::FileDialog::new()
+                        .add_filter("DeepSearch index export", &[transfer::EXPORT_EXTENSION])
+                        .pick_file()
+                    {
+                        match transfer::read_export_header(&source) {
+                            Ok(header) => {
+                                let root_path_input = header.root_path.clone();
+                                self.pending_import = Some(PendingImport { source, header, root_path_input, confirming_overwrite: false });
+                            }
+                            Err(e) => state.current_status = format!("Error: cannot import {}: {e}", source.display()),
+                        }
+                    }
+                }
+            });
+        });
 
         let mut dialog_result: Option<bool> = None;
         if let Some(path) = &self.confirming_delete {
@@ -112,6 +160,9 @@ impl IndexingTab {
             None => {}
         }
 
+        self.import_dialog(ui, state, command_sender);
+        self.relocate_dialog(ui, state, command_sender);
+
         egui::ScrollArea::vertical().show(ui, |ui| {
             if state.locations.is_empty() {
                 ui.label("No locations have been indexed yet.");
@@ -130,15 +181,55 @@ impl IndexingTab {
                                 if ui.button("🗑").on_hover_text("Delete Index").clicked() {
                                     self.confirming_delete = Some(path.clone());
                                 }
-                                if ui.button("🔄").on_hover_text("Rescan").clicked() {
+                                if ui.button("↪").on_hover_text("Relocate (the folder moved or its drive letter changed)").clicked() {
+                                    self.relocating = Some((path.clone(), path.clone()));
+                                }
+                                if ui.button("📤").on_hover_text("Export Index (.dsindex to share, .csv for spreadsheets)").clicked() {
+                                    let file_stem = Path::new(path).file_name().map_or_else(|| "index".to_string(), |name| name.to_string_lossy().to_string());
+                                    if let Some(destination) = rfd::FileDialog::new()
+                                        .set_file_name(format!("{file_stem}.{}", transfer::EXPORT_EXTENSION))
+                                        .add_filter("DeepSearch index export", &[transfer::EXPORT_EXTENSION])
+                                        .add_filter("CSV table", &["csv"])
+                                        .save_file()
+                                    {
+                                        state.is_running_task = true;
+                                        state.scan_progress = 0.0;
+                                        state.current_status = format!("Exporting {path}...");
+                                        command_sender.send(Command::ExportLocation { path: path.clone(), destination }).unwrap();
+                                    }
+                                }
+                                if state.partial_locations.contains(path) && ui.button("▶").on_hover_text("Resume Scan").clicked() {
+                                    state.is_running_task = true;
+                                    state.scan_progress = 0.0;
+                                    state.current_status = format!("Resuming scan of {path}...");
+                                    let task_controller = TaskController::new();
+                                    state.active_task_control = Some(task_controller.clone());
+                                    command_sender.send(Command::StartInitialScan { path: PathBuf::from(path), index_content: false, hash_files: false, resume: true, task_controller }).unwrap();
+                                }
+                                let rescan_button = ui.button("🔄").on_hover_text("Rescan (right-click for a full rebuild)");
+                                let mut full_rebuild = None;
+                                if rescan_button.clicked() {
+                                    full_rebuild = Some(false);
+                                }
+                                rescan_button.context_menu(|ui| {
+                                    if ui.button("Full Rebuild").clicked() {
+                                        full_rebuild = Some(true);
+                                        ui.close();
+                                    }
+                                });
+                                if let Some(full_rebuild) = full_rebuild {
                                     state.is_running_task = true;
                                     state.scan_progress = 0.0;
                                     state.current_status = format!("Rescanning {path}...");
                                     let task_controller = TaskController::new();
-                                state.active_task_control = Some(task_controller.clone());
-                                command_sender.send(Command::StartRescan { path: PathBuf::from(path), task_controller }).unwrap();
+                                    state.active_task_control = Some(task_controller.clone());
+                                    command_sender.send(Command::StartRescan { path: PathBuf::from(path), full_rebuild, task_controller }).unwrap();
+                                }
+                                ui.label(egui::RichText::new(format!("{count} items")).weak());
+                                if state.partial_locations.contains(path) {
+                                    ui.label(egui::RichText::new("⚠ Partial").color(egui::Color32::from_rgb(255, 190, 60)))
+                                        .on_hover_text("The initial scan was cancelled or interrupted. Resume it to complete the index.");
                                 }
-                                ui.label(egui::RichText::new(format!("{count} files")).weak());
                             });
                         });
                     });
@@ -147,4 +238,114 @@ impl IndexingTab {
             }
         });
     }
+
+    /// Asks where to register a picked export and, if that location is already indexed,
+    /// for confirmation before replacing it.
+    fn import_dialog(&mut self, ui: &egui::Ui, state: &mut AppState, command_sender: &Sender<Command>) {
+        let Some(pending) = &mut self.pending_import else { return };
+        let mut close = false;
+        let mut import = None;
+
+        egui::Window::new("Import Index")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ui.ctx(), |ui| {
+                let root_path = pending.root_path_input.trim().to_string();
+                if pending.confirming_overwrite {
+                    ui.label(format!("'{root_path}' is already indexed. Replace its index with the imported one?"));
+                    ui.horizontal(|ui| {
+                        if ui.button("Yes, Replace").clicked() {
+                            import = Some((root_path, true));
+                        }
+                        if ui.button("Back").clicked() {
+                            pending.confirming_overwrite = false;
+                        }
+                    });
+                    return;
+                }
+
+                ui.label(format!(
+                    "{} items exported from '{}' on {}.",
+                    pending.header.entries,
+                    pending.header.root_path,
+                    utils::format_date(pending.header.exported_at)
+                ));
+                ui.horizontal(|ui| {
+                    ui.label("Register under:");
+                    ui.text_edit_singleline(&mut pending.root_path_input);
+                    if ui.button("Browse...").clicked() {
+                        if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                            pending.root_path_input = path.display().to_string();
+                        }
+                    }
+                });
+                ui.label(egui::RichText::new("Use the path under which the indexed folder is reachable from this machine.").weak());
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(!root_path.is_empty(), egui::Button::new("Import")).clicked() {
+                        if state.locations.iter().any(|(path, _, _)| *path == root_path) {
+                            pending.confirming_overwrite = true;
+                        } else {
+                            import = Some((root_path, false));
+                        }
+                    }
+                    if ui.button("Cancel").clicked() {
+                        close = true;
+                    }
+                });
+            });
+
+        if let Some((root_path, overwrite)) = import {
+            let source = pending.source.clone();
+            state.is_running_task = true;
+            state.scan_progress = 0.0;
+            state.current_status = format!("Importing index as {root_path}...");
+            command_sender.send(Command::ImportLocation { source, root_path, overwrite }).unwrap();
+            close = true;
+        }
+        if close {
+            self.pending_import = None;
+        }
+    }
+
+    /// Asks for the new root path of a location whose folder moved.
+    fn relocate_dialog(&mut self, ui: &egui::Ui, state: &mut AppState, command_sender: &Sender<Command>) {
+        let Some((path, new_path_input)) = &mut self.relocating else { return };
+        let mut close = false;
+
+        egui::Window::new("Relocate Location")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ui.ctx(), |ui| {
+                ui.label(format!("Where is '{path}' now?"));
+                ui.horizontal(|ui| {
+                    ui.label("New path:");
+                    ui.text_edit_singleline(new_path_input);
+                    if ui.button("Browse...").clicked() {
+                        if let Some(new_path) = rfd::FileDialog::new().pick_folder() {
+                            *new_path_input = new_path.display().to_string();
+                        }
+                    }
+                });
+                ui.label(egui::RichText::new("The index is kept. A sample of its files is looked up under the new path first.").weak());
+                ui.horizontal(|ui| {
+                    let new_path = new_path_input.trim();
+                    let can_relocate = !new_path.is_empty() && new_path != path.as_str();
+                    if ui.add_enabled(can_relocate, egui::Button::new("Relocate")).clicked() {
+                        state.is_running_task = true;
+                        state.current_status = format!("Relocating {path}...");
+                        command_sender.send(Command::RelocateLocation { path: path.clone(), new_path: PathBuf::from(new_path) }).unwrap();
+                        close = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        close = true;
+                    }
+                });
+            });
+
+        if close {
+            self.relocating = None;
+        }
+    }
 }
@@ -1,14 +1,25 @@
+use std::path::PathBuf;
 use eframe::egui;
+use crate::gui::app::AppState;
 
 #[derive(Default)]
 pub struct MenuBar {
     pub show_about_window: bool,
+    pub show_settings_window: bool,
+    pub db_path_input: String,
 }
 
 
 impl MenuBar {
-    pub fn ui(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) {
+    pub fn ui(&mut self, ctx: &egui::Context, ui: &mut egui::Ui, state: &AppState, db_path_setting: &mut Option<PathBuf>) {
         egui::MenuBar::new().ui(ui, |ui| {
+            ui.menu_button("Settings", |ui| {
+                if ui.button("Index Database Location...").clicked() {
+                    self.db_path_input = db_path_setting.as_ref().map(|p| p.display().to_string()).unwrap_or_default();
+                    self.show_settings_window = true;
+                    ui.close();
+                }
+            });
             ui.menu_button("Help", |ui| {
                 if ui.button("About").clicked() {
                     self.show_about_window = true;
@@ -17,6 +28,41 @@ impl MenuBar {
             });
         });
 
+        if self.show_settings_window {
+            egui::Window::new("Index Database Location")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+                .show(ctx, |ui| {
+                    ui.label("Leave empty to use the default location.");
+                    ui.horizontal(|ui| {
+                        ui.label("Database file:");
+                        ui.add(egui::TextEdit::singleline(&mut self.db_path_input).hint_text("Default location"));
+                        if ui.button("Browse...").clicked() {
+                            if let Some(path) = rfd::FileDialog::new()
+                                .set_file_name(crate::config::DB_FILE_NAME)
+                                .add_filter("redb index", &["redb"])
+                                .save_file()
+                            {
+                                self.db_path_input = path.display().to_string();
+                            }
+                        }
+                    });
+                    ui.label(egui::RichText::new(format!("Currently open: {}", state.active_db_path.display())).weak());
+                    ui.label(egui::RichText::new("Changes take effect the next time DeepSearch starts. The --db command-line flag overrides this setting.").weak());
+                    ui.horizontal(|ui| {
+                        if ui.button("Save").clicked() {
+                            let input = self.db_path_input.trim();
+                            *db_path_setting = (!input.is_empty()).then(|| PathBuf::from(input));
+                            self.show_settings_window = false;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.show_settings_window = false;
+                        }
+                    });
+                });
+        }
+
         if self.show_about_window {
             egui::Window::new("About DeepSearch")
                 .collapsible(false)
@@ -30,6 +76,8 @@ impl MenuBar {
                         ui.label("Developed by Do Huy Hoang");
                         ui.hyperlink("https://github.com/dohuyhoang93/DeepSearch");
                         ui.add_space(10.0);
+                        ui.label(egui::RichText::new(format!("Index database: {}", state.active_db_path.display())).weak());
+                        ui.add_space(10.0);
                         if ui.button("Close").clicked() {
                             self.show_about_window = false;
                         }
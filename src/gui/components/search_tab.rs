@@ -1,11 +1,116 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
 
 use eframe::egui;
+use crate::db::FileMetadata;
 use crate::gui::app::AppState;
-use crate::gui::events::{Command, DisplayResult, LiveSearchResult};
+use crate::gui::events::{Command, DisplayResult, DuplicateGroup, LiveSearchResult};
 use crate::pop::control::TaskController;
+use crate::query::{NameMatcher, QueryMode};
+use crate::ranking::{self, SortBy};
+use crate::content::{self, ContentKind};
+use crate::utils;
+
+/// Pause in typing after which search-as-you-type starts a search.
+const SEARCH_DEBOUNCE: Duration = Duration::from_millis(250);
+/// Number of past queries kept in the search history.
+const SEARCH_HISTORY_LEN: usize = 20;
+/// Number of files whose content hits are listed open; the files found after them start collapsed.
+const LIVE_GROUPS_OPEN: usize = 20;
+
+/// A named set of search options: the checked locations, the live search folder, the content
+/// types and how names are matched.
+#[derive(Clone, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct SearchProfile {
+    pub name: String,
+    /// Root paths of the checked locations.
+    pub locations: Vec<String>,
+    pub is_live_search_active: bool,
+    pub live_search_path: String,
+    pub search_in_content: bool,
+    pub search_in_pdf: bool,
+    pub search_in_office: bool,
+    pub search_in_plain_text: bool,
+    pub content_exact_match: bool,
+    pub query_mode: QueryMode,
+    pub match_normalized_name: bool,
+    pub match_full_path: bool,
+}
+
+/// The content hits of one file in the live results list.
+pub struct LiveResultGroup {
+    pub file_path: String,
+    pub hits: Vec<LiveSearchResult>,
+    pub open: bool,
+    /// Rows the hits take when the group is open: each hit with its context lines.
+    hit_rows: usize,
+}
+
+impl LiveResultGroup {
+    /// Rows the group takes in the results list, its header included.
+    fn rows(&self) -> usize {
+        1 + if self.open { self.hit_rows } else { 0 }
+    }
+
+    /// The rows below the header: every hit's context lines around the hit itself.
+    fn lines(&self) -> impl Iterator<Item = LiveResultLine<'_>> {
+        self.hits.iter().flat_map(|hit| {
+            hit.context_before.iter().map(|line| LiveResultLine::Context(line))
+                .chain(std::iter::once(LiveResultLine::Hit(hit)))
+                .chain(hit.context_after.iter().map(|line| LiveResultLine::Context(line)))
+        })
+    }
+}
+
+enum LiveResultLine<'a> {
+    Context(&'a str),
+    Hit(&'a LiveSearchResult),
+}
+
+/// Search profiles and recent queries, persisted with the app settings.
+#[derive(Clone, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct SavedSearches {
+    pub profiles: Vec<SearchProfile>,
+    /// Most recent first, without duplicates.
+    pub history: Vec<String>,
+}
+
+impl SavedSearches {
+    /// Moves `keyword` to the front of the history, dropping the oldest queries beyond the limit.
+    pub fn remember_query(&mut self, keyword: &str) {
+        let keyword = keyword.trim();
+        if keyword.is_empty() {
+            return;
+        }
+        self.history.retain(|query| query != keyword);
+        self.history.insert(0, keyword.to_string());
+        self.history.truncate(SEARCH_HISTORY_LEN);
+    }
+
+    /// Remembers a query searched while typing. It replaces the most recent query when it
+    /// extends it, so the pauses while typing `invoice 2024` leave a single entry.
+    pub fn remember_typed_query(&mut self, keyword: &str) {
+        let keyword = keyword.trim();
+        if self.history.first().is_some_and(|latest| keyword.starts_with(latest.as_str())) {
+            self.history.remove(0);
+        }
+        self.remember_query(keyword);
+    }
+
+    /// Adds `profile`, replacing a profile with the same name.
+    pub fn save_profile(&mut self, profile: SearchProfile) {
+        match self.profiles.iter_mut().find(|existing| existing.name == profile.name) {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
+        }
+    }
+}
 
 #[allow(clippy::struct_excessive_bools)]
 pub struct SearchTab {
@@ -13,12 +118,45 @@ pub struct SearchTab {
     pub search_scope: HashMap<String, bool>,
     pub search_results: Vec<DisplayResult>,
     pub live_search_path_input: String,
-    pub live_search_results: Vec<LiveSearchResult>,
+    /// Content hits grouped by file, in the order the files were found.
+    pub live_search_results: Vec<LiveResultGroup>,
+    /// Position of each file's group in `live_search_results`.
+    live_search_groups: HashMap<String, usize>,
     pub is_live_search_active: bool,
-    pub live_search_in_content: bool,
+    pub search_in_content: bool,
     pub search_in_pdf: bool,
     pub search_in_office: bool,
     pub search_in_plain_text: bool,
+    /// Content search matches the keyword as typed, case and accents included.
+    pub content_exact_match: bool,
+    /// Lines, paragraphs or rows shown before and after each content hit.
+    pub content_context_lines: usize,
+    pub query_mode: QueryMode,
+    /// Glob and regex patterns match the normalized name instead of the original file name.
+    pub match_normalized_name: bool,
+    /// Query words match anywhere in the relative path instead of only the file name.
+    pub match_full_path: bool,
+    /// Why the current pattern does not compile, shown under the keyword field.
+    pub query_error: Option<String>,
+    /// Set by "Find Duplicates"; the results panel then lists `duplicate_groups`.
+    pub showing_duplicates: bool,
+    pub duplicate_groups: Vec<DuplicateGroup>,
+    /// Selected locations that were skipped because they were indexed without hashes.
+    pub unhashed_locations: Vec<String>,
+    /// Order of `search_results`; every incoming batch is merged into it.
+    pub sort_by: SortBy,
+    pub sort_descending: bool,
+    /// Each keyword change starts an indexed search once typing pauses.
+    pub search_as_you_type: bool,
+    /// Incremented by every search; updates tagged with an older generation are dropped.
+    pub search_generation: u64,
+    /// Controller of the running search, cancelled when a newer search replaces it.
+    pub active_search: Option<Arc<TaskController>>,
+    /// When the debounced search-as-you-type search is due.
+    pending_search: Option<Instant>,
+    /// Name of the profile last applied or saved, selected in the profile dropdown.
+    pub active_profile: Option<String>,
+    pub profile_name_input: String,
 }
 
 impl Default for SearchTab {
@@ -29,38 +167,110 @@ impl Default for SearchTab {
             search_results: vec![],
             live_search_path_input: String::new(),
             live_search_results: vec![],
+            live_search_groups: HashMap::new(),
             is_live_search_active: false,
-            live_search_in_content: false,
+            search_in_content: false,
             search_in_pdf: true,
             search_in_office: true,
             search_in_plain_text: true,
+            content_exact_match: false,
+            content_context_lines: content::DEFAULT_CONTEXT_LINES,
+            query_mode: QueryMode::Tokens,
+            match_normalized_name: false,
+            match_full_path: false,
+            query_error: None,
+            showing_duplicates: false,
+            duplicate_groups: vec![],
+            unhashed_locations: vec![],
+            sort_by: SortBy::Relevance,
+            sort_descending: true,
+            search_as_you_type: true,
+            search_generation: 0,
+            active_search: None,
+            pending_search: None,
+            active_profile: None,
+            profile_name_input: String::new(),
         }
     }
 }
 
 impl SearchTab {
-    pub fn ui(&mut self, ui: &mut egui::Ui, state: &mut AppState, command_sender: &Sender<Command>) {
+    #[allow(clippy::too_many_lines)]
+    pub fn ui(&mut self, ui: &mut egui::Ui, state: &mut AppState, command_sender: &Sender<Command>, saved: &mut SavedSearches) {
+        if let Some(due) = self.pending_search {
+            let now = Instant::now();
+            if now < due {
+                ui.ctx().request_repaint_after(due - now);
+            } else if !self.other_task_running(state) {
+                self.pending_search = None;
+                if self.trigger_search(state, command_sender) {
+                    saved.remember_typed_query(&self.search_keyword);
+                }
+            }
+        }
+
+        ui.add_enabled_ui(!self.other_task_running(state), |ui| {
+            self.profile_controls(ui, state, saved);
+        });
+        ui.add_space(4.0);
+
         // --- Top controls (Live Search, Path, etc.) ---
-        ui.add_enabled_ui(!state.is_running_task, |ui| {
+        ui.add_enabled_ui(!self.other_task_running(state), |ui| {
             ui.vertical(|ui| {
                 ui.horizontal(|ui| {
                     ui.checkbox(&mut self.is_live_search_active, "Live Search in Folder");
+                    ui.checkbox(&mut self.search_as_you_type, "Search as you type")
+                        .on_hover_text("Search the index while typing. Live searches in a folder still start with Enter or \"Search\".");
                 });
 
-                if self.is_live_search_active {
+                ui.horizontal(|ui| {
+                    let content_toggle = ui.checkbox(&mut self.search_in_content, "Search in file content")
+                        .on_hover_text("In indexed search, only locations indexed with their file contents are searched.");
+                    if content_toggle.changed() {
+                        self.validate_query();
+                    }
+                });
+
+                ui.add_enabled_ui(self.search_in_content, |ui| {
                     ui.horizontal(|ui| {
-                        ui.checkbox(&mut self.live_search_in_content, "Search in file content");
+                        ui.label("Include:");
+                        let extensions = |kind| content::extractors().extensions_of(kind).join(", ");
+                        ui.checkbox(&mut self.search_in_pdf, "PDFs").on_hover_text(extensions(ContentKind::Pdf));
+                        ui.checkbox(&mut self.search_in_office, "Office Files").on_hover_text(extensions(ContentKind::Office));
+                        ui.checkbox(&mut self.search_in_plain_text, "Plain Text").on_hover_text(extensions(ContentKind::PlainText));
+                        ui.checkbox(&mut self.content_exact_match, "Exact match")
+                            .on_hover_text("Match the keyword exactly as typed, including case and accents. By default every word matches regardless of case and accents, so \"thanh\" finds \"Thành\".");
+                        ui.label("Context:");
+                        ui.add(egui::DragValue::new(&mut self.content_context_lines).range(0..=10).suffix(" lines"))
+                            .on_hover_text("Lines, paragraphs or rows shown before and after each hit.");
                     });
+                });
 
-                    ui.add_enabled_ui(self.live_search_in_content, |ui| {
-                        ui.horizontal(|ui| {
-                            ui.label("Include:");
-                            ui.checkbox(&mut self.search_in_pdf, "PDFs");
-                            ui.checkbox(&mut self.search_in_office, "Office Files");
-                            ui.checkbox(&mut self.search_in_plain_text, "Plain Text");
+                let mut query_changed = false;
+                ui.add_enabled_ui(!self.search_in_content, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Match names by:");
+                        for mode in QueryMode::ALL {
+                            query_changed |= ui.radio_value(&mut self.query_mode, mode, mode.label()).changed();
+                        }
+                        ui.add_enabled_ui(self.query_mode == QueryMode::Tokens, |ui| {
+                            query_changed |= ui.checkbox(&mut self.match_full_path, "Full path")
+                                .on_hover_text("Match words anywhere in the path inside the location, folder names included. Override per query with in:name or in:path, or per word with name:word or path:word.")
+                                .changed();
+                        });
+                        ui.add_enabled_ui(matches!(self.query_mode, QueryMode::Glob | QueryMode::Regex), |ui| {
+                            query_changed |= ui.checkbox(&mut self.match_normalized_name, "Normalized name")
+                                .on_hover_text("Match the lowercase, accent-free name (punctuation removed) instead of the original file name.")
+                                .changed();
                         });
                     });
+                });
+                if query_changed {
+                    self.validate_query();
+                    self.schedule_search();
+                }
 
+                if self.is_live_search_active {
                     ui.horizontal(|ui| {
                         ui.label("Path:");
                         let text_edit = egui::TextEdit::singleline(&mut self.live_search_path_input).hint_text("C:\\Users\\YourUser\\Documents");
@@ -82,24 +292,59 @@ impl SearchTab {
         ui.horizontal(|ui| {
             ui.label("Keyword:");
             ui.label("🔍");
-            let response = ui.add_enabled(!state.is_running_task, egui::TextEdit::singleline(&mut self.search_keyword));
-            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
-                self.trigger_search(state, command_sender);
+            // Always editable: typing replaces the running search instead of waiting for it.
+            let response = ui.add(egui::TextEdit::singleline(&mut self.search_keyword))
+                .on_hover_text("Query syntax: words, \"exact phrases\", -excluded, a OR b, (groups) and the filters ext:pdf,docx size:>5MB modified:2024-01..2024-06 created:2023 name:word path:word in:path|name location:<location> type:file|folder");
+            if response.changed() {
+                self.validate_query();
+                self.schedule_search();
             }
-            if ui.add_enabled(!state.is_running_task, egui::Button::new("Search")).clicked() {
-                self.trigger_search(state, command_sender);
+            let pressed_enter = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            let clicked_search = ui.add_enabled(!self.other_task_running(state), egui::Button::new("Search")).clicked();
+            if pressed_enter || clicked_search {
+                self.pending_search = None;
+                if self.trigger_search(state, command_sender) {
+                    saved.remember_query(&self.search_keyword);
+                }
             }
-
-            if state.is_running_task && self.is_live_search_active {
-                if ui.button("Stop").clicked() {
-                    if let Some(controller) = &state.active_task_control {
-                        controller.cancel();
+            let mut recalled = None;
+            ui.add_enabled_ui(!saved.history.is_empty(), |ui| {
+                ui.menu_button("🕘", |ui| {
+                    for query in &saved.history {
+                        if ui.button(query).clicked() {
+                            recalled = Some(query.clone());
+                            ui.close();
+                        }
+                    }
+                    ui.separator();
+                    if ui.button("Clear history").clicked() {
+                        saved.history.clear();
+                        ui.close();
                     }
-                    state.is_running_task = false;
-                    state.is_paused = false;
-                    state.current_status = "Search stopped.".to_string();
+                }).response.on_hover_text("Recent searches");
+            });
+            if let Some(query) = recalled {
+                self.search_keyword = query;
+                self.validate_query();
+                self.pending_search = None;
+                if self.trigger_search(state, command_sender) {
+                    saved.remember_query(&self.search_keyword);
                 }
+            }
+            let duplicates_button = ui.add_enabled(!state.is_running_task && !self.is_live_search_active, egui::Button::new("🧬 Find Duplicates"))
+                .on_hover_text("List files with identical content in the selected locations (requires \"Hash files\" when indexing)");
+            if duplicates_button.clicked() {
+                self.trigger_find_duplicates(state, command_sender);
+            }
+
+            if self.active_search.is_some() && ui.button("Stop").clicked() {
+                // Stopping supersedes the search, so its in-flight progress cannot overwrite the status.
+                self.cancel_active_search(state);
+                state.current_status = "Search stopped.".to_string();
+            }
 
+            // Only live search walks files and can pause between them.
+            if self.active_search.is_some() && self.is_live_search_active {
                 if state.is_paused {
                     if ui.button("Resume").clicked() {
                         if let Some(controller) = &state.active_task_control {
@@ -118,30 +363,47 @@ impl SearchTab {
             }
         });
 
+        if let Some(error) = &self.query_error {
+            // No prefix, so the caret lines of query and pattern errors stay under the offending character.
+            ui.label(egui::RichText::new(error).color(egui::Color32::from_rgb(255, 120, 120)).monospace());
+        }
+
         ui.add_space(10.0);
 
         // --- Results Panels ---
+        let scope_enabled = !self.other_task_running(state);
+        let mut scope_changed = false;
         egui::SidePanel::left("search_scope_panel")
             .resizable(true)
             .default_width(250.0)
             .max_width(400.0)
             .show_inside(ui, |ui| {
-                ui.add_enabled_ui(!state.is_running_task, |ui| {
+                ui.add_enabled_ui(scope_enabled, |ui| {
                     ui.label(egui::RichText::new("Search In:").strong());
                     egui::ScrollArea::vertical().show(ui, |ui| {
                         for (path, _, _) in &state.locations {
                             if let Some(is_selected) = self.search_scope.get_mut(path) {
-                                ui.checkbox(is_selected, path);
+                                scope_changed |= ui.checkbox(is_selected, path).changed();
                             }
                         }
                     });
                 });
             });
+        if scope_changed {
+            self.schedule_search();
+        }
 
         egui::CentralPanel::default().show_inside(ui, |ui| {
-            ui.label(egui::RichText::new("Results:").strong());
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new("Results:").strong());
+                if !self.showing_duplicates && !self.search_in_content {
+                    self.sort_controls(ui);
+                }
+            });
 
-            if self.is_live_search_active && self.live_search_in_content {
+            if self.showing_duplicates {
+                self.draw_duplicates(ui, state, command_sender);
+            } else if self.search_in_content {
                 self.draw_live_search_results(ui, state, command_sender);
             } else {
                 self.draw_indexed_search_results(ui, state, command_sender);
@@ -149,44 +411,311 @@ impl SearchTab {
         });
     }
 
-    fn trigger_search(&mut self, state: &mut AppState, command_sender: &Sender<Command>) {
-        if !self.search_keyword.is_empty() {
-            let selected_locations: Vec<_> = state.locations.iter()
-                .filter(|(path, _, _)| *self.search_scope.get(path).unwrap_or(&false))
-                .map(|(path, table_name, _)| (path.clone(), table_name.clone()))
-                .collect();
+    /// Adds a batch of indexed results in the current order.
+    pub fn add_results(&mut self, results: Vec<DisplayResult>) {
+        ranking::merge_results(&mut self.search_results, results, self.sort_by, self.sort_descending);
+    }
+
+    /// Adds content hits to the group of their file, creating groups for new files. The first
+    /// files are listed open; later ones start collapsed so the list stays scannable.
+    pub fn add_live_results(&mut self, results: Vec<LiveSearchResult>) {
+        for result in results {
+            let index = *self.live_search_groups.entry(result.file_path.clone()).or_insert_with(|| {
+                self.live_search_results.push(LiveResultGroup {
+                    file_path: result.file_path.clone(),
+                    hits: Vec::new(),
+                    open: self.live_search_results.len() < LIVE_GROUPS_OPEN,
+                    hit_rows: 0,
+                });
+                self.live_search_results.len() - 1
+            });
+            let group = &mut self.live_search_results[index];
+            group.hit_rows += result.context_before.len() + 1 + result.context_after.len();
+            group.hits.push(result);
+        }
+    }
+
+    fn clear_live_results(&mut self) {
+        self.live_search_results.clear();
+        self.live_search_groups.clear();
+    }
+
+    /// Number of content hits across all files.
+    pub fn live_hit_count(&self) -> usize {
+        self.live_search_results.iter().map(|group| group.hits.len()).sum()
+    }
+
+    fn sort_controls(&mut self, ui: &mut egui::Ui) {
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            let arrow = if self.sort_descending { "⬇" } else { "⬆" };
+            let mut changed = ui.button(arrow).on_hover_text("Reverse the order").clicked();
+            if changed {
+                self.sort_descending = !self.sort_descending;
+            }
+            egui::ComboBox::from_id_salt("result_sort_by")
+                .selected_text(self.sort_by.label())
+                .show_ui(ui, |ui| {
+                    for sort_by in SortBy::ALL {
+                        if ui.selectable_value(&mut self.sort_by, sort_by, sort_by.label()).clicked() {
+                            self.sort_descending = sort_by.descending_by_default();
+                            changed = true;
+                        }
+                    }
+                });
+            ui.label("Sort by:");
+            if changed {
+                ranking::sort_results(&mut self.search_results, self.sort_by, self.sort_descending);
+            }
+        });
+    }
+
+    /// Compiles the keyword in the selected mode so query and pattern errors show up while typing.
+    fn validate_query(&mut self) {
+        self.query_error = if self.search_in_content || self.search_keyword.is_empty() {
+            None
+        } else {
+            NameMatcher::compile(self.query_mode, &self.search_keyword, self.match_normalized_name, self.match_full_path).err()
+        };
+    }
+
+    fn profile_controls(&mut self, ui: &mut egui::Ui, state: &AppState, saved: &mut SavedSearches) {
+        ui.horizontal(|ui| {
+            ui.label("Profile:");
+            let mut applied = None;
+            egui::ComboBox::from_id_salt("search_profile")
+                .selected_text(self.active_profile.as_deref().unwrap_or("None"))
+                .show_ui(ui, |ui| {
+                    for profile in &saved.profiles {
+                        if ui.selectable_label(self.active_profile.as_ref() == Some(&profile.name), &profile.name).clicked() {
+                            applied = Some(profile.clone());
+                        }
+                    }
+                });
+            if let Some(profile) = applied {
+                self.apply_profile(&profile, state);
+            }
+
+            ui.add(egui::TextEdit::singleline(&mut self.profile_name_input).hint_text("Profile name").desired_width(140.0));
+            let name = self.profile_name_input.trim().to_string();
+            if ui.add_enabled(!name.is_empty(), egui::Button::new("💾 Save"))
+                .on_hover_text("Save the selected locations and search options under this name")
+                .clicked()
+            {
+                saved.save_profile(self.profile(name.clone()));
+                self.active_profile = Some(name);
+                self.profile_name_input.clear();
+            }
+            if let Some(active) = self.active_profile.clone() {
+                if ui.button("🗑").on_hover_text(format!("Delete the profile '{active}'")).clicked() {
+                    saved.profiles.retain(|profile| profile.name != active);
+                    self.active_profile = None;
+                }
+            }
+        });
+    }
+
+    /// Captures the current search options as a profile.
+    fn profile(&self, name: String) -> SearchProfile {
+        let mut locations: Vec<String> = self.search_scope.iter()
+            .filter(|(_, is_selected)| **is_selected)
+            .map(|(path, _)| path.clone())
+            .collect();
+        locations.sort();
+        SearchProfile {
+            name,
+            locations,
+            is_live_search_active: self.is_live_search_active,
+            live_search_path: self.live_search_path_input.clone(),
+            search_in_content: self.search_in_content,
+            search_in_pdf: self.search_in_pdf,
+            search_in_office: self.search_in_office,
+            search_in_plain_text: self.search_in_plain_text,
+            content_exact_match: self.content_exact_match,
+            query_mode: self.query_mode,
+            match_normalized_name: self.match_normalized_name,
+            match_full_path: self.match_full_path,
+        }
+    }
+
+    /// Restores the options of a profile. Locations that are no longer indexed are ignored.
+    fn apply_profile(&mut self, profile: &SearchProfile, state: &AppState) {
+        for (path, _, _) in &state.locations {
+            self.search_scope.insert(path.clone(), profile.locations.contains(path));
+        }
+        self.is_live_search_active = profile.is_live_search_active;
+        self.live_search_path_input.clone_from(&profile.live_search_path);
+        self.search_in_content = profile.search_in_content;
+        self.search_in_pdf = profile.search_in_pdf;
+        self.search_in_office = profile.search_in_office;
+        self.search_in_plain_text = profile.search_in_plain_text;
+        self.content_exact_match = profile.content_exact_match;
+        self.query_mode = profile.query_mode;
+        self.match_normalized_name = profile.match_normalized_name;
+        self.match_full_path = profile.match_full_path;
+        self.active_profile = Some(profile.name.clone());
+        self.validate_query();
+        self.schedule_search();
+    }
+
+    /// Updates the location checkboxes for a new list of locations, keeping the choices made for
+    /// the locations that are still there. New locations start checked.
+    pub fn update_scope(&mut self, locations: &[(String, String, u64)]) {
+        self.search_scope = locations.iter()
+            .map(|(path, _, _)| (path.clone(), self.search_scope.get(path).copied().unwrap_or(true)))
+            .collect();
+    }
+
+    /// Whether a task other than a search (a scan, an import...) is running. Searches can be
+    /// replaced at any time, those tasks cannot be interrupted by a new search.
+    fn other_task_running(&self, state: &AppState) -> bool {
+        state.is_running_task && self.active_search.is_none()
+    }
+
+    /// Starts a search once typing pauses, when search-as-you-type applies.
+    fn schedule_search(&mut self) {
+        if self.search_as_you_type && !self.is_live_search_active {
+            self.pending_search = Some(Instant::now() + SEARCH_DEBOUNCE);
+        }
+    }
+
+    /// Cancels the running search, if any, and makes its remaining updates stale.
+    fn cancel_active_search(&mut self, state: &mut AppState) {
+        if let Some(controller) = self.active_search.take() {
+            controller.cancel();
+            // A paused live search has to wake up to notice the cancellation.
+            controller.resume();
+            state.is_running_task = false;
+            state.is_paused = false;
+        }
+        self.search_generation += 1;
+    }
+
+    /// Starts a search for the keyword, or clears the results when it is empty. Returns whether a
+    /// search was started, which is when the keyword belongs in the history.
+    fn trigger_search(&mut self, state: &mut AppState, command_sender: &Sender<Command>) -> bool {
+        self.validate_query();
+        if self.query_error.is_some() {
+            state.current_status = "The search query is invalid.".to_string();
+            return false;
+        }
+        if self.search_keyword.is_empty() {
+            // Clearing the keyword while typing clears the results of the previous keyword.
+            if self.search_as_you_type && !self.showing_duplicates {
+                self.cancel_active_search(state);
+                self.search_results.clear();
+                self.clear_live_results();
+            }
+        } else {
+            let selected_locations = self.selected_locations(state);
 
             if !selected_locations.is_empty() || self.is_live_search_active {
+                self.cancel_active_search(state);
+                self.showing_duplicates = false;
                 self.search_results.clear();
-                self.live_search_results.clear();
+                self.clear_live_results();
                 state.is_running_task = true;
                 state.is_paused = false;
 
                 let controller = TaskController::new();
                 state.active_task_control = Some(controller.clone());
+                self.active_search = Some(controller.clone());
 
                 command_sender.send(Command::StartSearch {
+                    generation: self.search_generation,
                     locations: selected_locations,
                     keyword: self.search_keyword.clone(),
+                    query_mode: self.query_mode,
+                    match_normalized_name: self.match_normalized_name,
+                    match_full_path: self.match_full_path,
                     is_live_search_active: self.is_live_search_active,
                     live_search_path: if self.is_live_search_active && !self.live_search_path_input.is_empty() {
                         Some(PathBuf::from(&self.live_search_path_input))
                     } else {
                         None
                     },
-                    search_in_content: self.live_search_in_content,
+                    search_in_content: self.search_in_content,
                     search_in_pdf: self.search_in_pdf,
                     search_in_office: self.search_in_office,
                     search_in_plain_text: self.search_in_plain_text,
+                    content_exact_match: self.content_exact_match,
+                    content_context_lines: self.content_context_lines,
                     task_controller: controller,
                 }).unwrap();
-            } else {
-                state.current_status = "Please select at least one location to search in.".to_string();
+                return true;
             }
+            state.current_status = "Please select at least one location to search in.".to_string();
         }
+        false
     }
 
-    fn draw_live_search_results(&self, ui: &mut egui::Ui, state: &AppState, command_sender: &Sender<Command>) {
+    fn selected_locations(&self, state: &AppState) -> Vec<(String, String)> {
+        state.locations.iter()
+            .filter(|(path, _, _)| *self.search_scope.get(path).unwrap_or(&false))
+            .map(|(path, table_name, _)| (path.clone(), table_name.clone()))
+            .collect()
+    }
+
+    fn trigger_find_duplicates(&mut self, state: &mut AppState, command_sender: &Sender<Command>) {
+        let locations = self.selected_locations(state);
+        if locations.is_empty() {
+            state.current_status = "Please select at least one location to search in.".to_string();
+            return;
+        }
+        self.showing_duplicates = true;
+        self.duplicate_groups.clear();
+        self.unhashed_locations.clear();
+        state.is_running_task = true;
+        state.scan_progress = 0.0;
+        command_sender.send(Command::FindDuplicates { locations }).unwrap();
+    }
+
+    fn draw_duplicates(&self, ui: &mut egui::Ui, state: &AppState, command_sender: &Sender<Command>) {
+        if !self.unhashed_locations.is_empty() {
+            ui.label(egui::RichText::new(format!(
+                "Skipped (indexed without \"Hash files\"): {}",
+                self.unhashed_locations.join(", ")
+            )).weak());
+        }
+        if self.duplicate_groups.is_empty() {
+            if !state.is_running_task {
+                ui.add_space(10.0);
+                ui.vertical_centered(|ui| {
+                    ui.label(egui::RichText::new("No duplicate files found in the selected locations.").strong().color(egui::Color32::WHITE));
+                });
+            }
+            return;
+        }
+
+        let wasted: u64 = self.duplicate_groups.iter().map(DuplicateGroup::wasted_size).sum();
+        ui.label(format!("{} groups of identical files, {} wasted in total.", self.duplicate_groups.len(), utils::format_size(wasted)));
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (i, group) in self.duplicate_groups.iter().enumerate() {
+                let title = format!(
+                    "{} copies · {} each · {} wasted",
+                    group.paths.len(),
+                    utils::format_size(group.size),
+                    utils::format_size(group.wasted_size())
+                );
+                egui::CollapsingHeader::new(title).id_salt(("duplicate_group", i)).show(ui, |ui| {
+                    for path in &group.paths {
+                        let response = ui.selectable_label(false, format!("{} {path}", utils::get_icon_for_path(path, false)));
+                        response.context_menu(|ui| {
+                            if ui.button("Open File").clicked() {
+                                command_sender.send(Command::OpenFile(path.clone())).unwrap();
+                                ui.close();
+                            }
+                            if ui.button("Open File Location").clicked() {
+                                command_sender.send(Command::OpenLocation(path.clone())).unwrap();
+                                ui.close();
+                            }
+                        });
+                    }
+                });
+            }
+        });
+    }
+
+    fn draw_live_search_results(&mut self, ui: &mut egui::Ui, state: &AppState, command_sender: &Sender<Command>) {
         if self.live_search_results.is_empty() && !state.is_running_task {
             ui.add_space(10.0);
             ui.vertical_centered(|ui| {
@@ -197,44 +726,110 @@ impl SearchTab {
                     }
             });
         } else {
-            let text_height = ui.text_style_height(&egui::TextStyle::Body) + 8.0; // Thêm khoảng cách cho margin
-            egui::ScrollArea::vertical().show_rows(ui, text_height, self.live_search_results.len(), |ui, row_range| {
-                for i in row_range {
-                    if let Some(result) = self.live_search_results.get(i) {
-                        let display_text = if std::path::Path::new(&result.file_path)
-                            .extension()
-                            .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf")) {
-                            format!("{} [Page {}] - {}", result.file_path, result.line_number, result.line_content)
-                        } else {
-                            format!("{} [Line {}] - {}", result.file_path, result.line_number, result.line_content)
-                        };
-                        
-                        egui::Frame::default()
-                            .fill(ui.style().visuals.widgets.inactive.bg_fill)
-                            .inner_margin(4.0)
-                            .corner_radius(4.0)
-                            .show(ui, |ui| {
-                                let response = ui.selectable_label(false, egui::RichText::new(display_text).color(egui::Color32::WHITE))
-                                    .on_hover_text(&result.file_path);
-
-                                response.context_menu(|ui| {
-                                    if ui.button("Open File").clicked() {
-                                        command_sender.send(Command::OpenFile(result.file_path.clone())).unwrap();
-                                        ui.close();
-                                    }
-                                    if ui.button("Open File Location").clicked() {
-                                        command_sender.send(Command::OpenLocation(result.file_path.clone())).unwrap();
-                                        ui.close();
-                                    }
-                                });
-                            });
-                        ui.add_space(2.0);
+            // Headers, hits and context lines are all one-line rows, so only the visible ones are laid out.
+            let row_height = ui.text_style_height(&egui::TextStyle::Body) + 4.0;
+            let total_rows = self.live_search_results.iter().map(LiveResultGroup::rows).sum();
+            let mut toggled = None;
+            ui.scope(|ui| {
+                ui.spacing_mut().item_spacing.y = 2.0;
+                egui::ScrollArea::vertical().show_rows(ui, row_height, total_rows, |ui, row_range| {
+                    let mut first_row = 0;
+                    for (index, group) in self.live_search_results.iter().enumerate() {
+                        if first_row >= row_range.end {
+                            break;
+                        }
+                        // The rows of this group that are visible, counted from its header.
+                        let rows = group.rows();
+                        let visible = row_range.start.saturating_sub(first_row)..(row_range.end - first_row).min(rows);
+                        first_row += rows;
+                        if visible.is_empty() {
+                            continue;
+                        }
+                        if visible.start == 0 && Self::draw_live_group_header(ui, group, command_sender).clicked() {
+                            toggled = Some(index);
+                        }
+                        let lines = group.lines().skip(visible.start.saturating_sub(1)).take(visible.len() - usize::from(visible.start == 0));
+                        for line in lines {
+                            Self::draw_live_result_line(ui, &group.file_path, &line, command_sender);
+                        }
                     }
-                }
+                });
             });
+            if let Some(index) = toggled {
+                let group = &mut self.live_search_results[index];
+                group.open = !group.open;
+            }
         }
     }
 
+    fn draw_live_group_header(ui: &mut egui::Ui, group: &LiveResultGroup, command_sender: &Sender<Command>) -> egui::Response {
+        let arrow = if group.open { "⏷" } else { "⏵" };
+        let count = group.hits.len();
+        let title = format!("{arrow} {} {} ({count} {})", utils::get_icon_for_path(&group.file_path, false), group.file_path, if count == 1 { "hit" } else { "hits" });
+        let response = ui.add(egui::Label::new(egui::RichText::new(title).strong()).sense(egui::Sense::click()).truncate())
+            .on_hover_text(&group.file_path);
+        Self::file_context_menu(&response, &group.file_path, command_sender);
+        response
+    }
+
+    fn draw_live_result_line(ui: &mut egui::Ui, file_path: &str, line: &LiveResultLine, command_sender: &Sender<Command>) {
+        ui.horizontal(|ui| {
+            ui.add_space(ui.spacing().indent);
+            match line {
+                LiveResultLine::Context(text) => {
+                    ui.add(egui::Label::new(egui::RichText::new(*text).weak()).truncate());
+                }
+                LiveResultLine::Hit(hit) => {
+                    egui::Frame::default()
+                        .fill(ui.style().visuals.widgets.inactive.bg_fill)
+                        .inner_margin(egui::Margin::symmetric(4, 0))
+                        .corner_radius(4.0)
+                        .show(ui, |ui| {
+                            let label = format!("[{}] ", hit.location);
+                            let response = ui.add(egui::Label::new(Self::highlighted_hit(ui, &label, hit)).sense(egui::Sense::click()).truncate())
+                                .on_hover_text(file_path);
+                            Self::file_context_menu(&response, file_path, command_sender);
+                        });
+                }
+            }
+        });
+    }
+
+    /// Lays out a hit's line after `label`, with the matched text highlighted.
+    fn highlighted_hit(ui: &egui::Ui, label: &str, hit: &LiveSearchResult) -> egui::text::LayoutJob {
+        let font_id = egui::TextStyle::Body.resolve(ui.style());
+        let normal = egui::TextFormat::simple(font_id, egui::Color32::WHITE);
+        let highlight = egui::TextFormat { background: ui.visuals().selection.bg_fill, ..normal.clone() };
+
+        let mut job = egui::text::LayoutJob::default();
+        job.append(label, 0.0, egui::TextFormat { color: ui.visuals().weak_text_color(), ..normal.clone() });
+        let text = hit.line_content.as_str();
+        let mut shown = 0;
+        for range in &hit.match_ranges {
+            let (Some(before), Some(matched)) = (text.get(shown..range.start), text.get(range.clone())) else {
+                break;
+            };
+            job.append(before, 0.0, normal.clone());
+            job.append(matched, 0.0, highlight.clone());
+            shown = range.end;
+        }
+        job.append(text.get(shown..).unwrap_or_default(), 0.0, normal);
+        job
+    }
+
+    fn file_context_menu(response: &egui::Response, file_path: &str, command_sender: &Sender<Command>) {
+        response.context_menu(|ui| {
+            if ui.button("Open File").clicked() {
+                command_sender.send(Command::OpenFile(file_path.to_string())).unwrap();
+                ui.close();
+            }
+            if ui.button("Open File Location").clicked() {
+                command_sender.send(Command::OpenLocation(file_path.to_string())).unwrap();
+                ui.close();
+            }
+        });
+    }
+
     fn draw_indexed_search_results(&self, ui: &mut egui::Ui, state: &AppState, command_sender: &Sender<Command>) {
         if self.search_results.is_empty() && !state.is_running_task {
             ui.add_space(10.0);
@@ -256,7 +851,12 @@ impl SearchTab {
 
                 for i in row_range {
                     if let Some(result) = self.search_results.get(i) {
-                        let truncated_path = SearchTab::truncate_path(&result.full_path, num_chars_to_keep);
+                        let details = result.metadata.as_ref().map(|metadata| match result.score {
+                            Some(score) => format!("{:.0}% · {}", score * 100.0, SearchTab::format_details(metadata)),
+                            None => SearchTab::format_details(metadata),
+                        });
+                        let details_chars = details.as_ref().map_or(0, |d| d.chars().count());
+                        let truncated_path = SearchTab::truncate_path(&result.full_path, num_chars_to_keep.saturating_sub(details_chars));
                         let display_text = format!("{} {}", result.icon, truncated_path);
                         
                         egui::Frame::default()
@@ -264,8 +864,16 @@ impl SearchTab {
                             .inner_margin(4.0)
                             .corner_radius(4.0)
                             .show(ui, |ui| {
-                                let response = ui.selectable_label(false, egui::RichText::new(display_text).color(egui::Color32::WHITE))
-                                    .on_hover_text(&*result.full_path);
+                                let response = ui.horizontal(|ui| {
+                                    let response = ui.selectable_label(false, egui::RichText::new(display_text).color(egui::Color32::WHITE))
+                                        .on_hover_text(SearchTab::format_tooltip(result));
+                                    if let Some(details) = &details {
+                                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                            ui.label(egui::RichText::new(details).weak());
+                                        });
+                                    }
+                                    response
+                                }).inner;
 
                                 response.context_menu(|ui| {
                                     if ui.button("Open File").clicked() {
@@ -285,6 +893,33 @@ impl SearchTab {
         }
     }
 
+    /// Short "size · modified date" summary shown at the end of an indexed result row.
+    fn format_details(metadata: &FileMetadata) -> String {
+        if metadata.is_dir {
+            utils::format_date(metadata.modified_time)
+        } else {
+            format!("{} · {}", utils::format_size(metadata.size), utils::format_date(metadata.modified_time))
+        }
+    }
+
+    fn format_tooltip(result: &DisplayResult) -> String {
+        let Some(metadata) = &result.metadata else {
+            return result.full_path.to_string();
+        };
+        let attributes: Vec<&str> = [(metadata.is_read_only, "read-only"), (metadata.is_hidden, "hidden")]
+            .into_iter()
+            .filter_map(|(set, name)| set.then_some(name))
+            .collect();
+        let attributes = if attributes.is_empty() { "-".to_string() } else { attributes.join(", ") };
+        format!(
+            "{}\nSize: {}\nModified: {}\nCreated: {}\nAttributes: {attributes}",
+            result.full_path,
+            utils::format_size(metadata.size),
+            utils::format_date(metadata.modified_time),
+            utils::format_date(metadata.created_time),
+        )
+    }
+
     fn truncate_path(path: &str, max_chars: usize) -> String {
         if path.chars().count() <= max_chars {
             return path.to_string();
@@ -1,52 +1,134 @@
+use std::collections::HashSet;
+use std::ops::Range;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::mpsc::Sender;
 use std::sync::OnceLock;
 use eframe::egui;
+use crate::content::ContentMatch;
+use crate::db::{FileMetadata, MigrationOutcome, RepairReport, SegmentLocation};
 use crate::pop::control::TaskController;
+use crate::query::QueryMode;
 
 pub enum Command {
     FetchLocations,
     StartSearch {
+        /// Tags every update of this search, so the GUI can drop those of superseded searches.
+        generation: u64,
         locations: Vec<(String, String)>,
         keyword: String,
+        query_mode: QueryMode,
+        match_normalized_name: bool,
+        match_full_path: bool,
         is_live_search_active: bool,
         live_search_path: Option<PathBuf>,
         search_in_content: bool,
         search_in_pdf: bool,
         search_in_office: bool,
         search_in_plain_text: bool,
+        content_exact_match: bool,
+        content_context_lines: usize,
         task_controller: Arc<TaskController>,
     },
     OpenFile(String),
     OpenLocation(String),
     DeleteLocation(String),
-    StartInitialScan { path: PathBuf, task_controller: Arc<TaskController> },
-    StartRescan { path: PathBuf, task_controller: Arc<TaskController> },
+    StartInitialScan { path: PathBuf, index_content: bool, hash_files: bool, resume: bool, task_controller: Arc<TaskController> },
+    StartRescan { path: PathBuf, full_rebuild: bool, task_controller: Arc<TaskController> },
+    VerifyAndRepair,
+    ExportLocation { path: String, destination: PathBuf },
+    ImportLocation { source: PathBuf, root_path: String, overwrite: bool },
+    RelocateLocation { path: String, new_path: PathBuf },
+    FindDuplicates { locations: Vec<(String, String)> },
 }
 
 #[derive(Debug)]
 pub enum GuiUpdate {
-    SearchResultsBatch(Vec<DisplayResult>),
-    LiveSearchResultsBatch(Vec<LiveSearchResult>),
+    /// Search updates carry the generation of the search they belong to (see `Command::StartSearch`).
+    SearchResultsBatch(u64, Vec<DisplayResult>),
+    LiveSearchResultsBatch(u64, Vec<LiveSearchResult>),
     ScanProgress(f32, String),
+    /// `ScanProgress` of a search; see `GuiSender::for_search`.
+    SearchProgress(u64, f32, String),
     ScanCompleted(usize),
-    SearchFinished,
-    LocationsUpdated(Vec<(String, String, u64)>),
+    RescanCompleted(RescanStats),
+    SearchFinished(u64),
+    /// The search pattern could not be compiled; shown next to the keyword field.
+    InvalidQuery(u64, String),
+    LocationsUpdated(Vec<(String, String, u64)>, HashSet<String>),
+    DatabaseMigrated(MigrationOutcome),
+    DatabaseUnavailable(String),
+    RepairCompleted(RepairReport),
+    ExportCompleted(u64, PathBuf),
+    ImportCompleted(String, u64),
+    DuplicatesFound { groups: Vec<DuplicateGroup>, unhashed_locations: Vec<String> },
+    LocationRelocated { old_path: String, new_path: String, found: usize, sampled: usize },
     Error(String),
+    /// `Error` of a search; see `GuiSender::for_search`.
+    SearchError(u64, String),
 }
 
+/// Counts of index entries changed by a delta rescan.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RescanStats {
+    pub added: usize,
+    pub modified: usize,
+    pub removed: usize,
+}
+
+/// Files with identical content, found through their stored hashes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DuplicateGroup {
+    pub size: u64,
+    pub paths: Vec<String>,
+}
+
+impl DuplicateGroup {
+    /// Space that would be freed by keeping a single copy.
+    pub fn wasted_size(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+/// A content search hit: one matching line, paragraph or row of a document.
 #[derive(Clone, Debug, PartialEq)]
 pub struct LiveSearchResult {
     pub file_path: String,
-    pub line_number: usize,
+    /// Page, paragraph, slide, row or line of the hit.
+    pub location: SegmentLocation,
     pub line_content: String,
+    /// Byte ranges of the matched text in `line_content`, for highlighting.
+    pub match_ranges: Vec<Range<usize>>,
+    /// The lines, paragraphs or rows right before and after the hit.
+    pub context_before: Vec<String>,
+    pub context_after: Vec<String>,
+}
+
+impl LiveSearchResult {
+    /// Displays a content match found in the document at `file_path`.
+    pub fn new(file_path: String, hit: ContentMatch) -> Self {
+        Self {
+            file_path,
+            location: hit.location,
+            line_content: hit.text,
+            match_ranges: hit.match_ranges,
+            context_before: hit.context_before,
+            context_after: hit.context_after,
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct DisplayResult {
     pub full_path: Arc<str>,
+    /// The indexed location, or the live search folder, the result was found in.
+    pub location: Arc<str>,
     pub icon: String,
+    pub metadata: Option<FileMetadata>,
+    /// Similarity to a fuzzy query, from 0 to 1; `None` for exact matches.
+    pub score: Option<f32>,
+    /// Relevance to the query from `Ranker`, the default order of the results.
+    pub relevance: f32,
 }
 
 /// Wraps `Sender<GuiUpdate>` to automatically call `ctx.request_repaint()` after each send,
@@ -55,14 +137,28 @@ pub struct DisplayResult {
 pub struct GuiSender {
     sender: Sender<GuiUpdate>,
     repaint_ctx: Arc<OnceLock<egui::Context>>,
+    /// Set on the sender of a search, see `for_search`.
+    search_generation: Option<u64>,
 }
 
 impl GuiSender {
     pub fn new(sender: Sender<GuiUpdate>, repaint_ctx: Arc<OnceLock<egui::Context>>) -> Self {
-        Self { sender, repaint_ctx }
+        Self { sender, repaint_ctx, search_generation: None }
+    }
+
+    /// Returns a sender for the search of `generation` that sends progress and errors as
+    /// `SearchProgress` and `SearchError`. The shared scan helpers report through it unchanged,
+    /// and the GUI drops these updates once the search is superseded, like its results.
+    pub fn for_search(&self, generation: u64) -> Self {
+        Self { search_generation: Some(generation), ..self.clone() }
     }
 
     pub fn send(&self, msg: GuiUpdate) -> Result<(), std::sync::mpsc::SendError<GuiUpdate>> {
+        let msg = match (self.search_generation, msg) {
+            (Some(generation), GuiUpdate::ScanProgress(progress, status)) => GuiUpdate::SearchProgress(generation, progress, status),
+            (Some(generation), GuiUpdate::Error(e)) => GuiUpdate::SearchError(generation, e),
+            (_, msg) => msg,
+        };
         let result = self.sender.send(msg);
         if let Some(ctx) = self.repaint_ctx.get() {
             ctx.request_repaint();
@@ -2,9 +2,14 @@
 
 use eframe::egui;
 
+mod config;
+mod content;
 mod db;
+mod transfer;
 mod pop;
 mod processes;
+mod query;
+mod ranking;
 mod utils;
 mod gui;
 
@@ -31,14 +36,23 @@ fn main() -> anyhow::Result<()> {
     let icon_bytes = include_bytes!("../assets/icon.png");
     let icon = load_icon_from_memory(icon_bytes)?;
 
+    // --- Content Extractors ---
+    // Formats beyond the built-in ones are registered here, before any search reads the registry.
+    let extractors = content::ExtractorRegistry::new();
+    content::install_extractors(extractors)?;
+
+    // --- Command-line Options ---
+    let launch_options = config::LaunchOptions::from_args();
+
     // --- Configure and Run the GUI ---
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default().with_inner_size([800.0, 600.0]).with_min_inner_size([600.0, 400.0]).with_icon(icon),
+        persistence_path: launch_options.persistence_path(),
         ..Default::default()
     };
     
     eframe::run_native(
-        "DeepSearch",
+        config::APP_NAME,
         native_options,
         Box::new(|cc| {
             // --- Font Setup ---
@@ -108,6 +122,22 @@ fn main() -> anyhow::Result<()> {
 
             app.set_repaint_ctx(cc.egui_ctx.clone());
 
+            // --- Start the Worker on the Selected Index Database ---
+            let (db_path, legacy_move) = launch_options.resolve_db_path(app.db_path_setting());
+            if let Some(legacy_move) = legacy_move {
+                let action = if legacy_move.copied { "copied" } else { "moved" };
+                let mut message = format!(
+                    "The index file of an older DeepSearch release was {action} from\n{}\nto\n{}",
+                    legacy_move.from.display(),
+                    legacy_move.to.display()
+                );
+                if legacy_move.copied {
+                    message.push_str("\n\nThe original file is no longer used and can be deleted.");
+                }
+                app.show_notice("Index Moved", message);
+            }
+            app.start_worker(db_path);
+
             // --- Load Background Texture ---
             let texture_handle = {
                 let image_bytes = include_bytes!("../assets/background.png");
@@ -1,5 +1,6 @@
-use crate::gui::events::GuiSender;
+use crate::gui::events::{GuiSender, RescanStats};
 use crate::pop::control::TaskController;
+use crate::query::QueryMode;
 use std::sync::mpsc::Receiver;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -7,6 +8,8 @@ use std::sync::Arc;
 #[allow(clippy::struct_excessive_bools)]
 pub struct Context {
     pub search_keyword: Option<String>,
+    /// Generation of the running search, echoed in its updates.
+    pub search_generation: u64,
 
     pub progress_reporter: Option<GuiSender>,
     pub live_search_root_path: Option<PathBuf>,
@@ -14,13 +17,24 @@ pub struct Context {
     pub search_in_pdf: bool,
     pub search_in_office: bool,
     pub search_in_plain_text: bool,
+    /// Content searches match the keyword as typed instead of its normalized words.
+    pub content_exact_match: bool,
+    /// Lines shown before and after each content hit.
+    pub content_context_lines: usize,
+    pub query_mode: QueryMode,
+    pub match_normalized_name: bool,
+    pub match_full_path: bool,
     pub task_controller: Option<Arc<TaskController>>,
 
     pub db_path: Option<PathBuf>,
     pub target_path: Option<PathBuf>,
-    pub file_data_stream: Option<Receiver<(String, crate::db::FileMetadata)>>,
+    pub index_content: bool,
+    pub hash_files: bool,
+    pub resume_scan: bool,
+    pub file_data_stream: Option<Receiver<crate::processes::scan::ScanEvent>>,
     pub files_found_count: usize,
     pub search_locations: Option<Vec<(String, String)>>,
     pub new_table_name: Option<String>,
     pub old_table_name: Option<String>,
+    pub rescan_stats: RescanStats,
 }
@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+use crate::db::DbManager;
+use crate::pop::context::Context;
+use crate::gui::events::{DuplicateGroup, GuiUpdate};
+
+/// Process: Groups the hashed files of the selected locations by size and content hash and
+/// sends every group with more than one file to the UI, largest wasted space first.
+pub fn find_duplicates(mut context: Context) -> anyhow::Result<Context> {
+    let db_path = context.db_path.as_ref().unwrap();
+    let reporter = context.progress_reporter.as_ref().unwrap();
+    let locations = context.search_locations.take().unwrap_or_default();
+
+    let db_manager = DbManager::new(db_path)?;
+    let mut files_by_hash: HashMap<(u64, [u8; 16]), Vec<String>> = HashMap::new();
+    let mut unhashed_locations = Vec::new();
+
+    for (i, (location_path, table_name)) in locations.iter().enumerate() {
+        #[allow(clippy::cast_precision_loss)]
+        reporter.send(GuiUpdate::ScanProgress(i as f32 / locations.len() as f32, format!("🧬 Comparing files in {location_path}...")))?;
+        if !db_manager.has_hash_index(table_name)? {
+            unhashed_locations.push(location_path.clone());
+            continue;
+        }
+        for (path, hash) in db_manager.get_all_hashes(table_name)? {
+            // Empty files are all identical; listing them as duplicates is just noise.
+            if hash.size == 0 {
+                continue;
+            }
+            let full_path = std::path::Path::new(location_path).join(&path).to_string_lossy().to_string();
+            files_by_hash.entry((hash.size, hash.digest)).or_default().push(full_path);
+        }
+    }
+
+    let mut groups: Vec<DuplicateGroup> = files_by_hash
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .map(|((size, _), mut paths)| {
+            paths.sort_unstable();
+            DuplicateGroup { size, paths }
+        })
+        .collect();
+    groups.sort_by(|a, b| b.wasted_size().cmp(&a.wasted_size()).then_with(|| a.paths.cmp(&b.paths)));
+
+    reporter.send(GuiUpdate::DuplicatesFound { groups, unhashed_locations })?;
+    Ok(context)
+}
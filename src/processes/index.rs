@@ -1,8 +1,19 @@
-use crate::db::{DbManager, FileMetadata};
+use crate::content;
+use crate::db::{ContentSegment, DbManager, FileHash, FileMetadata};
 use crate::pop::context::Context;
-use crate::gui::events::{GuiUpdate, GuiSender};
+use crate::utils;
+use crate::processes::scan::ScanEvent;
+use crate::gui::events::{GuiUpdate, GuiSender, RescanStats};
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::SystemTime;
 
 const BATCH_SIZE: usize = 50_000;
+/// Documents are extracted in much smaller groups so their text never piles up in memory.
+const CONTENT_BATCH_SIZE: usize = 256;
+/// Files hashed in parallel between two hash writes.
+const HASH_BATCH_SIZE: usize = 1024;
 
 /// A helper function to send progress updates if a reporter is available.
 fn report_progress(reporter: Option<&GuiSender>, progress: f32, message: &str) {
@@ -11,74 +22,287 @@ fn report_progress(reporter: Option<&GuiSender>, progress: f32, message: &str) {
     }
 }
 
+/// Writes a batch of file records and, when `index_content` is set, extracts the text of the
+/// supported documents in parallel and stores it in the location's content index.
+/// When `hash_source` is set, the files are also hashed; hashes stored in that table are reused
+/// for files whose size and modification time have not changed.
+fn write_batch(
+    db_manager: &DbManager,
+    table_name: &str,
+    root_path: &Path,
+    batch: &[(String, FileMetadata)],
+    index_content: bool,
+    hash_source: Option<&str>,
+) -> anyhow::Result<()> {
+    db_manager.write_to_table(table_name, batch)?;
+    if let Some(hash_source) = hash_source {
+        write_batch_hashes(db_manager, table_name, root_path, batch, hash_source)?;
+    }
+    if !index_content {
+        return Ok(());
+    }
+
+    let documents: Vec<&String> = batch
+        .iter()
+        .filter(|(_, metadata)| !metadata.is_dir && content::content_kind(&metadata.extension).is_some())
+        .map(|(path, _)| path)
+        .collect();
+
+    // Written even when empty so that the content tables exist and mark the location as content-indexed.
+    if documents.is_empty() {
+        return db_manager.write_content(table_name, &[]);
+    }
+
+    for chunk in documents.chunks(CONTENT_BATCH_SIZE) {
+        let extracted: Vec<(String, Vec<ContentSegment>)> = chunk
+            .par_iter()
+            .map(|path| ((*path).clone(), content::extract_segments(&root_path.join(path)).unwrap_or_default()))
+            .collect();
+        db_manager.write_content(table_name, &extracted)?;
+    }
+    Ok(())
+}
+
+fn write_batch_hashes(
+    db_manager: &DbManager,
+    table_name: &str,
+    root_path: &Path,
+    batch: &[(String, FileMetadata)],
+    hash_source: &str,
+) -> anyhow::Result<()> {
+    let files: Vec<&(String, FileMetadata)> = batch.iter().filter(|(_, metadata)| !metadata.is_dir).collect();
+    let keys: Vec<&str> = files.iter().map(|(path, _)| path.as_str()).collect();
+    let previous = db_manager.get_file_hashes(hash_source, &keys)?;
+
+    // Written even when empty so that the hash table exists and marks the location as hashed.
+    if files.is_empty() {
+        return db_manager.write_hashes(table_name, &[]);
+    }
+
+    for chunk in files.chunks(HASH_BATCH_SIZE) {
+        let results: Vec<(String, Option<FileHash>)> = chunk
+            .par_iter()
+            .map(|(path, metadata)| {
+                let unchanged = previous.get(path).copied()
+                    .filter(|hash| hash.size == metadata.size && hash.modified_time == metadata.modified_time);
+                let hash = unchanged.or_else(|| {
+                    utils::hash_file(&root_path.join(path)).map(|digest| FileHash {
+                        size: metadata.size,
+                        modified_time: metadata.modified_time,
+                        digest,
+                    })
+                });
+                (path.clone(), hash)
+            })
+            .collect();
+
+        let mut hashes: Vec<(String, FileHash)> = Vec::with_capacity(results.len());
+        let mut unreadable: Vec<String> = Vec::new();
+        for (path, hash) in results {
+            match hash {
+                Some(hash) => hashes.push((path, hash)),
+                None => unreadable.push(path),
+            }
+        }
+        db_manager.write_hashes(table_name, &hashes)?;
+        // A file that could not be read must not keep the hash of an older version.
+        db_manager.remove_hashes(table_name, &unreadable)?;
+    }
+    Ok(())
+}
+
 /// Process: Reads file data from the stream in the context and writes it to the DB in batches.
 pub fn write_index_from_stream_batched(mut context: Context) -> anyhow::Result<Context> {
     let reporter = context.progress_reporter.as_ref();
     let db_path = context.db_path.as_ref().unwrap();
-    let target_path = context.target_path.as_ref().unwrap().to_str().unwrap();
+    let root_path = context.target_path.as_ref().unwrap();
+    let target_path = root_path.to_str().unwrap();
+    let controller = context.task_controller.take()
+        .ok_or_else(|| anyhow::anyhow!("Task controller not available for indexing"))?;
     let rx = context.file_data_stream.take().unwrap(); // Take ownership of the receiver
 
     let db_manager = DbManager::new(db_path)?;
     let table_name = db_manager.get_or_create_table_name(target_path)?;
+    // A resumed scan keeps indexing contents if the interrupted one did.
+    let index_content = context.index_content || (context.resume_scan && db_manager.has_content_index(&table_name)?);
+    let hash_files = context.hash_files || (context.resume_scan && db_manager.has_hash_index(&table_name)?);
+    let hash_source = hash_files.then_some(table_name.as_str());
+    // A resumed scan keeps the formats of the interrupted one, so that the next rescan extracts
+    // newer formats from the files written before the interruption.
+    if index_content && !context.resume_scan {
+        db_manager.set_content_extensions(&table_name, &content::extractors().extensions())?;
+    }
 
     let mut batch: Vec<(String, FileMetadata)> = Vec::with_capacity(BATCH_SIZE);
+    // Subdirectories whose entries are all in `batch` or already written; checkpointed on flush.
+    let mut completed_subdirs: Vec<String> = Vec::new();
     let mut total_indexed_count = 0;
 
     report_progress(reporter, 0.90, "⚙️ Indexing files...");
 
-    for file_data in rx {
-        batch.push(file_data);
-        total_indexed_count += 1;
+    for event in rx {
+        match event {
+            ScanEvent::Entry(path, metadata) => {
+                batch.push((path, metadata));
+                total_indexed_count += 1;
+            }
+            ScanEvent::SubdirCompleted(subdir) => completed_subdirs.push(subdir),
+        }
         if batch.len() >= BATCH_SIZE {
-            db_manager.write_to_table(&table_name, &batch)?;
+            write_batch(&db_manager, &table_name, root_path, &batch, index_content, hash_source)?;
+            db_manager.add_scan_checkpoints(target_path, &completed_subdirs)?;
             report_progress(reporter, 0.90, &format!("⚙️ Indexed {total_indexed_count} files..."));
             batch.clear();
+            completed_subdirs.clear();
         }
     }
 
     // Write any remaining files in the last batch
     if !batch.is_empty() {
-        db_manager.write_to_table(&table_name, &batch)?;
+        write_batch(&db_manager, &table_name, root_path, &batch, index_content, hash_source)?;
         report_progress(reporter, 0.99, &format!("⚙️ Indexed {total_indexed_count} files, finalizing..."));
     }
 
+    // A cancelled scan stays partial and can be resumed from its checkpoints.
+    if controller.is_cancelled() {
+        db_manager.add_scan_checkpoints(target_path, &completed_subdirs)?;
+    } else {
+        db_manager.finish_scan(target_path)?;
+    }
+
     context.files_found_count = total_indexed_count;
     Ok(context)
 }
 
-/// Process: Reads file data from the stream in the context and writes it to the new DB table for rescan.
+/// Process: Reads file data from the stream in the context and writes it to a brand-new DB table
+/// for a full rebuild. The new table is swapped in by `rescan_atomic_swap_final`.
 pub fn rescan_write_index_from_stream_batched(mut context: Context) -> anyhow::Result<Context> {
     let reporter = context.progress_reporter.as_ref();
     let db_path = context.db_path.as_ref().unwrap();
-    let _root_path = context.target_path.as_ref().unwrap().to_str().unwrap();
-    let new_table_name = context.new_table_name.as_ref().unwrap();
+    let root_path = context.target_path.as_ref().unwrap();
+    let root_path_str = root_path.to_str().unwrap();
     let rx = context.file_data_stream.take().unwrap(); // Take ownership of the receiver
 
+    // Generate new table name
+    let new_table_name = format!("index_{:x}_{}",
+        md5::compute(root_path_str.as_bytes()),
+        SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?.as_secs()
+    );
+
     let db_manager = DbManager::new(db_path)?;
+    // The rebuilt index keeps the content index if the current one has it.
+    let index_content = match &context.old_table_name {
+        Some(old_table_name) => db_manager.has_content_index(old_table_name)?,
+        None => false,
+    };
+    // Hashes are kept as well, reusing the old ones for unchanged files.
+    let hash_source = match &context.old_table_name {
+        Some(old_table_name) if db_manager.has_hash_index(old_table_name)? => Some(old_table_name.as_str()),
+        _ => None,
+    };
+    if index_content {
+        db_manager.set_content_extensions(&new_table_name, &content::extractors().extensions())?;
+    }
     let mut batch: Vec<(String, FileMetadata)> = Vec::with_capacity(BATCH_SIZE);
     let mut total_indexed_count = 0;
 
-    report_progress(reporter, 0.33, "🔄 Rescan Phase 2/3: Building new index...");
+    report_progress(reporter, 0.33, "🔄 Full rebuild Phase 2/3: Building new index...");
 
-    for file_data in rx {
-        batch.push(file_data);
+    for event in rx {
+        let ScanEvent::Entry(path, metadata) = event else { continue };
+        batch.push((path, metadata));
         total_indexed_count += 1;
         if batch.len() >= BATCH_SIZE {
-            db_manager.write_to_table(new_table_name, &batch)?;
-            report_progress(reporter, 0.33, &format!("🔄 Rescan Phase 2/3: Indexed {total_indexed_count} files..."));
+            write_batch(&db_manager, &new_table_name, root_path, &batch, index_content, hash_source)?;
+            report_progress(reporter, 0.33, &format!("🔄 Full rebuild Phase 2/3: Indexed {total_indexed_count} files..."));
             batch.clear();
         }
     }
 
     // Write any remaining files in the last batch
     if !batch.is_empty() {
-        db_manager.write_to_table(new_table_name, &batch)?;
-        report_progress(reporter, 0.66, &format!("🔄 Rescan Phase 2/3: Indexed {total_indexed_count} files, finalizing..."));
+        write_batch(&db_manager, &new_table_name, root_path, &batch, index_content, hash_source)?;
+        report_progress(reporter, 0.66, &format!("🔄 Full rebuild Phase 2/3: Indexed {total_indexed_count} files, finalizing..."));
     }
 
+    context.new_table_name = Some(new_table_name);
     context.files_found_count = total_indexed_count;
     Ok(context)
 }
 
+/// Process: Compares the streamed walk against the location's existing table using the stored
+/// `modified_time`, and only inserts, updates or removes the keys that changed.
+/// Every batch is written in its own bounded transaction.
+pub fn rescan_apply_delta_batched(mut context: Context) -> anyhow::Result<Context> {
+    let reporter = context.progress_reporter.as_ref();
+    let db_path = context.db_path.as_ref().unwrap();
+    let root_path = context.target_path.as_ref().unwrap();
+    let table_name = context.old_table_name.take()
+        .ok_or_else(|| anyhow::anyhow!("Table name not found in context for delta rescan"))?;
+    let controller = context.task_controller.take()
+        .ok_or_else(|| anyhow::anyhow!("Task controller not available for delta rescan"))?;
+    let rx = context.file_data_stream.take().unwrap(); // Take ownership of the receiver
+
+    let db_manager = DbManager::new(db_path)?;
+    let index_content = db_manager.has_content_index(&table_name)?;
+    let hash_source = db_manager.has_hash_index(&table_name)?.then_some(table_name.as_str());
+    // Unchanged files of formats supported since the content index was built are extracted too.
+    let supported_extensions = content::extractors().extensions();
+    let new_extensions: HashSet<String> = if index_content {
+        db_manager.missing_content_extensions(&table_name, &supported_extensions)?.into_iter().collect()
+    } else {
+        HashSet::new()
+    };
+
+    report_progress(reporter, 0.01, "🔄 Rescan: Loading existing index...");
+    // Keys still left in this map after the walk no longer exist on disk.
+    let mut existing = db_manager.get_modified_times(&table_name)?;
 
+    let mut batch: Vec<(String, FileMetadata)> = Vec::with_capacity(BATCH_SIZE);
+    let mut stats = RescanStats::default();
+    let mut total_seen_count = 0;
 
+    for event in rx {
+        let ScanEvent::Entry(path, metadata) = event else { continue };
+        total_seen_count += 1;
+        match existing.remove(&path) {
+            None => stats.added += 1,
+            Some(modified_time) if modified_time != metadata.modified_time => stats.modified += 1,
+            Some(_) if !metadata.is_dir && new_extensions.contains(&metadata.extension) => {}
+            Some(_) => continue,
+        }
+        batch.push((path, metadata));
+        if batch.len() >= BATCH_SIZE {
+            write_batch(&db_manager, &table_name, root_path, &batch, index_content, hash_source)?;
+            report_progress(reporter, 0.90, &format!("🔄 Rescan: {} added, {} modified so far...", stats.added, stats.modified));
+            batch.clear();
+        }
+    }
+
+    if !batch.is_empty() {
+        write_batch(&db_manager, &table_name, root_path, &batch, index_content, hash_source)?;
+    }
+
+    // An interrupted walk has not seen every file, so nothing can be considered removed.
+    if controller.is_cancelled() {
+        report_progress(reporter, 1.0, "Rescan cancelled, removals skipped.");
+    } else {
+        // A complete walk also completes an interrupted initial scan.
+        db_manager.finish_scan(root_path.to_str().unwrap())?;
+        if index_content {
+            db_manager.set_content_extensions(&table_name, &supported_extensions)?;
+        }
+        let removed: Vec<String> = existing.into_keys().collect();
+        for chunk in removed.chunks(BATCH_SIZE) {
+            db_manager.remove_from_table(&table_name, chunk)?;
+            db_manager.remove_content(&table_name, chunk)?;
+            db_manager.remove_hashes(&table_name, chunk)?;
+            stats.removed += chunk.len();
+            report_progress(reporter, 0.95, &format!("🔄 Rescan: Removed {} deleted files...", stats.removed));
+        }
+    }
+
+    context.files_found_count = total_seen_count;
+    context.rescan_stats = stats;
+    Ok(context)
+}
@@ -1,14 +1,14 @@
+use crate::content::{self, ContentKind, ContentMatcher};
+use crate::db::FileMetadata;
 use crate::pop::context::Context;
+use crate::query::{Candidate, NameMatcher};
+use crate::ranking::Ranker;
 use crate::utils;
 use anyhow::Result;
-use std::fs::File;
-use std::io::{BufReader, BufRead, Read};
 use std::thread;
 use std::sync::{Arc, Mutex};
 use std::mem;
-use crate::gui::events::{GuiUpdate, LiveSearchResult, DisplayResult};
-use calamine::{open_workbook, Reader, Xlsx};
-use docx_rs::{read_docx, DocumentChild, ParagraphChild, RunChild};
+use crate::gui::events::{GuiUpdate, DisplayResult, LiveSearchResult};
 
 const BATCH_SIZE: usize = 1;
 
@@ -18,13 +18,22 @@ const BATCH_SIZE: usize = 1;
 pub fn live_search_2_phase(mut context: Context) -> Result<Context> {
     let root_path = context.live_search_root_path.take().ok_or_else(|| anyhow::anyhow!("Live search path not provided"))?;
     let search_keyword = context.search_keyword.take().ok_or_else(|| anyhow::anyhow!("Search keyword not provided"))?;
-    let normalized_keyword = utils::normalize_string(&search_keyword);
     let search_in_content = context.search_in_content;
     let search_in_pdf = context.search_in_pdf;
     let search_in_office = context.search_in_office;
     let search_in_plain_text = context.search_in_plain_text;
+    let content_matcher = ContentMatcher::new(&search_keyword, context.content_exact_match);
+    let context_lines = context.content_context_lines;
     let reporter = context.progress_reporter.take().ok_or_else(|| anyhow::anyhow!("Reporter not available"))?;
+    let generation = context.search_generation;
     let controller = context.task_controller.take().ok_or_else(|| anyhow::anyhow!("Task controller not available"))?;
+    let matcher = match NameMatcher::compile(context.query_mode, &search_keyword, context.match_normalized_name, context.match_full_path) {
+        Ok(matcher) => matcher,
+        Err(e) => {
+            reporter.send(GuiUpdate::InvalidQuery(generation, e))?;
+            return Ok(context);
+        }
+    };
 
     utils::report_progress(Some(&reporter), 0.0, &format!("🔍 Starting 2-phase live search for '{}' in '{}'...", search_keyword, root_path.display()));
 
@@ -34,125 +43,65 @@ pub fn live_search_2_phase(mut context: Context) -> Result<Context> {
 
         // NOTE: This search_action is now "clean". It contains no controller logic.
         // It is designed to be passed into the `controlled_two_phase_scan` helper.
+        let root_path_str = root_path.to_string_lossy();
+        let location: Arc<str> = root_path_str.as_ref().into();
+        let ranker = Ranker::new(&matcher);
         let search_action = |entry: walkdir::DirEntry| {
             if search_in_content {
+                if entry.file_type().is_dir() {
+                    return;
+                }
                 let path = entry.path();
-                let extension = path.extension().and_then(|s| s.to_str());
-                match extension {
-                    Some("pdf") if search_in_pdf => {
-                        if let Ok(text_content) = pdf_extract::extract_text(entry.path()) {
-                            for (page_num, page_text) in text_content.split('\x0C').enumerate() {
-                                if page_text.contains(&search_keyword) {
-                                    let snippet = page_text.lines().find(|l| l.contains(&search_keyword)).unwrap_or("").trim().to_string();
-                                    let result = LiveSearchResult {
-                                        file_path: entry.path().to_string_lossy().to_string(),
-                                        line_number: page_num + 1,
-                                        line_content: snippet,
-                                    };
-                                    let mut batch = live_results_batch.lock().unwrap();
-                                    batch.push(result);
-                                    if batch.len() >= BATCH_SIZE {
-                                        reporter.send(GuiUpdate::LiveSearchResultsBatch(mem::take(&mut *batch))).ok();
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    Some("docx") if search_in_office => {
-                         if let Ok(mut file) = File::open(entry.path()) {
-                             let mut buf = Vec::new();
-                             if file.read_to_end(&mut buf).is_ok() {
-                                 if let Ok(docx) = read_docx(&buf) {
-                                     let mut full_text = String::new();
-                                     for child in docx.document.children {
-                                         if let DocumentChild::Paragraph(p) = child {
-                                             for p_child in p.children {
-                                                 if let ParagraphChild::Run(r) = p_child {
-                                                     for r_child in r.children {
-                                                         if let RunChild::Text(t) = r_child {
-                                                             full_text.push_str(&t.text);
-                                                         }
-                                                     }
-                                                 }
-                                             }
-                                             full_text.push('\n');
-                                         }
-                                     }
-                                     if full_text.contains(&search_keyword) {
-                                         let snippet = full_text.lines().find(|l| l.contains(&search_keyword)).unwrap_or("").trim().to_string();
-                                         let result = LiveSearchResult {
-                                             file_path: entry.path().to_string_lossy().to_string(),
-                                             line_number: 1,
-                                             line_content: snippet,
-                                         };
-                                         let mut batch = live_results_batch.lock().unwrap();
-                                         batch.push(result);
-                                         if batch.len() >= BATCH_SIZE {
-                                             reporter.send(GuiUpdate::LiveSearchResultsBatch(mem::take(&mut *batch))).ok();
-                                         }
-                                     }
-                                 }
-                             }
-                         }
-                    }
-                    Some("xlsx") if search_in_office => {
-                        if let Ok(mut workbook) = open_workbook::<Xlsx<_>, _>(entry.path()) {
-                            for sheet_name in workbook.sheet_names().clone() {
-                                if let Ok(range) = workbook.worksheet_range(&sheet_name) {
-                                    for (i, row) in range.rows().enumerate() {
-                                        let row_text: String = row.iter().map(ToString::to_string).collect::<Vec<_>>().join(" | ");
-                                        if row_text.contains(&search_keyword) {
-                                            let result = LiveSearchResult {
-                                                file_path: entry.path().to_string_lossy().to_string(),
-                                                line_number: i + 1,
-                                                line_content: row_text.trim().to_string(),
-                                            };
-                                            let mut batch = live_results_batch.lock().unwrap();
-                                            batch.push(result);
-                                            if batch.len() >= BATCH_SIZE {
-                                                reporter.send(GuiUpdate::LiveSearchResultsBatch(mem::take(&mut *batch))).ok();
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    Some("txt" | "md" | "log" | "rs" | "py" | "js" | "html" | "css" | "json" | "xml" | "toml") if search_in_plain_text => {
-                        if let Ok(file) = File::open(entry.path()) {
-                            let reader = BufReader::new(file);
-                            for (line_number, line) in reader.lines().enumerate() {
-                                if let Ok(line_content) = line {
-                                    if line_content.contains(&search_keyword) {
-                                        let result = LiveSearchResult {
-                                            file_path: entry.path().to_string_lossy().to_string(),
-                                            line_number: line_number + 1,
-                                            line_content: line_content.trim().to_string(),
-                                        };
-                                        let mut batch = live_results_batch.lock().unwrap();
-                                        batch.push(result);
-                                        if batch.len() >= BATCH_SIZE {
-                                            reporter.send(GuiUpdate::LiveSearchResultsBatch(mem::take(&mut *batch))).ok();
-                                        }
-                                    }
-                                }
-                            }
-                        }
+                let Some(extractor) = content::extractors().for_path(path) else {
+                    return;
+                };
+                let included = match extractor.kind() {
+                    ContentKind::Pdf => search_in_pdf,
+                    ContentKind::Office => search_in_office,
+                    ContentKind::PlainText => search_in_plain_text,
+                };
+                if !included {
+                    return;
+                }
+                let Ok(segments) = extractor.extract(path) else {
+                    return;
+                };
+                let segments: Vec<_> = segments.collect();
+                let hits = content::find_hits(&segments, &content_matcher, context_lines);
+                if !hits.is_empty() {
+                    // All hits of a file travel together, so its group is complete when it appears.
+                    let file_path = path.to_string_lossy();
+                    let mut batch = live_results_batch.lock().unwrap();
+                    batch.extend(hits.into_iter().map(|hit| LiveSearchResult::new(file_path.to_string(), hit)));
+                    if batch.len() >= BATCH_SIZE {
+                        reporter.send(GuiUpdate::LiveSearchResultsBatch(generation, mem::take(&mut *batch))).ok();
                     }
-                    _ => {}
                 }
             } else {
-                let query_tokens: Vec<&str> = normalized_keyword.split_whitespace().collect();
-                let normalized_filename = utils::normalize_string(&entry.file_name().to_string_lossy());
-                if utils::contains_all_tokens(&normalized_filename, &query_tokens) {
+                // Size and dates are only read from disk when the query filters on them.
+                let metadata = if matcher.needs_file_stats() {
+                    FileMetadata::from_dir_entry(&entry)
+                } else {
+                    FileMetadata::from_dir_entry_name(&entry)
+                };
+                let relative_path = entry.path().strip_prefix(&root_path).unwrap_or(entry.path()).to_string_lossy();
+                let normalized_path = if matcher.needs_paths() { utils::normalize_path(&relative_path) } else { String::new() };
+                let candidate = Candidate { location: &root_path_str, relative_path: &relative_path, metadata: &metadata, normalized_path: &normalized_path };
+                let score = if matcher.is_empty() { None } else { matcher.score(&candidate) };
+                if let Some(score) = score {
+                    let metadata = if matcher.needs_file_stats() { metadata } else { FileMetadata::from_dir_entry(&entry) };
                     let result = DisplayResult {
                         full_path: entry.path().to_string_lossy().to_string().into(),
-                        icon: utils::get_icon_for_path(&entry.path().to_string_lossy()).to_string(),
+                        icon: utils::get_icon_for_path(&entry.path().to_string_lossy(), entry.file_type().is_dir()).to_string(),
+                        location: location.clone(),
+                        relevance: ranker.relevance(score, &relative_path, &metadata),
+                        metadata: Some(metadata),
+                        score: matcher.is_fuzzy().then_some(score),
                     };
                     let mut batch = indexed_results_batch.lock().unwrap();
                     batch.push(result);
                     if batch.len() >= BATCH_SIZE {
-                        reporter.send(GuiUpdate::SearchResultsBatch(mem::take(&mut *batch))).ok();
+                        reporter.send(GuiUpdate::SearchResultsBatch(generation, mem::take(&mut *batch))).ok();
                     }
                 }
             }
@@ -164,15 +113,15 @@ pub fn live_search_2_phase(mut context: Context) -> Result<Context> {
         // Send any remaining results
         let mut live_batch = live_results_batch.lock().unwrap();
         if !live_batch.is_empty() {
-            reporter.send(GuiUpdate::LiveSearchResultsBatch(mem::take(&mut *live_batch))).ok();
+            reporter.send(GuiUpdate::LiveSearchResultsBatch(generation, mem::take(&mut *live_batch))).ok();
         }
         let mut indexed_batch = indexed_results_batch.lock().unwrap();
         if !indexed_batch.is_empty() {
-            reporter.send(GuiUpdate::SearchResultsBatch(mem::take(&mut *indexed_batch))).ok();
+            reporter.send(GuiUpdate::SearchResultsBatch(generation, mem::take(&mut *indexed_batch))).ok();
         }
 
         // Signal completion
-        reporter.send(GuiUpdate::SearchFinished).ok();
+        reporter.send(GuiUpdate::SearchFinished(generation)).ok();
     });
 
     Ok(context)
@@ -4,3 +4,4 @@ pub mod output;
 pub mod scan;
 pub mod search;
 pub mod live_search;
+pub mod duplicates;
@@ -2,77 +2,101 @@
 use crate::db::{DbManager, FileMetadata};
 use crate::pop::context::Context;
 use crate::utils;
-use std::sync::mpsc;
-use std::time::SystemTime;
+use crate::gui::events::GuiSender;
+use crate::pop::control::TaskController;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc};
 use std::thread;
-use redb::TableDefinition;
 
 
 
 
-// --- PROCESSES ---
+// --- HELPERS ---
 
-/// Process: Scans the directory using a throughput-optimized parallel method (jwalk + `par_bridge`)
-/// and streams file data. This process is controllable.
-pub fn scan_directory_streaming(mut context: Context) -> anyhow::Result<Context> {
-    let root_path = context.target_path.as_ref().unwrap().clone();
-    let reporter = context.progress_reporter.clone();
-    let controller = context.task_controller.take().ok_or_else(|| anyhow::anyhow!("Task controller not available for scan"))?;
-    let (tx, rx) = mpsc::channel();
-
-    utils::report_progress(reporter.as_ref(), 0.0, &format!("🔍 Starting initial scan for '{}'...", root_path.display()));
+/// An item of the stream produced by the scanning processes.
+pub enum ScanEvent {
+    /// A walked file or directory, keyed by its path relative to the scanned root.
+    Entry(String, FileMetadata),
+    /// Every entry below this top-level subdirectory has already been sent.
+    SubdirCompleted(String),
+}
 
-    let action_root_path = root_path.clone();
-    let tx_clone = tx.clone(); // Clone tx for the closure
+/// Spawns the controllable 2-phase walk of `root_path` on a background thread and streams a
+/// `ScanEvent::Entry` for every file and directory found. The contents of the top-level
+/// subdirectories in `completed_subdirs` are skipped.
+fn spawn_metadata_stream(
+    root_path: PathBuf,
+    reporter: Option<GuiSender>,
+    controller: Arc<TaskController>,
+    completed_subdirs: HashSet<String>,
+) -> mpsc::Receiver<ScanEvent> {
+    let (tx, rx) = mpsc::channel();
 
     thread::spawn(move || {
         let action = |entry: walkdir::DirEntry| {
             let relative_path = entry
                 .path()
-                .strip_prefix(&action_root_path)
+                .strip_prefix(&root_path)
                 .unwrap()
                 .to_string_lossy()
                 .to_string();
 
-            let metadata = match entry.metadata() {
-                Ok(meta) => FileMetadata {
-                    normalized_name: utils::normalize_string(&entry.file_name().to_string_lossy()),
-                    modified_time: meta.modified()
-                        .ok()
-                        .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
-                        .map_or(0, |d| d.as_secs()),
-                },
-                Err(_) => FileMetadata {
-                    normalized_name: utils::normalize_string(&entry.file_name().to_string_lossy()),
-                    modified_time: 0,
-                },
-            };
-
-            tx_clone.send((relative_path, metadata)).ok();
+            let metadata = FileMetadata::from_dir_entry(&entry);
+
+            tx.send(ScanEvent::Entry(relative_path, metadata)).ok();
         };
 
-        utils::controlled_two_phase_scan(
-            &action_root_path,
+        utils::resumable_two_phase_scan(
+            &root_path,
             reporter.as_ref(),
             &controller,
+            &completed_subdirs,
             action,
+            |subdir| { tx.send(ScanEvent::SubdirCompleted(subdir)).ok(); },
         );
     });
 
-    context.file_data_stream = Some(rx);
+    rx
+}
+
+// --- PROCESSES ---
+
+/// Process: Scans the directory using a throughput-optimized parallel method (jwalk + `par_bridge`)
+/// and streams file data. This process is controllable. When `resume_scan` is set, the
+/// subdirectories checkpointed by an interrupted scan are skipped.
+pub fn scan_directory_streaming(mut context: Context) -> anyhow::Result<Context> {
+    let root_path = context.target_path.as_ref().unwrap().clone();
+    let reporter = context.progress_reporter.clone();
+    let controller = context.task_controller.clone().ok_or_else(|| anyhow::anyhow!("Task controller not available for scan"))?;
+    let db_manager = DbManager::new(context.db_path.as_ref().unwrap())?;
+    let root_path_str = root_path.to_str().unwrap();
+
+    // The scan stays marked as partial until `write_index_from_stream_batched` completes it.
+    db_manager.begin_scan(root_path_str, context.resume_scan)?;
+    let completed_subdirs = if context.resume_scan {
+        let completed_subdirs = db_manager.get_scan_checkpoints(root_path_str)?;
+        utils::report_progress(reporter.as_ref(), 0.0, &format!("🔍 Resuming scan for '{}', {} folders already done...", root_path.display(), completed_subdirs.len()));
+        completed_subdirs
+    } else {
+        utils::report_progress(reporter.as_ref(), 0.0, &format!("🔍 Starting initial scan for '{}'...", root_path.display()));
+        HashSet::new()
+    };
+
+    context.file_data_stream = Some(spawn_metadata_stream(root_path, reporter, controller, completed_subdirs));
     Ok(context)
 }
 
-/// Process: Scans the directory for a rescan operation, generates new table name,
-/// retrieves old table name, and streams file data.
+/// Process: Scans the directory for a rescan operation, retrieves the current table name
+/// of the location, and streams file data. The task controller is left in the context so
+/// that later processes can tell whether the walk was cancelled.
 pub fn rescan_scan_streaming(mut context: Context) -> anyhow::Result<Context> {
     let root_path = context.target_path.as_ref().unwrap().clone();
     let db_path = context.db_path.as_ref().unwrap().clone();
     let reporter = context.progress_reporter.clone();
-    let controller = context.task_controller.take().ok_or_else(|| anyhow::anyhow!("Task controller not available for rescan scan"))?;
-    let (tx, rx) = mpsc::channel();
+    let controller = context.task_controller.clone().ok_or_else(|| anyhow::anyhow!("Task controller not available for rescan scan"))?;
 
-    utils::report_progress(reporter.as_ref(), 0.0, &format!("🔄 Rescan Phase 1/3: Scanning for '{}'...", root_path.display()));
+    utils::report_progress(reporter.as_ref(), 0.0, &format!("🔄 Rescan: Scanning '{}'...", root_path.display()));
 
     let db_manager = DbManager::new(&db_path)?;
     let root_path_str = root_path.to_str().unwrap();
@@ -81,59 +105,13 @@ pub fn rescan_scan_streaming(mut context: Context) -> anyhow::Result<Context> {
     let old_table_name = db_manager.get_table_name(root_path_str)?
         .ok_or_else(|| anyhow::anyhow!("Could not find old table name for location '{root_path_str}'"))?;
 
-    // Generate new table name
-    let new_table_name = format!("index_{:x}_{}",
-        md5::compute(root_path_str.as_bytes()),
-        SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?.as_secs()
-    );
-
-    // Store table names in context for subsequent processes
+    // Store the table name in context for subsequent processes
     context.old_table_name = Some(old_table_name);
-    context.new_table_name = Some(new_table_name.clone());
-
-    let action_root_path = root_path.clone();
-    let tx_clone = tx.clone(); // Clone tx for the closure
-
-    thread::spawn(move || {
-        let action = |entry: walkdir::DirEntry| {
-            let relative_path = entry
-                .path()
-                .strip_prefix(&action_root_path)
-                .unwrap()
-                .to_string_lossy()
-                .to_string();
 
-            let metadata = match entry.metadata() {
-                Ok(meta) => FileMetadata {
-                    normalized_name: utils::normalize_string(&entry.file_name().to_string_lossy()),
-                    modified_time: meta.modified()
-                        .ok()
-                        .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
-                        .map_or(0, |d| d.as_secs()),
-                },
-                Err(_) => FileMetadata {
-                    normalized_name: utils::normalize_string(&entry.file_name().to_string_lossy()),
-                    modified_time: 0,
-                },
-            };
-
-            tx_clone.send((relative_path, metadata)).ok();
-        };
-
-        utils::controlled_two_phase_scan(
-            &action_root_path,
-            reporter.as_ref(),
-            &controller,
-            action,
-        );
-    });
-
-    context.file_data_stream = Some(rx);
+    context.file_data_stream = Some(spawn_metadata_stream(root_path, reporter, controller, HashSet::new()));
     Ok(context)
 }
 
-
-
 /// Process: Performs the final atomic swapof the new index table with the old one, and cleans up.
 pub fn rescan_atomic_swap_final(mut context: Context) -> anyhow::Result<Context> {
     let root_path = context.target_path.as_ref().unwrap().clone();
@@ -146,20 +124,19 @@ pub fn rescan_atomic_swap_final(mut context: Context) -> anyhow::Result<Context>
     let old_table_name = context.old_table_name.take()
         .ok_or_else(|| anyhow::anyhow!("Old table name not found in context for atomic swap"))?;
 
-    utils::report_progress(reporter.as_ref(), 0.66, "🔄 Rescan Phase 3/3: Swapping index and cleaning up...");
+    utils::report_progress(reporter.as_ref(), 0.66, "🔄 Full rebuild Phase 3/3: Swapping index and cleaning up...");
 
     let db_manager = DbManager::new(&db_path)?;
 
     // Atomically swap tables
     db_manager.swap_location_table(root_path_str, &new_table_name)?;
     
-    // Delete the old table
+    // Delete the old table and its trigram postings
     let delete_txn = db_manager.db.begin_write()?;
-    let old_table_def: TableDefinition<&str, &[u8]> = TableDefinition::new(&old_table_name);
-    delete_txn.delete_table(old_table_def)?;
+    DbManager::delete_index_tables(&delete_txn, &old_table_name)?;
     delete_txn.commit()?;
 
-    utils::report_progress(reporter.as_ref(), 1.0, "✅ Rescan complete.");
+    utils::report_progress(reporter.as_ref(), 1.0, "✅ Full rebuild complete.");
 
     Ok(context)
 }
@@ -1,7 +1,13 @@
-use crate::db::DbManager;
+use crate::db::{ContentHit, DbManager};
 use crate::pop::context::Context;
+use crate::pop::control::TaskController;
+use crate::query::NameMatcher;
+use crate::ranking::Ranker;
 use crate::utils;
-use crate::gui::events::{GuiUpdate, DisplayResult};
+use crate::content::{self, ContentKind, ContentMatch, ContentMatcher};
+use crate::gui::events::{GuiUpdate, DisplayResult, LiveSearchResult};
+
+use std::sync::Arc;
 
 const BATCH_SIZE: usize = 200; // Send results in small batches for a responsive UI
 
@@ -10,8 +16,23 @@ pub fn search_index(mut context: Context) -> anyhow::Result<Context> {
     let db_path = context.db_path.as_ref().unwrap();
     let raw_keyword = context.search_keyword.as_ref().unwrap();
     let reporter = context.progress_reporter.as_ref().unwrap();
+    let generation = context.search_generation;
+    let controller = context.task_controller.clone()
+        .ok_or_else(|| anyhow::anyhow!("Task controller not available for search"))?;
 
     let normalized_keyword = utils::normalize_string(raw_keyword);
+    // Content searches always match words; the query mode applies to file names.
+    let matcher = if context.search_in_content {
+        None
+    } else {
+        match NameMatcher::compile(context.query_mode, raw_keyword, context.match_normalized_name, context.match_full_path) {
+            Ok(matcher) => Some(matcher),
+            Err(e) => {
+                reporter.send(GuiUpdate::InvalidQuery(generation, e))?;
+                return Ok(context);
+            }
+        }
+    };
     reporter.send(GuiUpdate::ScanProgress(0.0, format!("🔍 Searching for '{raw_keyword}'...")))?;
 
     let db_manager = DbManager::new(db_path)?;
@@ -23,41 +44,116 @@ pub fn search_index(mut context: Context) -> anyhow::Result<Context> {
     if let Some(locations_to_search) = locations_to_search {
         let num_locations = locations_to_search.len();
         if num_locations == 0 {
-            reporter.send(GuiUpdate::SearchFinished)?;
+            reporter.send(GuiUpdate::SearchFinished(generation))?;
             return Ok(context); // No locations to search
         }
 
         for (i, (location_path, table_name)) in locations_to_search.iter().enumerate() {
+            // A newer search replaces this one.
+            if controller.is_cancelled() {
+                break;
+            }
             #[allow(clippy::cast_precision_loss)]
             reporter.send(GuiUpdate::ScanProgress(i as f32 / num_locations as f32, format!("Searching in {location_path}...")))?;
             
-            let found_paths = db_manager.search_in_table(table_name, &normalized_keyword)?;
-
-            for path in found_paths {
-                let full_path = std::path::Path::new(location_path).join(&path).to_string_lossy().to_string();
-                
-                let display_result = DisplayResult {
-                    icon: utils::get_icon_for_path(&full_path).to_string(),
-                    full_path: full_path.into(),
-                };
-
-                batch.push(display_result);
-                total_found += 1;
-
-                if batch.len() >= BATCH_SIZE {
-                    reporter.send(GuiUpdate::SearchResultsBatch(batch.clone()))?;
-                    batch.clear();
+            let Some(matcher) = &matcher else {
+                total_found += search_content_index(&db_manager, &context, location_path, table_name, &normalized_keyword, &controller)?;
+                continue;
+            };
+
+            let ranker = Ranker::new(matcher);
+            let location: Arc<str> = location_path.as_str().into();
+
+            db_manager.search_in_table(table_name, location_path, matcher, &controller, |found_paths| {
+                for (path, metadata, score) in found_paths {
+                    let full_path = std::path::Path::new(location_path).join(&path).to_string_lossy().to_string();
+
+                    let display_result = DisplayResult {
+                        icon: utils::get_icon_for_path(&full_path, metadata.is_dir).to_string(),
+                        full_path: full_path.into(),
+                        location: location.clone(),
+                        relevance: ranker.relevance(score, &path, &metadata),
+                        metadata: Some(metadata),
+                        score: matcher.is_fuzzy().then_some(score),
+                    };
+
+                    batch.push(display_result);
+                    total_found += 1;
+
+                    if batch.len() >= BATCH_SIZE {
+                        reporter.send(GuiUpdate::SearchResultsBatch(generation, batch.clone()))?;
+                        batch.clear();
+                    }
                 }
-            }
+                Ok(())
+            })?;
         }
     }
 
     // Send the final batch if any results are left
     if !batch.is_empty() {
-        reporter.send(GuiUpdate::SearchResultsBatch(batch))?;
+        reporter.send(GuiUpdate::SearchResultsBatch(generation, batch))?;
     }
 
-    reporter.send(GuiUpdate::SearchFinished)?;
+    reporter.send(GuiUpdate::SearchFinished(generation))?;
     context.files_found_count = total_found; // Reuse this field to pass the final count
     Ok(context)
-}
\ No newline at end of file
+}
+
+/// Streams the hits of a location's persistent content index as `LiveSearchResult`s, so they
+/// are displayed exactly like live content search results. Locations indexed without their
+/// contents are skipped. Returns the number of hits sent, stopping early once `controller` is cancelled.
+fn search_content_index(
+    db_manager: &DbManager,
+    context: &Context,
+    location_path: &str,
+    table_name: &str,
+    normalized_keyword: &str,
+    controller: &TaskController,
+) -> anyhow::Result<usize> {
+    let reporter = context.progress_reporter.as_ref().unwrap();
+    let generation = context.search_generation;
+
+    // The index only stores normalized words, so exact matches are checked on the original text.
+    let content_matcher = ContentMatcher::new(context.search_keyword.as_deref().unwrap_or_default(), context.content_exact_match);
+
+    let mut total_found = 0;
+    let has_index = db_manager.search_content_in_table(table_name, normalized_keyword, context.content_context_lines, controller, |hits| {
+        let mut batch = Vec::with_capacity(hits.len());
+        for ContentHit { key: path, segment, context_before, context_after } in hits {
+            if context.content_exact_match && !content_matcher.is_match(&segment.text) {
+                continue;
+            }
+            let extension = std::path::Path::new(&path).extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default();
+            let included = match content::content_kind(&extension) {
+                Some(ContentKind::Pdf) => context.search_in_pdf,
+                Some(ContentKind::Office) => context.search_in_office,
+                Some(ContentKind::PlainText) => context.search_in_plain_text,
+                None => false,
+            };
+            if !included {
+                continue;
+            }
+
+            let hit = ContentMatch {
+                location: segment.location,
+                match_ranges: content_matcher.match_ranges(&segment.text),
+                text: segment.text,
+                context_before,
+                context_after,
+            };
+            batch.push(LiveSearchResult::new(std::path::Path::new(location_path).join(&path).to_string_lossy().to_string(), hit));
+        }
+
+        total_found += batch.len();
+        if !batch.is_empty() {
+            reporter.send(GuiUpdate::LiveSearchResultsBatch(generation, batch))?;
+        }
+        Ok(())
+    })?;
+
+    if !has_index {
+        reporter.send(GuiUpdate::ScanProgress(0.0, format!("{location_path} has no content index, skipped.")))?;
+    }
+    Ok(total_found)
+}
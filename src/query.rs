@@ -0,0 +1,1005 @@
+use std::collections::HashMap;
+use regex::{Regex, RegexBuilder};
+use unicode_width::UnicodeWidthStr;
+use serde::{Deserialize, Serialize};
+use crate::db::FileMetadata;
+use crate::utils;
+
+/// How the search keyword is matched against file names.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QueryMode {
+    /// Words, phrases and field filters (see `Query`), matched case- and diacritic-insensitively.
+    #[default]
+    Tokens,
+    /// A wildcard pattern (`*`, `?`, `[abc]`) that must match the whole name, ignoring case.
+    Glob,
+    /// A regular expression that must match somewhere in the name.
+    Regex,
+    /// Typo-tolerant words, ignoring the spacing between them; results are ranked by similarity.
+    Fuzzy,
+}
+
+impl QueryMode {
+    pub const ALL: [Self; 4] = [Self::Tokens, Self::Fuzzy, Self::Glob, Self::Regex];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Tokens => "Query",
+            Self::Glob => "Wildcards",
+            Self::Regex => "Regex",
+            Self::Fuzzy => "Fuzzy",
+        }
+    }
+}
+
+/// A compiled file-name query. Built once per search and shared by every candidate check.
+#[derive(Debug, Clone)]
+pub enum NameMatcher {
+    Query(Query),
+    Fuzzy(FuzzyQuery),
+    /// `on_normalized` selects the normalized name instead of the original file name.
+    Pattern { regex: Regex, on_normalized: bool },
+}
+
+impl NameMatcher {
+    /// Compiles `keyword` for the given mode. `in_path` makes query words match the relative path
+    /// by default. The error message is meant to be shown to the user.
+    pub fn compile(mode: QueryMode, keyword: &str, on_normalized: bool, in_path: bool) -> Result<Self, String> {
+        let pattern = match mode {
+            QueryMode::Tokens => return Query::parse(keyword, in_path).map(Self::Query).map_err(|e| e.render(keyword)),
+            QueryMode::Fuzzy => return Ok(Self::Fuzzy(FuzzyQuery::new(keyword))),
+            QueryMode::Glob => glob_to_regex(keyword.trim())?,
+            QueryMode::Regex => keyword.to_string(),
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(mode == QueryMode::Glob)
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(Self::Pattern { regex, on_normalized })
+    }
+
+    /// Whether the query has nothing to match on, in which case no search is run.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Query(query) => !query.expr.constrains(),
+            Self::Fuzzy(fuzzy) => fuzzy.words.is_empty(),
+            Self::Pattern { .. } => false,
+        }
+    }
+
+    /// Normalized words every match must contain, usable with the trigram index.
+    pub fn required_words(&self) -> Vec<String> {
+        match self {
+            Self::Query(query) => query.expr.required_words(),
+            // Typos break trigrams, so fuzzy queries always check every record.
+            Self::Fuzzy(_) | Self::Pattern { .. } => Vec::new(),
+        }
+    }
+
+    /// Normalized words that results are ranked against; patterns have none.
+    pub fn ranking_words(&self) -> Vec<String> {
+        match self {
+            Self::Query(query) => query.expr.required_words(),
+            Self::Fuzzy(fuzzy) => fuzzy.words.iter().map(|word| word.iter().collect()).collect(),
+            Self::Pattern { .. } => Vec::new(),
+        }
+    }
+
+    /// Whether matching needs the size or dates of a file, which live search must read from disk.
+    pub fn needs_file_stats(&self) -> bool {
+        match self {
+            Self::Query(query) => query.expr.needs_file_stats(),
+            Self::Fuzzy(_) | Self::Pattern { .. } => false,
+        }
+    }
+
+    /// Whether matching needs `Candidate::normalized_path`.
+    pub fn needs_paths(&self) -> bool {
+        match self {
+            Self::Query(query) => query.expr.needs_paths(),
+            Self::Fuzzy(_) | Self::Pattern { .. } => false,
+        }
+    }
+
+    pub fn is_match(&self, candidate: &Candidate) -> bool {
+        match self {
+            Self::Query(query) => query.expr.matches(candidate),
+            Self::Fuzzy(fuzzy) => fuzzy.score(candidate.metadata).is_some(),
+            Self::Pattern { regex, on_normalized: true } => regex.is_match(&candidate.metadata.normalized_name),
+            Self::Pattern { regex, on_normalized: false } => regex.is_match(file_name_of(candidate.relative_path)),
+        }
+    }
+
+    /// Matches a candidate and returns its score, from 0 to 1. Only fuzzy queries grade their
+    /// matches; every other match scores 1.
+    pub fn score(&self, candidate: &Candidate) -> Option<f32> {
+        match self {
+            Self::Fuzzy(fuzzy) => fuzzy.score(candidate.metadata),
+            _ => self.is_match(candidate).then_some(1.0),
+        }
+    }
+
+    pub fn is_fuzzy(&self) -> bool {
+        matches!(self, Self::Fuzzy(_))
+    }
+}
+
+// --- Fuzzy Matching ---
+
+/// A typo-tolerant query. Words are looked up in the normalized name with its spaces removed, so
+/// `hop dong` finds `hopdong` and `baocao` finds `bao cao`.
+#[derive(Debug, Clone)]
+pub struct FuzzyQuery {
+    words: Vec<Vec<char>>,
+    /// All words joined, tried as a single word as well so a typo in a short word can be absorbed.
+    joined: Vec<char>,
+}
+
+impl FuzzyQuery {
+    fn new(keyword: &str) -> Self {
+        let normalized = utils::normalize_string(keyword);
+        Self {
+            words: normalized.split_whitespace().map(|word| word.chars().collect()).collect(),
+            joined: normalized.chars().filter(|c| !c.is_whitespace()).collect(),
+        }
+    }
+
+    /// Scores how closely the name (without its extension) contains the query, from 0 to 1, or
+    /// returns `None` when a word needs more edits than its length allows.
+    fn score(&self, metadata: &FileMetadata) -> Option<f32> {
+        if self.words.is_empty() {
+            return None;
+        }
+        let name = metadata.normalized_name.as_str();
+        let stem = name.strip_suffix(metadata.extension.as_str()).map_or(name, str::trim_end);
+        let compact: Vec<char> = stem.chars().filter(|c| !c.is_whitespace()).collect();
+
+        let score_words = |words: &[Vec<char>]| -> Option<f32> {
+            let mut total = 0;
+            let mut edits = 0;
+            for word in words {
+                edits += substring_edit_distance(word, &compact, allowed_edits(word.len()))?;
+                total += word.len();
+            }
+            #[allow(clippy::cast_precision_loss)]
+            let similarity = (total - edits) as f32 / total as f32;
+            // Names that are barely longer than the query rank above names that merely contain it.
+            #[allow(clippy::cast_precision_loss)]
+            let coverage = (total as f32 / compact.len().max(1) as f32).min(1.0);
+            Some(0.8 * similarity + 0.2 * coverage)
+        };
+
+        let by_word = score_words(&self.words);
+        let whole = if self.words.len() > 1 { score_words(std::slice::from_ref(&self.joined)) } else { None };
+        match (by_word, whole) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+/// Edits tolerated in a fuzzy word: none for short words, where one edit changes the meaning.
+fn allowed_edits(len: usize) -> usize {
+    match len {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// Smallest edit distance between `pattern` and any substring of `text`, or `None` once it is
+/// certain to exceed `limit`.
+fn substring_edit_distance(pattern: &[char], text: &[char], limit: usize) -> Option<usize> {
+    // `previous[j]`: edits to match the pattern prefix so far ending at text position j. A match
+    // may start anywhere, so the empty prefix costs nothing at every position.
+    let mut previous = vec![0; text.len() + 1];
+    let mut current = vec![0; text.len() + 1];
+    for (i, &p) in pattern.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, &t) in text.iter().enumerate() {
+            let substitution = previous[j] + usize::from(p != t);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+        if previous.iter().all(|&edits| edits > limit) {
+            return None;
+        }
+    }
+    previous.into_iter().min().filter(|&edits| edits <= limit)
+}
+
+// --- Structured Queries ---
+
+/// A file or directory being checked against a query.
+pub struct Candidate<'a> {
+    /// The indexed location, or the live search folder, the entry was found in.
+    pub location: &'a str,
+    /// The entry's path relative to `location`.
+    pub relative_path: &'a str,
+    /// `relative_path` normalized by `PathNormalizer`; only filled in when the matcher
+    /// `needs_paths`, empty otherwise.
+    pub normalized_path: &'a str,
+    pub metadata: &'a FileMetadata,
+}
+
+/// Builds normalized relative paths (folder names and the file name, normalized and separated by
+/// spaces) for path matching. Each folder is normalized only once, since the records of a folder
+/// share it.
+#[derive(Default)]
+pub struct PathNormalizer {
+    folders: HashMap<String, String>,
+}
+
+impl PathNormalizer {
+    pub fn normalize(&mut self, relative_path: &str, normalized_name: &str) -> String {
+        let Some(split) = relative_path.rfind(['/', '\\']) else {
+            return normalized_name.to_string();
+        };
+        let folder = &relative_path[..split];
+        if !self.folders.contains_key(folder) {
+            self.folders.insert(folder.to_string(), utils::normalize_path(folder));
+        }
+        let normalized_folder = &self.folders[folder];
+        match (normalized_folder.is_empty(), normalized_name.is_empty()) {
+            (true, _) => normalized_name.to_string(),
+            (false, true) => normalized_folder.clone(),
+            (false, false) => format!("{normalized_folder} {normalized_name}"),
+        }
+    }
+}
+
+/// A query syntax error at a character position of the query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    fn new(position: usize, message: impl Into<String>) -> Self {
+        Self { position, message: message.into() }
+    }
+
+    /// Renders the query with a caret under the offending character, for a monospace label. The
+    /// caret is indented by the display width of the text before it, so wide (CJK) characters and
+    /// combining accents keep it aligned.
+    pub fn render(&self, query: &str) -> String {
+        let prefix: String = query.chars().take(self.position).collect();
+        format!("{query}\n{}^ {}", " ".repeat(prefix.width()), self.message)
+    }
+}
+
+/// Inclusive lower and upper limits on a size or a timestamp.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Bounds {
+    min: Option<u64>,
+    max: Option<u64>,
+}
+
+impl Bounds {
+    fn contains(self, value: u64) -> bool {
+        self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    /// Normalized text that must appear in the normalized name or, with `in_path`, anywhere in
+    /// the normalized relative path.
+    Text { text: String, in_path: bool },
+    /// Lowercase extensions without the dot; any of them matches.
+    Extension(Vec<String>),
+    Size(Bounds),
+    Modified(Bounds),
+    Created(Bounds),
+    /// Lowercase substring of the location root, with `/` separators.
+    Location(String),
+    Kind { is_dir: bool },
+}
+
+impl Term {
+    fn matches(&self, candidate: &Candidate) -> bool {
+        let metadata = candidate.metadata;
+        match self {
+            Self::Text { text, in_path: false } => metadata.normalized_name.contains(text.as_str()),
+            Self::Text { text, in_path: true } => candidate.normalized_path.contains(text.as_str()),
+            Self::Extension(extensions) => extensions.contains(&metadata.extension),
+            Self::Size(bounds) => !metadata.is_dir && bounds.contains(metadata.size),
+            Self::Modified(bounds) => bounds.contains(metadata.modified_time),
+            Self::Created(bounds) => bounds.contains(metadata.created_time),
+            Self::Location(location) => comparable_path(candidate.location).contains(location.as_str()),
+            Self::Kind { is_dir } => metadata.is_dir == *is_dir,
+        }
+    }
+}
+
+/// Lowercases a path and unifies its separators so path filters work on every platform.
+fn comparable_path(path: &str) -> String {
+    path.to_lowercase().replace('\\', "/")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    Term(Term),
+}
+
+impl Expr {
+    fn matches(&self, candidate: &Candidate) -> bool {
+        match self {
+            Self::And(exprs) => exprs.iter().all(|expr| expr.matches(candidate)),
+            Self::Or(exprs) => exprs.iter().any(|expr| expr.matches(candidate)),
+            Self::Not(expr) => !expr.matches(candidate),
+            Self::Term(term) => term.matches(candidate),
+        }
+    }
+
+    /// Whether the expression restricts anything. Empty text is dropped while parsing, so only the
+    /// empty query (or one of only punctuation) does not.
+    fn constrains(&self) -> bool {
+        !matches!(self, Self::And(exprs) if exprs.is_empty())
+    }
+
+    /// Drops text terms that normalize to nothing, such as `-` or `"!!"`, which would otherwise
+    /// match every name (or, negated, none). Returns `None` when nothing is left.
+    fn without_empty_text(self) -> Option<Self> {
+        let prune = |exprs: Vec<Self>, combine: fn(Vec<Self>) -> Self| {
+            let mut exprs: Vec<Self> = exprs.into_iter().filter_map(Self::without_empty_text).collect();
+            match exprs.len() {
+                0 | 1 => exprs.pop(),
+                _ => Some(combine(exprs)),
+            }
+        };
+        match self {
+            Self::Term(Term::Text { text, .. }) if text.is_empty() => None,
+            Self::Term(_) => Some(self),
+            Self::Not(expr) => expr.without_empty_text().map(|expr| Self::Not(Box::new(expr))),
+            Self::And(exprs) => prune(exprs, Self::And),
+            Self::Or(exprs) => prune(exprs, Self::Or),
+        }
+    }
+
+    fn required_words(&self) -> Vec<String> {
+        match self {
+            Self::And(exprs) => exprs.iter().flat_map(Self::required_words).collect(),
+            // Path words may sit in a folder name, so only name words can use the name trigrams.
+            Self::Term(Term::Text { text, in_path: false }) => text.split_whitespace().map(ToString::to_string).collect(),
+            Self::Or(_) | Self::Not(_) | Self::Term(_) => Vec::new(),
+        }
+    }
+
+    fn needs_paths(&self) -> bool {
+        match self {
+            Self::And(exprs) | Self::Or(exprs) => exprs.iter().any(Self::needs_paths),
+            Self::Not(expr) => expr.needs_paths(),
+            Self::Term(term) => matches!(term, Term::Text { in_path: true, .. }),
+        }
+    }
+
+    fn needs_file_stats(&self) -> bool {
+        match self {
+            Self::And(exprs) | Self::Or(exprs) => exprs.iter().any(Self::needs_file_stats),
+            Self::Not(expr) => expr.needs_file_stats(),
+            Self::Term(term) => matches!(term, Term::Size(_) | Term::Modified(_) | Term::Created(_)),
+        }
+    }
+}
+
+/// A parsed query such as `invoice ext:pdf size:>5MB modified:2024-01..2024-06 -draft`.
+///
+/// Words and `"quoted phrases"` must appear in the normalized name, or anywhere in the normalized
+/// relative path when searching paths (`in:path`). `name:` and `path:` choose for a single term.
+/// Terms are combined with AND; `OR` (or `|`) separates alternatives, `-` negates a term and
+/// parentheses group terms. Field filters: `ext:`, `size:`, `modified:`, `created:`, `location:`
+/// and `type:`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    expr: Expr,
+}
+
+impl Query {
+    /// Parses `query`; `in_path` makes plain words match the whole relative path unless the
+    /// query itself says `in:name`.
+    pub fn parse(query: &str, in_path: bool) -> Result<Self, ParseError> {
+        let mut in_path = in_path;
+        let all_tokens = tokenize(query)?;
+        let mut tokens = Vec::with_capacity(all_tokens.len());
+        let mut depth = 0usize;
+        // `in:` applies to the whole query, so it is taken out before parsing and only accepted
+        // where it cannot look like part of a group, an alternative or a negation.
+        for (index, (token, position)) in all_tokens.iter().enumerate() {
+            match token {
+                Token::LParen => depth += 1,
+                Token::RParen => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            let Token::Field(name, value, value_position) = token else {
+                tokens.push((token.clone(), *position));
+                continue;
+            };
+            if name != "in" {
+                tokens.push((token.clone(), *position));
+                continue;
+            }
+            if matches!(tokens.last(), Some((Token::Not, _))) {
+                return Err(ParseError::new(*position, "'in:' cannot be negated"));
+            }
+            if depth > 0 {
+                return Err(ParseError::new(*position, "'in:' applies to the whole query and cannot be inside parentheses"));
+            }
+            let next = all_tokens.get(index + 1).map(|(token, _)| token);
+            if matches!(tokens.last(), Some((Token::Or, _))) || matches!(next, Some(Token::Or)) {
+                return Err(ParseError::new(*position, "'in:' applies to the whole query and cannot be an OR alternative"));
+            }
+            in_path = match value.to_lowercase().as_str() {
+                "path" => true,
+                "name" => false,
+                _ => return Err(ParseError::new(*value_position, "expected 'name' or 'path'")),
+            };
+        }
+        let mut parser = Parser { tokens, index: 0, end: query.chars().count(), in_path };
+        if parser.tokens.is_empty() {
+            return Ok(Self { expr: Expr::And(Vec::new()) });
+        }
+        let expr = parser.parse_or()?;
+        if let Some((_, position)) = parser.peek() {
+            return Err(ParseError::new(position, "unexpected ')'"));
+        }
+        Ok(Self { expr: expr.without_empty_text().unwrap_or(Expr::And(Vec::new())) })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    Or,
+    Not,
+    Word(String),
+    Phrase(String),
+    /// Field name, value and the position of the value.
+    Field(String, String, usize),
+}
+
+fn tokenize(query: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        match chars[i] {
+            c if c.is_whitespace() => i += 1,
+            '(' => {
+                tokens.push((Token::LParen, start));
+                i += 1;
+            }
+            ')' => {
+                tokens.push((Token::RParen, start));
+                i += 1;
+            }
+            '|' => {
+                tokens.push((Token::Or, start));
+                i += 1;
+            }
+            '-' if chars.get(i + 1).is_some_and(|c| !c.is_whitespace()) => {
+                tokens.push((Token::Not, start));
+                i += 1;
+            }
+            '"' => {
+                let (text, next) = read_quoted(&chars, i)?;
+                tokens.push((Token::Phrase(text), start));
+                i = next;
+            }
+            _ => {
+                let end = word_end(&chars, i);
+                let colon = chars[i..end].iter().position(|&c| c == ':').map(|offset| i + offset);
+                match colon {
+                    // Only letters form a field name, so words like `10:30` stay plain text.
+                    Some(colon) if colon > i && chars[i..colon].iter().all(char::is_ascii_alphabetic) => {
+                        let name: String = chars[i..colon].iter().collect::<String>().to_lowercase();
+                        let value_start = colon + 1;
+                        let (value, next) = if chars.get(value_start) == Some(&'"') {
+                            read_quoted(&chars, value_start)?
+                        } else {
+                            let value_end = word_end(&chars, value_start);
+                            (chars[value_start..value_end].iter().collect(), value_end)
+                        };
+                        if value.is_empty() {
+                            return Err(ParseError::new(value_start, format!("missing value after '{name}:'")));
+                        }
+                        tokens.push((Token::Field(name, value, value_start), start));
+                        i = next;
+                    }
+                    _ => {
+                        let word: String = chars[i..end].iter().collect();
+                        let token = if word == "OR" { Token::Or } else { Token::Word(word) };
+                        tokens.push((token, start));
+                        i = end;
+                    }
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Reads a `"quoted"` string starting at the opening quote; returns it and the position after it.
+fn read_quoted(chars: &[char], open: usize) -> Result<(String, usize), ParseError> {
+    let close = chars[open + 1..].iter().position(|&c| c == '"')
+        .ok_or_else(|| ParseError::new(open, "the quote is never closed"))?;
+    let text = chars[open + 1..open + 1 + close].iter().collect();
+    Ok((text, open + close + 2))
+}
+
+/// Finds the end of a bare word. Parentheses inside a word, as in `report(1)`, stay part of it.
+fn word_end(chars: &[char], start: usize) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+    while i < chars.len() {
+        match chars[i] {
+            c if c.is_whitespace() => break,
+            '|' => break,
+            '(' => depth += 1,
+            ')' if depth == 0 => break,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    i
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    index: usize,
+    /// Length of the query, used as the position of "unexpected end" errors.
+    end: usize,
+    /// Whether plain words and phrases match the relative path instead of the name.
+    in_path: bool,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<(&Token, usize)> {
+        self.tokens.get(self.index).map(|(token, position)| (token, *position))
+    }
+
+    fn next(&mut self) -> Option<(Token, usize)> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ParseError> {
+        let mut branches = vec![self.parse_and()?];
+        while matches!(self.peek(), Some((Token::Or, _))) {
+            self.next();
+            branches.push(self.parse_and()?);
+        }
+        Ok(if branches.len() == 1 { branches.remove(0) } else { Expr::Or(branches) })
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ParseError> {
+        let mut terms = Vec::new();
+        while let Some((token, position)) = self.peek() {
+            match token {
+                Token::Or if terms.is_empty() => return Err(ParseError::new(position, "OR needs a term on both sides")),
+                Token::Or | Token::RParen => break,
+                _ => terms.push(self.parse_unary()?),
+            }
+        }
+        match terms.len() {
+            0 => Err(ParseError::new(self.peek().map_or(self.end, |(_, position)| position), "expected a search term")),
+            1 => Ok(terms.remove(0)),
+            _ => Ok(Expr::And(terms)),
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        if matches!(self.peek(), Some((Token::Not, _))) {
+            self.next();
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_atom()
+    }
+
+    fn parse_atom(&mut self) -> Result<Expr, ParseError> {
+        match self.next() {
+            Some((Token::LParen, position)) => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some((Token::RParen, _)) => Ok(expr),
+                    _ => Err(ParseError::new(position, "'(' is never closed")),
+                }
+            }
+            Some((Token::Word(text) | Token::Phrase(text), _)) => Ok(Expr::Term(Term::Text { text: utils::normalize_string(&text), in_path: self.in_path })),
+            Some((Token::Field(name, value, value_position), position)) => parse_field(&name, &value, position, value_position).map(Expr::Term),
+            Some((Token::RParen, position)) => Err(ParseError::new(position, "unexpected ')'")),
+            Some((Token::Or, position)) => Err(ParseError::new(position, "OR needs a term on both sides")),
+            Some((Token::Not, position)) => Err(ParseError::new(position, "'-' must be followed by a term")),
+            None => Err(ParseError::new(self.end, "expected a search term")),
+        }
+    }
+}
+
+fn parse_field(name: &str, value: &str, position: usize, value_position: usize) -> Result<Term, ParseError> {
+    match name {
+        "ext" | "extension" => {
+            let extensions: Vec<String> = value.split(',').map(|ext| ext.trim().trim_start_matches('.').to_lowercase()).collect();
+            if extensions.iter().any(String::is_empty) {
+                return Err(ParseError::new(value_position, "empty extension"));
+            }
+            Ok(Term::Extension(extensions))
+        }
+        "size" => {
+            // Like a bare date stands for the whole day, a bare size stands for every size that is
+            // displayed as it; comparisons and ranges use the exact value.
+            let exact = value.starts_with(['<', '>', '=']) || value.contains("..");
+            let parse_value: fn(&str) -> Result<(u64, u64), String> = if exact { parse_size } else { parse_displayed_size };
+            parse_bounds(value, value_position, parse_value).map(Term::Size)
+        }
+        "modified" | "date" => parse_bounds(value, value_position, parse_date).map(Term::Modified),
+        "created" => parse_bounds(value, value_position, parse_date).map(Term::Created),
+        "name" => Ok(Term::Text { text: utils::normalize_string(value), in_path: false }),
+        "path" => Ok(Term::Text { text: utils::normalize_path(value), in_path: true }),
+        "location" => Ok(Term::Location(comparable_path(value))),
+        "type" | "is" => match value.to_lowercase().as_str() {
+            "file" => Ok(Term::Kind { is_dir: false }),
+            "dir" | "folder" | "directory" => Ok(Term::Kind { is_dir: true }),
+            _ => Err(ParseError::new(value_position, "expected 'file' or 'folder'")),
+        },
+        _ => Err(ParseError::new(
+            position,
+            format!("unknown field '{name}' (use ext, size, modified, created, name, path, location, type or in)"),
+        )),
+    }
+}
+
+/// Parses a comparison (`>5MB`, `<=2024-03`), a range (`1MB..10MB`, `2024-01..`) or a single
+/// value. `parse_value` returns the first and last value a literal stands for, e.g. the first and
+/// last second of a month.
+fn parse_bounds<F>(value: &str, position: usize, parse_value: F) -> Result<Bounds, ParseError>
+where
+    F: Fn(&str) -> Result<(u64, u64), String>,
+{
+    let parse_at = |text: &str, offset: usize| parse_value(text).map_err(|e| ParseError::new(position + offset, e));
+
+    for (operator, len) in [(">=", 2), ("<=", 2), (">", 1), ("<", 1), ("=", 1)] {
+        if let Some(operand) = value.strip_prefix(operator) {
+            let (first, last) = parse_at(operand, len)?;
+            return Ok(match operator {
+                ">=" => Bounds { min: Some(first), max: None },
+                "<=" => Bounds { min: None, max: Some(last) },
+                ">" => Bounds { min: Some(last.saturating_add(1)), max: None },
+                "<" => Bounds { min: None, max: Some(first.saturating_sub(1)) },
+                _ => Bounds { min: Some(first), max: Some(last) },
+            });
+        }
+    }
+
+    if let Some((low, high)) = value.split_once("..") {
+        if low.is_empty() && high.is_empty() {
+            return Err(ParseError::new(position, "a range needs at least one end"));
+        }
+        let min = if low.is_empty() { None } else { Some(parse_at(low, 0)?.0) };
+        let max = if high.is_empty() { None } else { Some(parse_at(high, low.chars().count() + 2)?.1) };
+        return Ok(Bounds { min, max });
+    }
+
+    let (first, last) = parse_at(value, 0)?;
+    Ok(Bounds { min: Some(first), max: Some(last) })
+}
+
+/// Parses a size such as `500`, `12KB` or `1.5GB` (binary units, like the displayed sizes).
+fn parse_size(text: &str) -> Result<(u64, u64), String> {
+    let (number, multiplier, _) = split_size(text)?;
+    let bytes = to_bytes(number * multiplier);
+    Ok((bytes, bytes))
+}
+
+/// Parses a size into the sizes that round to it at the precision it is written with, so `5MB`
+/// covers 4.5 MB up to 5.5 MB and `1.5GB` covers 1.45 GB up to 1.55 GB. Byte counts are exact.
+fn parse_displayed_size(text: &str) -> Result<(u64, u64), String> {
+    let (number, multiplier, decimals) = split_size(text)?;
+    let half_step = multiplier / 10f64.powi(decimals) / 2.0;
+    let first = to_bytes((number * multiplier - half_step).ceil());
+    let last = to_bytes((number * multiplier + half_step).ceil()).saturating_sub(1).max(first);
+    Ok((first, last))
+}
+
+/// Splits a size into its number, the bytes per unit and the number of decimals written.
+fn split_size(text: &str) -> Result<(f64, f64, i32), String> {
+    let split = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+    let (digits, unit) = text.split_at(split);
+    let number: f64 = digits.parse().map_err(|_| format!("'{text}' is not a size"))?;
+    let multiplier: u64 = match unit.to_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        "T" | "TB" => 1 << 40,
+        _ => return Err(format!("unknown size unit '{unit}' (use B, KB, MB, GB or TB)")),
+    };
+    let decimals = digits.split_once('.').map_or(0, |(_, fraction)| fraction.len());
+    #[allow(clippy::cast_precision_loss)]
+    Ok((number, multiplier as f64, i32::try_from(decimals).unwrap_or(i32::MAX)))
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn to_bytes(value: f64) -> u64 {
+    // `as` saturates, so negative values become 0 and huge ones `u64::MAX`.
+    value as u64
+}
+
+/// Years a date filter may name: timestamps are seconds since 1970, and four digits are enough.
+const DATE_YEARS: std::ops::RangeInclusive<u64> = 1970..=9999;
+
+/// Parses `YYYY`, `YYYY-MM` or `YYYY-MM-DD` into the first and last second (UTC) of that period.
+fn parse_date(text: &str) -> Result<(u64, u64), String> {
+    let invalid = || format!("'{text}' is not a date (use YYYY, YYYY-MM or YYYY-MM-DD)");
+    let parts: Vec<&str> = text.split('-').collect();
+    let number = |part: &str| part.parse::<u64>().map_err(|_| invalid());
+    let (year, month, day) = match parts.as_slice() {
+        [year] => (number(year)?, None, None),
+        [year, month] => (number(year)?, Some(number(month)?), None),
+        [year, month, day] => (number(year)?, Some(number(month)?), Some(number(day)?)),
+        _ => return Err(invalid()),
+    };
+    if !DATE_YEARS.contains(&year) {
+        return Err(format!("the year in '{text}' must be between {} and {}", DATE_YEARS.start(), DATE_YEARS.end()));
+    }
+    if month.is_some_and(|month| !(1..=12).contains(&month)) {
+        return Err(format!("the month in '{text}' must be between 1 and 12"));
+    }
+    if let (Some(month), Some(day)) = (month, day) {
+        let days = days_in_month(year, month);
+        if !(1..=days).contains(&day) {
+            return Err(format!("the day in '{text}' must be between 1 and {days}"));
+        }
+    }
+
+    let year = i64::try_from(year).map_err(|_| invalid())?;
+    let first_day = utils::days_from_civil(year, month.unwrap_or(1), day.unwrap_or(1));
+    let next_day = match (month, day) {
+        (_, Some(_)) => first_day + 1,
+        (Some(month), None) if month < 12 => utils::days_from_civil(year, month + 1, 1),
+        (_, None) => utils::days_from_civil(year + 1, 1, 1),
+    };
+    let to_secs = |days: i64| days.checked_mul(86_400).and_then(|secs| u64::try_from(secs).ok()).ok_or_else(invalid);
+    Ok((to_secs(first_day)?, to_secs(next_day)? - 1))
+}
+
+fn days_in_month(year: u64, month: u64) -> u64 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Translates a wildcard pattern into an anchored regular expression.
+fn glob_to_regex(glob: &str) -> Result<String, String> {
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            '[' => {
+                // Character classes are passed through; `[!...]` is the shell spelling of `[^...]`.
+                pattern.push('[');
+                let negated = chars.peek() == Some(&'!');
+                if negated {
+                    chars.next();
+                    pattern.push('^');
+                }
+                let mut closed = false;
+                let mut previous = None;
+                while let Some(c) = chars.next() {
+                    if c == ']' {
+                        closed = true;
+                        break;
+                    }
+                    // The regex crate reads `[` as a nested class and `&&`, `--` and `~~` as set
+                    // operations; none of these mean anything in a glob, so they match literally.
+                    let in_dash_pair = c == '-' && (previous == Some('-') || chars.peek() == Some(&'-'));
+                    if matches!(c, '\\' | '[' | '&' | '~') || in_dash_pair {
+                        pattern.push('\\');
+                    }
+                    pattern.push(c);
+                    previous = Some(c);
+                }
+                if !closed {
+                    return Err("'[' is not closed by a matching ']'".to_string());
+                }
+                if previous.is_none() {
+                    let class = if negated { "[!]" } else { "[]" };
+                    return Err(format!("'{class}' lists no characters; use '[[]' to match a literal '['"));
+                }
+                pattern.push(']');
+            }
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    Ok(pattern)
+}
+
+/// Returns the last component of an index key, whichever separator it uses.
+pub fn file_name_of(key: &str) -> &str {
+    key.rsplit(['/', '\\']).next().unwrap_or(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob_matches(glob: &str, name: &str) -> bool {
+        let pattern = glob_to_regex(glob).unwrap();
+        RegexBuilder::new(&pattern).case_insensitive(true).build().unwrap().is_match(name)
+    }
+
+    #[test]
+    fn glob_wildcards_match_whole_names() {
+        assert!(glob_matches("*.rs", "main.RS"));
+        assert!(!glob_matches("*.rs", "main.rs.bak"));
+        assert!(glob_matches("report-??.pdf", "report-07.pdf"));
+        assert!(glob_matches("a+b (1).txt", "a+b (1).txt"));
+    }
+
+    #[test]
+    fn glob_classes_take_their_characters_literally() {
+        assert!(glob_matches("file[0-9].txt", "file7.txt"));
+        assert!(glob_matches("file[!0-9].txt", "fileA.txt"));
+        assert!(!glob_matches("file[!0-9].txt", "file7.txt"));
+        for name in ["a&.txt", "a~.txt", "a[.txt", "a\\.txt"] {
+            assert!(glob_matches("a[&~[\\].txt", name), "{name}");
+        }
+        assert!(glob_matches("[a&&b]", "&"));
+        assert!(glob_matches("[a--b]", "-"));
+        assert!(!glob_matches("[a--b]", "c"));
+        assert!(glob_matches("[~~x]", "~"));
+    }
+
+    #[test]
+    fn glob_rejects_unclosed_and_empty_classes() {
+        assert!(glob_to_regex("file[0-9").unwrap_err().contains("not closed"));
+        assert!(glob_to_regex("file[].txt").unwrap_err().contains("'[]' lists no characters"));
+        assert!(glob_to_regex("file[!].txt").unwrap_err().contains("'[!]' lists no characters"));
+    }
+
+    /// Scores `name` against a fuzzy query, with the record built the way a scan builds it.
+    fn fuzzy_score(query: &str, name: &str) -> Option<f32> {
+        FuzzyQuery::new(query).score(&FileMetadata::from_v1(name, utils::normalize_string(name), 0))
+    }
+
+    #[test]
+    fn fuzzy_words_ignore_the_spaces_of_the_name() {
+        assert_eq!(fuzzy_score("hop dong", "hopdong.docx"), Some(1.0));
+        assert_eq!(fuzzy_score("hopdong", "Hop Dong.docx"), Some(1.0));
+        assert_eq!(fuzzy_score("bao cao", "BaoCao.pdf"), Some(1.0));
+        assert_eq!(fuzzy_score("baocao", "Báo cáo.pdf"), Some(1.0));
+        // The extension is not part of the name being matched.
+        assert_eq!(fuzzy_score("docx", "hopdong.docx"), None);
+    }
+
+    #[test]
+    fn fuzzy_edits_grow_with_the_word_length() {
+        assert_eq!([3, 4, 7, 8].map(allowed_edits), [0, 1, 1, 2]);
+        assert!(fuzzy_score("cat", "cot.txt").is_none());
+        assert!(fuzzy_score("raport", "report.txt").is_some());
+        assert!(fuzzy_score("rapirt", "report.txt").is_none());
+        assert!(fuzzy_score("contrcat", "contract.txt").is_some());
+        assert!(fuzzy_score("kontrcat", "contract.txt").is_none());
+    }
+
+    #[test]
+    fn fuzzy_scores_prefer_exact_and_short_names() {
+        let exact = fuzzy_score("report", "report.pdf").unwrap();
+        let longer = fuzzy_score("report", "report final draft.pdf").unwrap();
+        let typo = fuzzy_score("repot", "report.pdf").unwrap();
+        assert!(exact > longer, "{exact} > {longer}");
+        assert!(longer > typo, "{longer} > {typo}");
+    }
+
+    fn parse(query: &str) -> Result<Expr, ParseError> {
+        Query::parse(query, false).map(|query| query.expr)
+    }
+
+    fn text(text: &str) -> Expr {
+        Expr::Term(Term::Text { text: text.to_string(), in_path: false })
+    }
+
+    #[test]
+    fn query_combines_terms() {
+        assert_eq!(parse("invoice ext:pdf").unwrap(), Expr::And(vec![text("invoice"), Expr::Term(Term::Extension(vec!["pdf".to_string()]))]));
+        assert_eq!(parse("a OR (b -c)").unwrap(), Expr::Or(vec![text("a"), Expr::And(vec![text("b"), Expr::Not(Box::new(text("c")))])]));
+        assert_eq!(parse("report(1)").unwrap(), text("report1"));
+        assert_eq!(parse("10:30").unwrap(), text("1030"));
+    }
+
+    #[test]
+    fn query_drops_text_without_letters_or_digits() {
+        assert_eq!(parse("report - \"!!\"").unwrap(), text("report"));
+        assert_eq!(parse("report -!! OR ...").unwrap(), text("report"));
+        assert_eq!(parse("(!!) name:??").unwrap(), Expr::And(Vec::new()));
+        assert!(!Query::parse("!!", false).unwrap().expr.constrains());
+    }
+
+    #[test]
+    fn query_in_applies_to_the_whole_query() {
+        let query = Query::parse("contracts in:path", false).unwrap();
+        assert_eq!(query.expr, Expr::Term(Term::Text { text: "contracts".to_string(), in_path: true }));
+        assert_eq!(parse("in:name contracts").unwrap(), text("contracts"));
+        assert!(parse("(a in:path)").unwrap_err().message.contains("inside parentheses"));
+        assert!(parse("a OR in:path").unwrap_err().message.contains("OR alternative"));
+        assert!(parse("in:path | a").unwrap_err().message.contains("OR alternative"));
+        assert_eq!(parse("-in:path a").unwrap_err(), ParseError::new(1, "'in:' cannot be negated"));
+        assert_eq!(parse("in:folder").unwrap_err().position, 3);
+    }
+
+    #[test]
+    fn query_reports_syntax_errors() {
+        assert_eq!(parse("(a b").unwrap_err().message, "'(' is never closed");
+        assert_eq!(parse("a )").unwrap_err(), ParseError::new(2, "unexpected ')'"));
+        assert_eq!(parse("OR a").unwrap_err().message, "OR needs a term on both sides");
+        assert_eq!(parse("\"open").unwrap_err().message, "the quote is never closed");
+        assert_eq!(parse("ext:").unwrap_err(), ParseError::new(4, "missing value after 'ext:'"));
+        assert!(parse("color:red").unwrap_err().message.starts_with("unknown field 'color'"));
+    }
+
+    #[test]
+    fn bare_sizes_cover_their_displayed_value() {
+        let size = |query: &str| match parse(query).unwrap() {
+            Expr::Term(Term::Size(bounds)) => bounds,
+            expr => panic!("{expr:?}"),
+        };
+        assert_eq!(size("size:500"), Bounds { min: Some(500), max: Some(500) });
+        assert_eq!(size("size:5MB"), Bounds { min: Some(9 << 19), max: Some((11 << 19) - 1) });
+        assert_eq!(size("size:1.5KB"), Bounds { min: Some(1485), max: Some(1587) });
+        assert_eq!(size("size:=5MB"), Bounds { min: Some(5 << 20), max: Some(5 << 20) });
+        assert_eq!(size("size:>5MB"), Bounds { min: Some((5 << 20) + 1), max: None });
+        assert_eq!(size("size:1MB..2MB"), Bounds { min: Some(1 << 20), max: Some(2 << 20) });
+        assert!(parse("size:5XB").unwrap_err().message.contains("unknown size unit"));
+    }
+
+    #[test]
+    fn dates_cover_their_whole_period() {
+        assert_eq!(parse_date("1970"), Ok((0, 365 * 86_400 - 1)));
+        assert_eq!(parse_date("2024-02"), Ok((1_706_745_600, 1_709_251_199)));
+        assert_eq!(parse_date("2024-02-29"), Ok((1_709_164_800, 1_709_251_199)));
+        assert_eq!(parse_date("9999-12-31").map(|(_, last)| last), Ok(253_402_300_799));
+    }
+
+    #[test]
+    fn dates_outside_the_calendar_are_rejected() {
+        for (date, message) in [
+            ("1969", "year"),
+            ("10000", "year"),
+            ("18446744073709551615", "year"),
+            ("2024-13", "month"),
+            ("2024-00", "month"),
+            ("2023-02-29", "between 1 and 28"),
+            ("2024-04-31", "between 1 and 30"),
+            ("2100-02-29", "between 1 and 28"),
+            ("2024-01-00", "between 1 and 31"),
+            ("2024/01", "not a date"),
+            ("2024-01-01-01", "not a date"),
+        ] {
+            let error = parse_date(date).unwrap_err();
+            assert!(error.contains(message), "{date}: {error}");
+        }
+    }
+
+    #[test]
+    fn error_caret_follows_display_width() {
+        let error = parse("báo cáo size:x").unwrap_err();
+        assert_eq!(error.render("báo cáo size:x"), "báo cáo size:x\n             ^ 'x' is not a size");
+        let query = "報告書 ext:";
+        assert_eq!(parse(query).unwrap_err().render(query), "報告書 ext:\n           ^ missing value after 'ext:'");
+        let decomposed = "ba\u{300}o size:x";
+        assert!(parse(decomposed).unwrap_err().render(decomposed).ends_with("\n         ^ 'x' is not a size"));
+    }
+}
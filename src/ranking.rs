@@ -0,0 +1,285 @@
+use std::cmp::Ordering;
+use std::time::SystemTime;
+use crate::db::FileMetadata;
+use crate::gui::events::DisplayResult;
+use crate::query::NameMatcher;
+use crate::utils;
+
+/// Files modified this many days ago get half of the recency weight.
+const RECENCY_HALF_LIFE_DAYS: f32 = 30.0;
+
+/// Scores how relevant a matched name is to the query, from 0 to 1. Computed by the search
+/// worker for every result, so the results panel can order each streamed batch on arrival.
+pub struct Ranker {
+    /// Normalized query words the name is compared with; empty for wildcard and regex patterns.
+    words: Vec<String>,
+    now: u64,
+}
+
+impl Ranker {
+    pub fn new(matcher: &NameMatcher) -> Self {
+        Self {
+            words: matcher.ranking_words(),
+            now: utils::system_time_to_secs(SystemTime::now()),
+        }
+    }
+
+    /// Combines an exact name match, whole-word and word-prefix matches, a shallow path and a
+    /// recent modification, scaled by the match score (the similarity of fuzzy matches).
+    pub fn relevance(&self, match_score: f32, relative_path: &str, metadata: &FileMetadata) -> f32 {
+        let name = metadata.normalized_name.as_str();
+        let stem = name.strip_suffix(metadata.extension.as_str()).map_or(name, str::trim_end);
+        let name_words: Vec<&str> = stem.split_whitespace().collect();
+
+        let (exact, whole_words, prefixes) = if self.words.is_empty() {
+            (0.0, 0.0, 0.0)
+        } else {
+            let exact = if self.words.iter().map(String::as_str).eq(name_words.iter().copied()) { 1.0 } else { 0.0 };
+            let whole_words = self.words.iter().filter(|word| name_words.contains(&word.as_str())).count();
+            let prefixes = self.words.iter().filter(|word| name_words.iter().any(|name_word| name_word.starts_with(word.as_str()))).count();
+            #[allow(clippy::cast_precision_loss)]
+            let count = self.words.len() as f32;
+            #[allow(clippy::cast_precision_loss)]
+            (exact, whole_words as f32 / count, prefixes as f32 / count)
+        };
+
+        #[allow(clippy::cast_precision_loss)]
+        let depth = 1.0 / (1.0 + relative_path.matches(['/', '\\']).count() as f32);
+        #[allow(clippy::cast_precision_loss)]
+        let age_days = self.now.saturating_sub(metadata.modified_time) as f32 / 86_400.0;
+        let recency = if metadata.modified_time == 0 { 0.0 } else { 1.0 / (1.0 + age_days / RECENCY_HALF_LIFE_DAYS) };
+
+        match_score * (0.35 * exact + 0.2 * whole_words + 0.15 * prefixes + 0.15 * depth + 0.15 * recency)
+    }
+}
+
+/// The orders the indexed results panel can be sorted in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortBy {
+    #[default]
+    Relevance,
+    Name,
+    Folder,
+    Size,
+    Modified,
+    Location,
+}
+
+impl SortBy {
+    pub const ALL: [Self; 6] = [Self::Relevance, Self::Name, Self::Folder, Self::Size, Self::Modified, Self::Location];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Relevance => "Relevance",
+            Self::Name => "Name",
+            Self::Folder => "Folder",
+            Self::Size => "Size",
+            Self::Modified => "Date modified",
+            Self::Location => "Location",
+        }
+    }
+
+    /// Whether the order starts with the largest value when first selected: the most relevant,
+    /// largest and newest files come first, names and paths are alphabetical.
+    pub fn descending_by_default(self) -> bool {
+        matches!(self, Self::Relevance | Self::Size | Self::Modified)
+    }
+
+    /// Compares two results in ascending order; ties fall back to relevance, then the path.
+    pub fn compare(self, a: &DisplayResult, b: &DisplayResult) -> Ordering {
+        let primary = match self {
+            Self::Relevance => Ordering::Equal,
+            Self::Name => compare_ignore_case(file_name(&a.full_path), file_name(&b.full_path)),
+            Self::Folder => compare_ignore_case(folder(&a.full_path), folder(&b.full_path)),
+            Self::Size => size(a).cmp(&size(b)),
+            Self::Modified => modified_time(a).cmp(&modified_time(b)),
+            Self::Location => compare_ignore_case(&a.location, &b.location),
+        };
+        primary
+            .then_with(|| a.relevance.total_cmp(&b.relevance))
+            .then_with(|| a.full_path.cmp(&b.full_path))
+    }
+}
+
+/// Sorts results by `sort_by`. The sort is stable.
+pub fn sort_results(results: &mut [DisplayResult], sort_by: SortBy, descending: bool) {
+    if descending {
+        results.sort_by(|a, b| sort_by.compare(b, a));
+    } else {
+        results.sort_by(|a, b| sort_by.compare(a, b));
+    }
+}
+
+/// Adds a batch to results already sorted by `sort_by`: only the batch is sorted, then both are
+/// merged in one pass. Results that compare equal keep the existing one first.
+pub fn merge_results(results: &mut Vec<DisplayResult>, mut batch: Vec<DisplayResult>, sort_by: SortBy, descending: bool) {
+    sort_results(&mut batch, sort_by, descending);
+    let in_order = |a: &DisplayResult, b: &DisplayResult| {
+        let ordering = if descending { sort_by.compare(b, a) } else { sort_by.compare(a, b) };
+        ordering != Ordering::Greater
+    };
+
+    let mut merged = Vec::with_capacity(results.len() + batch.len());
+    let mut existing = std::mem::take(results).into_iter().peekable();
+    let mut batch = batch.into_iter().peekable();
+    while let (Some(a), Some(b)) = (existing.peek(), batch.peek()) {
+        let next = if in_order(a, b) { existing.next() } else { batch.next() };
+        merged.extend(next);
+    }
+    merged.extend(existing);
+    merged.extend(batch);
+    *results = merged;
+}
+
+fn compare_ignore_case(a: &str, b: &str) -> Ordering {
+    a.chars().flat_map(char::to_lowercase).cmp(b.chars().flat_map(char::to_lowercase))
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+fn folder(path: &str) -> &str {
+    path.rfind(['/', '\\']).map_or("", |end| &path[..end])
+}
+
+fn size(result: &DisplayResult) -> u64 {
+    result.metadata.as_ref().map_or(0, |metadata| metadata.size)
+}
+
+fn modified_time(result: &DisplayResult) -> u64 {
+    result.metadata.as_ref().map_or(0, |metadata| metadata.modified_time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    const DAY: u64 = 86_400;
+
+    fn ranker(words: &[&str]) -> Ranker {
+        Ranker { words: words.iter().map(ToString::to_string).collect(), now: 1_000 * DAY }
+    }
+
+    fn record(name: &str, modified_time: u64) -> FileMetadata {
+        let mut metadata = FileMetadata::from_v1(name, utils::normalize_string(name), modified_time);
+        metadata.size = 1;
+        metadata
+    }
+
+    #[test]
+    fn exact_names_outrank_prefixes_and_substrings() {
+        let ranker = ranker(&["report"]);
+        let relevance = |name: &str| ranker.relevance(1.0, name, &record(name, 0));
+        let exact = relevance("Report.pdf");
+        let whole_word = relevance("report final.pdf");
+        let prefix = relevance("reports.pdf");
+        let substring = relevance("myreport.pdf");
+        assert!(exact > whole_word, "{exact} > {whole_word}");
+        assert!(whole_word > prefix, "{whole_word} > {prefix}");
+        assert!(prefix > substring, "{prefix} > {substring}");
+        // Only the depth weight is left for a name that merely contains the query.
+        assert!((substring - 0.15).abs() < 1e-6, "{substring}");
+    }
+
+    #[test]
+    fn shallow_and_recent_files_win_ties() {
+        let ranker = ranker(&["report"]);
+        let metadata = record("report.pdf", 0);
+        assert!(ranker.relevance(1.0, "report.pdf", &metadata) > ranker.relevance(1.0, "a/b/report.pdf", &metadata));
+        assert!(ranker.relevance(1.0, "report.pdf", &metadata) > ranker.relevance(1.0, "a\\report.pdf", &metadata));
+
+        let today = ranker.relevance(1.0, "report.pdf", &record("report.pdf", 1_000 * DAY));
+        let last_month = ranker.relevance(1.0, "report.pdf", &record("report.pdf", 970 * DAY));
+        let unknown = ranker.relevance(1.0, "report.pdf", &record("report.pdf", 0));
+        assert!(today > last_month && last_month > unknown, "{today} > {last_month} > {unknown}");
+        // A month old file gets half of the recency weight.
+        assert!((today - last_month - 0.075).abs() < 1e-6);
+    }
+
+    #[test]
+    fn relevance_scales_with_the_match_score() {
+        let pattern_ranker = ranker(&[]);
+        let ranker = ranker(&["report"]);
+        let metadata = record("report.pdf", 0);
+        let full = ranker.relevance(1.0, "report.pdf", &metadata);
+        assert!((ranker.relevance(0.5, "report.pdf", &metadata) - full / 2.0).abs() < 1e-6);
+        // Patterns have no words to compare, so only the depth and recency count.
+        assert!((pattern_ranker.relevance(1.0, "report.pdf", &metadata) - 0.15).abs() < 1e-6);
+    }
+
+    fn result(full_path: &str, location: &str, size: u64, modified_time: u64, relevance: f32) -> DisplayResult {
+        let mut metadata = record(file_name(full_path), modified_time);
+        metadata.size = size;
+        DisplayResult {
+            full_path: Arc::from(full_path),
+            location: Arc::from(location),
+            icon: String::new(),
+            metadata: Some(metadata),
+            score: None,
+            relevance,
+        }
+    }
+
+    fn sorted_names(results: &[DisplayResult], sort_by: SortBy, descending: bool) -> Vec<String> {
+        let mut results = results.to_vec();
+        sort_results(&mut results, sort_by, descending);
+        results.iter().map(|result| file_name(&result.full_path).to_string()).collect()
+    }
+
+    #[test]
+    fn results_sort_by_every_order() {
+        let results = [
+            result("/b/alpha.txt", "Work", 30, 10, 0.2),
+            result("/a/Beta.txt", "archive", 10, 30, 0.9),
+            result("/c/x/gamma.txt", "Home", 20, 20, 0.5),
+        ];
+        for (sort_by, ascending) in [
+            (SortBy::Relevance, ["alpha.txt", "gamma.txt", "Beta.txt"]),
+            (SortBy::Name, ["alpha.txt", "Beta.txt", "gamma.txt"]),
+            (SortBy::Folder, ["Beta.txt", "alpha.txt", "gamma.txt"]),
+            (SortBy::Size, ["Beta.txt", "gamma.txt", "alpha.txt"]),
+            (SortBy::Modified, ["alpha.txt", "gamma.txt", "Beta.txt"]),
+            (SortBy::Location, ["Beta.txt", "gamma.txt", "alpha.txt"]),
+        ] {
+            assert_eq!(sorted_names(&results, sort_by, false), ascending, "{sort_by:?}");
+            let descending: Vec<&str> = ascending.into_iter().rev().collect();
+            assert_eq!(sorted_names(&results, sort_by, true), descending, "{sort_by:?} descending");
+        }
+    }
+
+    #[test]
+    fn merged_batches_keep_the_order() {
+        let mut results = Vec::new();
+        merge_results(&mut results, vec![result("/a/c.txt", "Work", 3, 0, 0.1), result("/a/a.txt", "Work", 1, 0, 0.1)], SortBy::Name, false);
+        merge_results(&mut results, vec![result("/a/d.txt", "Work", 4, 0, 0.1), result("/a/b.txt", "Work", 2, 0, 0.1)], SortBy::Name, false);
+        merge_results(&mut results, Vec::new(), SortBy::Name, false);
+        let names: Vec<&str> = results.iter().map(|result| file_name(&result.full_path)).collect();
+        assert_eq!(names, ["a.txt", "b.txt", "c.txt", "d.txt"]);
+
+        let batches = [
+            vec![result("/a/x.txt", "Work", 5, 0, 0.3), result("/a/y.txt", "Work", 9, 0, 0.1)],
+            vec![result("/a/z.txt", "Work", 5, 0, 0.2), result("/a/w.txt", "Work", 1, 0, 0.4)],
+        ];
+        let mut merged = Vec::new();
+        for batch in batches.clone() {
+            merge_results(&mut merged, batch, SortBy::Size, true);
+        }
+        let mut sorted = batches.concat();
+        sort_results(&mut sorted, SortBy::Size, true);
+        let paths = |results: &[DisplayResult]| results.iter().map(|result| result.full_path.to_string()).collect::<Vec<_>>();
+        assert_eq!(paths(&merged), paths(&sorted));
+    }
+
+    #[test]
+    fn sort_ties_fall_back_to_relevance_then_path() {
+        let results = [
+            result("/a/b.txt", "Work", 10, 10, 0.5),
+            result("/a/a.txt", "Work", 10, 10, 0.5),
+            result("/a/c.txt", "Work", 10, 10, 0.9),
+        ];
+        assert_eq!(sorted_names(&results, SortBy::Size, true), ["c.txt", "b.txt", "a.txt"]);
+        assert_eq!(sorted_names(&results, SortBy::Location, false), ["a.txt", "b.txt", "c.txt"]);
+    }
+}
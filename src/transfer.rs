@@ -0,0 +1,453 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::SystemTime;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use crate::db::{ContentSegment, DbManager, FileHash, FileMetadata, CONTENT_VERSION, RECORD_VERSION};
+use crate::gui::events::GuiSender;
+use crate::utils;
+
+/// Identifies location exports in the header line.
+const EXPORT_FORMAT: &str = "deepsearch-location";
+/// Layout of the export file. v1: a header line followed by one record per line.
+/// v2: records also carry the content segments and the hash of their file.
+const EXPORT_FORMAT_VERSION: u64 = 2;
+/// File extension of compressed JSON Lines exports.
+pub const EXPORT_EXTENSION: &str = "dsindex";
+
+const IMPORT_BATCH_SIZE: usize = 50_000;
+/// Records whose hashes and content segments are loaded together while exporting.
+const EXPORT_BATCH_SIZE: usize = 1024;
+/// Documents whose segments are written to the content index together while importing.
+const CONTENT_IMPORT_BATCH_SIZE: usize = 256;
+
+/// First line of an export: describes where the records come from and how they are encoded.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExportHeader {
+    pub format: String,
+    pub version: u64,
+    pub record_version: u64,
+    /// The root path the location had on the exporting machine.
+    pub root_path: String,
+    pub exported_at: u64,
+    pub entries: u64,
+    /// Whether the location had a content index; its records then carry their segments.
+    #[serde(default)]
+    pub has_content: bool,
+    /// Encoding of the segments, when `has_content` is set.
+    #[serde(default)]
+    pub content_version: u64,
+    /// The extensions extracted into the content index.
+    #[serde(default)]
+    pub content_extensions: Vec<String>,
+    /// Whether the location was hashed; its records then carry their hash when one was stored.
+    #[serde(default)]
+    pub has_hashes: bool,
+}
+
+/// One indexed file or directory, keyed by its path relative to the location root.
+#[derive(Serialize, Deserialize)]
+struct ExportRecord {
+    path: String,
+    #[serde(flatten)]
+    metadata: FileMetadata,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hash: Option<FileHash>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    segments: Vec<ContentSegment>,
+}
+
+/// A record of an export written with record version 1, kept for import.
+#[derive(Deserialize)]
+struct ExportRecordV1 {
+    path: String,
+    normalized_name: String,
+    modified_time: u64,
+}
+
+/// Parses one record line written with `record_version`, upgrading older records to the current
+/// layout.
+fn parse_record(line: &str, record_version: u64) -> serde_json::Result<ExportRecord> {
+    if record_version < 2 {
+        let old: ExportRecordV1 = serde_json::from_str(line)?;
+        let metadata = FileMetadata::from_v1(&to_local_separators(&old.path), old.normalized_name, old.modified_time);
+        return Ok(ExportRecord { path: old.path, metadata, hash: None, segments: Vec::new() });
+    }
+    serde_json::from_str(line)
+}
+
+/// Writes the index of `root_path` to `destination`: gzip-compressed JSON Lines for `.dsindex`
+/// files, including the content index and hashes, or a flat CSV table of the file metadata for
+/// `.csv` files. Returns the number of records written.
+pub fn export_location(db_manager: &DbManager, root_path: &str, destination: &Path) -> anyhow::Result<u64> {
+    let table_name = db_manager.get_table_name(root_path)?
+        .ok_or_else(|| anyhow::anyhow!("The location '{root_path}' is not indexed"))?;
+    let is_csv = destination.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+
+    let file = BufWriter::new(File::create(destination)?);
+    if is_csv {
+        export_csv(db_manager, root_path, &table_name, file)
+    } else {
+        export_jsonl(db_manager, root_path, &table_name, file)
+    }
+}
+
+fn export_jsonl(db_manager: &DbManager, root_path: &str, table_name: &str, file: BufWriter<File>) -> anyhow::Result<u64> {
+    let mut writer = GzEncoder::new(file, Compression::default());
+    // Undecodable rows are skipped below, so count the records that will actually be written.
+    let mut entries = 0;
+    db_manager.for_each_record(table_name, |_, _| {
+        entries += 1;
+        Ok(())
+    })?;
+    let has_content = db_manager.has_content_index(table_name)?;
+    let header = ExportHeader {
+        format: EXPORT_FORMAT.to_string(),
+        version: EXPORT_FORMAT_VERSION,
+        record_version: RECORD_VERSION,
+        root_path: root_path.to_string(),
+        exported_at: utils::system_time_to_secs(SystemTime::now()),
+        entries,
+        has_content,
+        content_version: if has_content { CONTENT_VERSION } else { 0 },
+        content_extensions: if has_content { db_manager.get_content_extensions(table_name)? } else { Vec::new() },
+        has_hashes: db_manager.has_hash_index(table_name)?,
+    };
+    serde_json::to_writer(&mut writer, &header)?;
+    writer.write_all(b"\n")?;
+
+    let mut count = 0;
+    let mut batch: Vec<(String, FileMetadata)> = Vec::with_capacity(EXPORT_BATCH_SIZE);
+    db_manager.for_each_record(table_name, |path, metadata| {
+        batch.push((path.to_string(), metadata));
+        if batch.len() >= EXPORT_BATCH_SIZE {
+            count += write_export_batch(db_manager, table_name, &header, &mut batch, &mut writer)?;
+        }
+        Ok(())
+    })?;
+    count += write_export_batch(db_manager, table_name, &header, &mut batch, &mut writer)?;
+    writer.finish()?.flush()?;
+    Ok(count)
+}
+
+/// Writes and empties a batch of records, together with their hashes and content segments.
+fn write_export_batch(
+    db_manager: &DbManager,
+    table_name: &str,
+    header: &ExportHeader,
+    batch: &mut Vec<(String, FileMetadata)>,
+    writer: &mut impl Write,
+) -> anyhow::Result<u64> {
+    let keys: Vec<&str> = batch.iter().map(|(path, _)| path.as_str()).collect();
+    let mut hashes = if header.has_hashes { db_manager.get_file_hashes(table_name, &keys)? } else { HashMap::new() };
+    let mut segments = if header.has_content { db_manager.get_content_segments(table_name, &keys)? } else { HashMap::new() };
+
+    let mut count = 0;
+    for (path, metadata) in batch.drain(..) {
+        let record = ExportRecord {
+            hash: hashes.remove(&path),
+            segments: segments.remove(&path).unwrap_or_default(),
+            path,
+            metadata,
+        };
+        serde_json::to_writer(&mut *writer, &record)?;
+        writer.write_all(b"\n")?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+fn export_csv(db_manager: &DbManager, root_path: &str, table_name: &str, mut writer: BufWriter<File>) -> anyhow::Result<u64> {
+    writeln!(writer, "path,full_path,type,extension,size,modified,created,read_only,hidden")?;
+    let mut count = 0;
+    db_manager.for_each_record(table_name, |path, metadata| {
+        let full_path = Path::new(root_path).join(path).to_string_lossy().to_string();
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{},{}",
+            csv_field(path),
+            csv_field(&full_path),
+            if metadata.is_dir { "directory" } else { "file" },
+            csv_field(&metadata.extension),
+            metadata.size,
+            utils::format_date(metadata.modified_time),
+            utils::format_date(metadata.created_time),
+            metadata.is_read_only,
+            metadata.is_hidden,
+        )?;
+        count += 1;
+        Ok(())
+    })?;
+    writer.flush()?;
+    Ok(count)
+}
+
+/// Quotes a CSV field when it contains a separator, a quote or a line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn open_export(source: &Path) -> anyhow::Result<impl BufRead> {
+    Ok(BufReader::new(GzDecoder::new(BufReader::new(File::open(source)?))))
+}
+
+fn parse_header(line: Option<std::io::Result<String>>) -> anyhow::Result<ExportHeader> {
+    let line = line.ok_or_else(|| anyhow::anyhow!("The file is empty"))??;
+    let header: ExportHeader = serde_json::from_str(&line)
+        .map_err(|_| anyhow::anyhow!("The file is not a DeepSearch index export"))?;
+    if header.format != EXPORT_FORMAT {
+        anyhow::bail!("The file is not a DeepSearch index export");
+    }
+    // Older records are upgraded while importing.
+    if header.version > EXPORT_FORMAT_VERSION || header.record_version > RECORD_VERSION || header.content_version > CONTENT_VERSION {
+        anyhow::bail!(
+            "The export uses format v{} with record v{} and content v{}, but this version of DeepSearch reads up to format v{EXPORT_FORMAT_VERSION} with record v{RECORD_VERSION} and content v{CONTENT_VERSION}",
+            header.version,
+            header.record_version,
+            header.content_version
+        );
+    }
+    Ok(header)
+}
+
+/// Reads only the header of an export, so the user can see where it comes from before importing.
+pub fn read_export_header(source: &Path) -> anyhow::Result<ExportHeader> {
+    parse_header(open_export(source)?.lines().next())
+}
+
+/// Checks that a record can belong to a location: a relative path that stays below the root and
+/// a normalized name that matches the file name. Paths may use either separator, since exports
+/// can come from another operating system.
+fn validate_record(record: &ExportRecord) -> Result<(), String> {
+    let segments: Vec<&str> = record.path.split(['/', '\\']).collect();
+    let is_relative = segments.iter().all(|segment| !segment.is_empty() && *segment != "." && *segment != "..")
+        && !segments[0].contains(':');
+    if !is_relative {
+        return Err(format!("'{}' is not a path relative to the location root", record.path));
+    }
+    let file_name = segments[segments.len() - 1];
+    if record.metadata.normalized_name != utils::normalize_string(file_name) {
+        return Err(format!("the name stored for '{}' does not match its path", record.path));
+    }
+    Ok(())
+}
+
+/// Rewrites the separators of an imported relative path for the current platform.
+fn to_local_separators(path: &str) -> String {
+    path.split(['/', '\\']).collect::<Vec<_>>().join(std::path::MAIN_SEPARATOR_STR)
+}
+
+/// Imports an export written by `export_location` and registers it under `root_path`.
+/// Every record is validated; nothing is registered unless the whole file is valid, and an
+/// existing location is only replaced when `overwrite` is set. Returns the number of records.
+pub fn import_location(
+    db_manager: &DbManager,
+    source: &Path,
+    root_path: &str,
+    overwrite: bool,
+    reporter: Option<&GuiSender>,
+) -> anyhow::Result<u64> {
+    if !overwrite && db_manager.get_table_name(root_path)?.is_some() {
+        anyhow::bail!("The location '{root_path}' is already indexed");
+    }
+
+    let mut lines = open_export(source)?.lines();
+    let header = parse_header(lines.next())?;
+
+    let table_name = format!("index_{:x}_{}",
+        md5::compute(root_path.as_bytes()),
+        SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?.as_secs()
+    );
+
+    match write_records(db_manager, &table_name, &header, lines, reporter) {
+        Ok(count) => {
+            db_manager.register_location_table(root_path, &table_name, overwrite)?;
+            Ok(count)
+        }
+        Err(e) => {
+            // Drop whatever was written so a rejected file leaves no orphan tables behind.
+            let txn = db_manager.db.begin_write()?;
+            DbManager::delete_index_tables(&txn, &table_name)?;
+            txn.commit()?;
+            Err(e)
+        }
+    }
+}
+
+fn write_records(
+    db_manager: &DbManager,
+    table_name: &str,
+    header: &ExportHeader,
+    lines: impl Iterator<Item = std::io::Result<String>>,
+    reporter: Option<&GuiSender>,
+) -> anyhow::Result<u64> {
+    let mut seen_paths = HashSet::new();
+    let mut batch: Vec<(String, FileMetadata)> = Vec::with_capacity(IMPORT_BATCH_SIZE);
+    let mut hashes: Vec<(String, FileHash)> = Vec::new();
+    let mut documents: Vec<(String, Vec<ContentSegment>)> = Vec::with_capacity(CONTENT_IMPORT_BATCH_SIZE);
+    let mut count: u64 = 0;
+
+    // The content and hash tables mark the location as content-indexed and hashed, so they are
+    // created even when no record carries segments or a hash.
+    if header.has_content {
+        db_manager.write_content(table_name, &[])?;
+        let extensions: Vec<&str> = header.content_extensions.iter().map(String::as_str).collect();
+        db_manager.set_content_extensions(table_name, &extensions)?;
+    }
+    if header.has_hashes {
+        db_manager.write_hashes(table_name, &[])?;
+    }
+
+    for (i, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line_number = i + 2; // The header is line 1.
+        let record = parse_record(&line, header.record_version)
+            .map_err(|e| anyhow::anyhow!("Invalid record on line {line_number}: {e}"))?;
+        validate_record(&record).map_err(|e| anyhow::anyhow!("Invalid record on line {line_number}: {e}"))?;
+        let path = to_local_separators(&record.path);
+        if !seen_paths.insert(path.clone()) {
+            anyhow::bail!("Invalid record on line {line_number}: '{}' appears more than once", record.path);
+        }
+
+        if let Some(hash) = record.hash.filter(|_| header.has_hashes) {
+            hashes.push((path.clone(), hash));
+        }
+        if header.has_content && !record.segments.is_empty() {
+            documents.push((path.clone(), record.segments));
+            if documents.len() >= CONTENT_IMPORT_BATCH_SIZE {
+                db_manager.write_content(table_name, &documents)?;
+                documents.clear();
+            }
+        }
+        batch.push((path, record.metadata));
+        count += 1;
+        if batch.len() >= IMPORT_BATCH_SIZE {
+            db_manager.write_to_table(table_name, &batch)?;
+            db_manager.write_hashes(table_name, &hashes)?;
+            batch.clear();
+            hashes.clear();
+            #[allow(clippy::cast_precision_loss)]
+            let progress = count as f32 / header.entries.max(1) as f32;
+            utils::report_progress(reporter, progress.min(1.0), &format!("📥 Imported {count} of {} records...", header.entries));
+        }
+    }
+    // Always write, even an empty batch, so the location table exists.
+    db_manager.write_to_table(table_name, &batch)?;
+    if !hashes.is_empty() {
+        db_manager.write_hashes(table_name, &hashes)?;
+    }
+    if !documents.is_empty() {
+        db_manager.write_content(table_name, &documents)?;
+    }
+
+    if count != header.entries {
+        anyhow::bail!("The file is incomplete: expected {} records, found {count}", header.entries);
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::SegmentLocation;
+    use crate::pop::control::TaskController;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("deepsearch_transfer_{}_{name}", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        path
+    }
+
+    fn record(name: &str) -> FileMetadata {
+        FileMetadata {
+            normalized_name: utils::normalize_string(name),
+            modified_time: 10,
+            size: 20,
+            created_time: 5,
+            is_dir: false,
+            extension: "txt".to_string(),
+            is_read_only: false,
+            is_hidden: false,
+        }
+    }
+
+    #[test]
+    fn export_round_trip_keeps_contents_and_hashes() {
+        let db_path = temp_path("round_trip.redb");
+        let export_path = temp_path("round_trip.dsindex");
+        let db_manager = DbManager::new(&db_path).unwrap();
+        let table_name = db_manager.get_or_create_table_name("/source").unwrap();
+        db_manager.write_to_table(&table_name, &[("a.txt".to_string(), record("a.txt")), ("b.txt".to_string(), record("b.txt"))]).unwrap();
+        let segment = ContentSegment { location: SegmentLocation::Line(3), text: "Quarterly budget".to_string() };
+        db_manager.write_content(&table_name, &[("a.txt".to_string(), vec![segment.clone()])]).unwrap();
+        db_manager.set_content_extensions(&table_name, &["txt"]).unwrap();
+        let hash = FileHash { size: 20, modified_time: 10, digest: [7; 16] };
+        db_manager.write_hashes(&table_name, &[("b.txt".to_string(), hash)]).unwrap();
+
+        assert_eq!(export_location(&db_manager, "/source", &export_path).unwrap(), 2);
+        assert_eq!(import_location(&db_manager, &export_path, "/copy", false, None).unwrap(), 2);
+
+        let imported = db_manager.get_table_name("/copy").unwrap().unwrap();
+        let mut hits = Vec::new();
+        let has_index = db_manager.search_content_in_table(&imported, "budget", 0, &TaskController::new(), |found| {
+            hits.extend(found);
+            Ok(())
+        }).unwrap();
+        assert!(has_index);
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].key.as_str(), &hits[0].segment), ("a.txt", &segment));
+        assert_eq!(db_manager.get_file_hashes(&imported, &["a.txt", "b.txt"]).unwrap(), HashMap::from([("b.txt".to_string(), hash)]));
+        assert_eq!(db_manager.get_content_extensions(&imported).unwrap(), ["txt"]);
+
+        drop(db_manager);
+        std::fs::remove_file(&db_path).ok();
+        std::fs::remove_file(&export_path).ok();
+    }
+
+    #[test]
+    fn record_v1_exports_are_upgraded() {
+        let db_path = temp_path("v1.redb");
+        let export_path = temp_path("v1.dsindex");
+        let mut writer = GzEncoder::new(File::create(&export_path).unwrap(), Compression::default());
+        writeln!(writer, r#"{{"format":"deepsearch-location","version":1,"record_version":1,"root_path":"/old","exported_at":0,"entries":1}}"#).unwrap();
+        writeln!(writer, r#"{{"path":"docs/.Notes.MD","normalized_name":"notesmd","modified_time":42}}"#).unwrap();
+        writer.finish().unwrap();
+
+        let db_manager = DbManager::new(&db_path).unwrap();
+        assert_eq!(import_location(&db_manager, &export_path, "/new", false, None).unwrap(), 1);
+        let table_name = db_manager.get_table_name("/new").unwrap().unwrap();
+        let mut records = Vec::new();
+        db_manager.for_each_record(&table_name, |path, metadata| {
+            records.push((path.to_string(), metadata));
+            Ok(())
+        }).unwrap();
+        let expected = FileMetadata {
+            normalized_name: "notesmd".to_string(),
+            modified_time: 42,
+            size: 0,
+            created_time: 0,
+            is_dir: false,
+            extension: "md".to_string(),
+            is_read_only: false,
+            is_hidden: true,
+        };
+        assert_eq!(records, [(to_local_separators("docs/.Notes.MD"), expected)]);
+        assert!(!db_manager.has_content_index(&table_name).unwrap());
+
+        drop(db_manager);
+        std::fs::remove_file(&db_path).ok();
+        std::fs::remove_file(&export_path).ok();
+    }
+}
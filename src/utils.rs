@@ -1,6 +1,6 @@
 
 use std::sync::LazyLock;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use unicode_normalization::char::is_combining_mark;
 use unicode_normalization::UnicodeNormalization;
 use crate::gui::events::{GuiUpdate, GuiSender};
@@ -9,6 +9,7 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use rayon::prelude::*;
 use walkdir::WalkDir;
 use std::sync::Arc;
+use std::time::SystemTime;
 use crate::pop::control::TaskController;
 
 
@@ -55,6 +56,36 @@ pub fn normalize_string(s: &str) -> String {
         .join(" ")
 }
 
+/// Normalizes a single character the way `normalize_string` does: accents removed and lowercased.
+/// Punctuation becomes an empty string; whitespace is left to the caller.
+pub fn normalize_char(c: char) -> String {
+    remove_vietnamese_accents(c.encode_utf8(&mut [0; 4]))
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Normalizes every segment of a path and joins them with spaces, so words can be matched across
+/// folder names regardless of the platform's separator.
+pub fn normalize_path(path: &str) -> String {
+    path.split(['/', '\\'])
+        .map(normalize_string)
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Returns the distinct character trigrams of an already-normalized string.
+/// Strings shorter than three characters have no trigrams.
+pub fn trigrams(s: &str) -> Vec<String> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut result: Vec<String> = chars.windows(3).map(|w| w.iter().collect()).collect();
+    result.sort_unstable();
+    result.dedup();
+    result
+}
+
 // --- Filesystem Scan Helpers ---
 
 pub fn report_progress(reporter: Option<&GuiSender>, progress: f32, message: &str) {
@@ -81,8 +112,16 @@ pub fn discover_fs_structure(root_path: &Path, reporter: Option<&GuiSender>) ->
     (top_level_entries, subdirs)
 }
 
+/// Files and directories are indexed; other entry types (symbolic links, devices...) are skipped.
+/// The type comes from the directory listing, so this costs no extra `stat` and links are not followed.
+fn is_indexable(entry: &walkdir::DirEntry) -> bool {
+    let file_type = entry.file_type();
+    file_type.is_file() || file_type.is_dir()
+}
+
 /// A new, reusable helper that performs a controllable 2-phase scan.
 /// It encapsulates the logic of discovering, iterating, and checking the controller state.
+/// The action is called for every file and directory below `root_path`.
 pub fn controlled_two_phase_scan<F>(
     root_path: &Path,
     reporter: Option<&GuiSender>,
@@ -91,12 +130,30 @@ pub fn controlled_two_phase_scan<F>(
 )
 where
     F: Fn(walkdir::DirEntry) + Send + Sync,
+{
+    resumable_two_phase_scan(root_path, reporter, controller, &HashSet::new(), action, |_| {});
+}
+
+/// Same as `controlled_two_phase_scan`, but skips the contents of the top-level subdirectories
+/// named in `completed_subdirs` and calls `on_subdir_completed` with the name of every
+/// top-level subdirectory that was walked to the end without being cancelled.
+pub fn resumable_two_phase_scan<F, C>(
+    root_path: &Path,
+    reporter: Option<&GuiSender>,
+    controller: &Arc<TaskController>,
+    completed_subdirs: &HashSet<String>,
+    action: F,
+    on_subdir_completed: C,
+)
+where
+    F: Fn(walkdir::DirEntry) + Send + Sync,
+    C: Fn(String) + Send + Sync,
 {
     let (top_level_entries, subdirs) = discover_fs_structure(root_path, reporter);
 
-    // --- Phase 1: Process top-level files ---
-    let top_level_files: Vec<_> = top_level_entries.into_iter().filter(|e| e.path().is_file()).collect();
-    top_level_files.into_par_iter().for_each(|entry| {
+    // --- Phase 1: Process top-level files and directories ---
+    let top_level_items: Vec<_> = top_level_entries.into_iter().filter(is_indexable).collect();
+    top_level_items.into_par_iter().for_each(|entry| {
         if controller.is_cancelled() { return; }
         controller.check_and_wait_if_paused();
         if controller.is_cancelled() { return; }
@@ -106,6 +163,8 @@ where
     if controller.is_cancelled() { return; }
 
     // --- Phase 2: Process subdirectories ---
+    let subdir_name = |subdir: &Path| subdir.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let subdirs: Vec<_> = subdirs.into_iter().filter(|subdir| !completed_subdirs.contains(&subdir_name(subdir))).collect();
     let num_subdirs = subdirs.len();
     let processed_subdirs = AtomicUsize::new(0);
     report_progress(reporter, 0.05, "Phase 2/2: Scanning files...");
@@ -113,9 +172,10 @@ where
     subdirs.into_par_iter().for_each(|subdir| {
         if controller.is_cancelled() { return; }
 
-        let walker = WalkDir::new(&subdir).into_iter().filter_map(Result::ok);
+        // The subdirectory itself was already handled in phase 1.
+        let walker = WalkDir::new(&subdir).min_depth(1).into_iter().filter_map(Result::ok);
         for entry in walker {
-            if entry.path().is_file() {
+            if is_indexable(&entry) {
                 if controller.is_cancelled() { break; } // Break from inner loop
                 controller.check_and_wait_if_paused();
                 if controller.is_cancelled() { break; }
@@ -123,6 +183,10 @@ where
             }
         }
 
+        if !controller.is_cancelled() {
+            on_subdir_completed(subdir_name(&subdir));
+        }
+
         let processed_count = processed_subdirs.fetch_add(1, Ordering::SeqCst);
         if num_subdirs > 0 {
             #[allow(clippy::cast_precision_loss)]
@@ -132,28 +196,88 @@ where
     });
 }
 
+/// Computes the MD5 digest of a file's content, streaming it from disk.
+/// Returns `None` if the file cannot be read.
+pub fn hash_file(path: &Path) -> Option<[u8; 16]> {
+    let mut file = std::fs::File::open(path).ok()?;
+    let mut context = md5::Context::new();
+    std::io::copy(&mut file, &mut context).ok()?;
+    Some(context.finalize().0)
+}
+
+// --- Display Helpers ---
 
+/// Converts a `SystemTime` into whole seconds since the Unix epoch (0 for earlier times).
+pub fn system_time_to_secs(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
 
-/// Checks if a target string contains all of the provided tokens.
-pub fn contains_all_tokens(target: &str, tokens: &[&str]) -> bool {
-    if tokens.is_empty() {
-        return true; // Or false, depending on desired behavior for empty query
+/// Formats seconds since the Unix epoch as a `YYYY-MM-DD` UTC date.
+pub fn format_date(secs: u64) -> String {
+    if secs == 0 {
+        return "-".to_string();
+    }
+    let (year, month, day) = civil_from_days(secs / 86_400);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Converts a day count since 1970-01-01 into a (year, month, day) civil date.
+#[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+fn civil_from_days(days: u64) -> (i64, u64, u64) {
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097) as u64;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe as i64 + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Converts a (year, month, day) civil date into a day count since 1970-01-01.
+/// The inverse of `civil_from_days`; `month` and `day` are 1-based.
+#[allow(clippy::cast_possible_wrap)]
+pub fn days_from_civil(year: i64, month: u64, day: u64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy as i64;
+    era * 146_097 + doe - 719_468
+}
+
+/// Formats a byte count using binary units (e.g. `1.5 MB`).
+#[allow(clippy::cast_precision_loss)]
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
     }
-    tokens.iter().all(|token| target.contains(token))
 }
 
-// Helper to get an icon based on file extension
-pub fn get_icon_for_path(path: &str) -> &'static str {
-    let path_buf = PathBuf::from(path);
-    if path_buf.is_dir() {
+// Helper to get an icon based on file extension.
+// `is_dir` comes from the walked entry or the indexed metadata, so no filesystem access is needed.
+pub fn get_icon_for_path(path: &str, is_dir: bool) -> &'static str {
+    if is_dir {
         return "📁"; // Folder icon
     }
-    match path_buf.extension().and_then(|s| s.to_str()) {
+    match Path::new(path).extension().and_then(|s| s.to_str()) {
         Some("txt" | "md" | "log") => "📄", // Text file
         Some("pdf") => "📃", // PDF
-        Some("doc" | "docx") => "📝", // Word document
-        Some("xls" | "xlsx" | "csv") => "📊", // Spreadsheet
-        Some("ppt" | "pptx") => " presentation", // Presentation
+        Some("doc" | "docx" | "odt") => "📝", // Word document
+        Some("xls" | "xlsx" | "xlsb" | "ods" | "csv") => "📊", // Spreadsheet
+        Some("ppt" | "pptx" | "odp") => " presentation", // Presentation
         Some("zip" | "rar" | "7z" | "tar" | "gz") => "📦", // Archive
         Some("jpg" | "jpeg" | "png" | "gif" | "bmp" | "svg") => "🖼️", // Image
         Some("mp3" | "wav" | "flac" | "ogg") => "🎵", // Audio